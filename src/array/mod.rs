@@ -28,7 +28,6 @@
 // TODO look at R arrays and other data types
 
 // heap sort, merge sort, quick sort
-pub struct BigIntegral {}
 
 pub fn convolve(a: &[f64], b: &[f64]) -> Box<[f64]> {
     unimplemented!()
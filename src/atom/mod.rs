@@ -5,10 +5,157 @@
 // By using this software in any fashion, you are agreeing to be bound by the terms of this license.
 // You must not remove this notice, or any other, from this software.
 
-use handle::Handle;
+//! A reference to a changing value.
+//!
+//! An atom holds one value at a time. Readers deref to get the current value,
+//! writers reset it, or swap in f of the current value. Swap reads, computes
+//! and then compare-and-sets, retrying when another writer got there first,
+//! so f may run more than once and should be free of side effects.
+//!
+//! Compare-and-set compares by identity, the same word, not by equality.
+//! The cell itself is a short critical section, so a reader never sees
+//! a value retired out from under it. Sharing an atom between threads
+//! requires the atomic anchor build, without `anchor_non_atomic`.
+
+use std::sync::{Arc, Mutex};
+use value::Value;
+
+pub type Validator = Arc<dyn Fn(&Value) -> bool + Send + Sync>;
+/// Called with the watch key, old and new values, after each change.
+pub type Watch = Arc<dyn Fn(&Value, &Value, &Value) + Send + Sync>;
 
 pub struct Atom {
-    pub handle: Handle,
+    state: Mutex<Value>,
+    validator: Mutex<Option<Validator>>,
+    watches: Mutex<Vec<(Value, Watch)>>,
+}
+
+impl Atom {
+    pub fn new(v: Value) -> Atom {
+        Atom { state: Mutex::new(v), validator: Mutex::new(None), watches: Mutex::new(vec![]) }
+    }
+
+    pub fn deref(&self) -> Value { self.state.lock().unwrap().split_out() }
+
+    /// Sets the value to new if the current value is old, by identity.
+    pub fn compare_and_set(&self, old: &Value, new: Value) -> bool {
+        self.validate(&new);
+        let prev = {
+            let mut state = self.state.lock().unwrap();
+            if state._handle().unit != old._handle().unit {
+                return false
+            }
+            std::mem::replace(&mut *state, new.split_out())
+        };
+        self.notify(&prev, &new);
+        true
+    }
+
+    /// Sets the value, returning it.
+    pub fn reset(&self, v: Value) -> Value {
+        self.validate(&v);
+        let prev = {
+            let mut state = self.state.lock().unwrap();
+            std::mem::replace(&mut *state, v.split_out())
+        };
+        self.notify(&prev, &v);
+        v
+    }
+
+    /// Sets the value to f of the current value, returning the new value.
+    pub fn swap(&self, f: impl Fn(&Value) -> Value) -> Value {
+        loop {
+            let old = self.deref();
+            let new = f(&old);
+            if self.compare_and_set(&old, new.split_out()) {
+                return new
+            }
+        }
+    }
+
+    /// Checks every value before it is set. Panics if the current value is invalid.
+    pub fn set_validator(&self, f: impl Fn(&Value) -> bool + Send + Sync + 'static) {
+        let f: Validator = Arc::new(f);
+        if !f(&self.deref()) {
+            panic!("Invalid reference state: {}", self.deref());
+        }
+        *self.validator.lock().unwrap() = Some(f);
+    }
+
+    pub fn add_watch(&self, key: Value, f: impl Fn(&Value, &Value, &Value) + Send + Sync + 'static) {
+        let mut watches = self.watches.lock().unwrap();
+        watches.retain(|w| w.0 != key);
+        watches.push((key, Arc::new(f)));
+    }
+
+    pub fn remove_watch(&self, key: &Value) {
+        self.watches.lock().unwrap().retain(|w| w.0 != *key);
+    }
+
+    fn validate(&self, v: &Value) {
+        let f = self.validator.lock().unwrap().clone();
+        if let Some(f) = f {
+            if !f(v) {
+                panic!("Invalid reference state: {}", v);
+            }
+        }
+    }
+
+    fn notify(&self, old: &Value, new: &Value) {
+        // Called outside the lock, so a watch may use the atom.
+        let watches: Vec<(Value, Watch)> = self.watches.lock().unwrap().iter()
+            .map(|w| (w.0.split_out(), w.1.clone())).collect();
+        for (key, f) in watches {
+            f(&key, old, new);
+        }
+    }
 }
 
-// deref
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(not(feature = "anchor_non_atomic"))]
+    use std::thread;
+
+    // Sharing values across threads needs atomic alias counts.
+    #[cfg(not(feature = "anchor_non_atomic"))]
+    #[test]
+    fn swaps() {
+        let a = Arc::new(Atom::new(0.into()));
+        let workers: Vec<_> = (0..4).map(|_| {
+            let a = a.clone();
+            thread::spawn(move || {
+                for _ in 0..1000 {
+                    a.swap(|x| x.split_out().inc());
+                }
+            })
+        }).collect();
+        for w in workers {
+            w.join().unwrap();
+        }
+        assert_eq!(a.deref(), 4000.into());
+    }
+
+    #[test]
+    fn watches_and_validators() {
+        let a = Atom::new(1.into());
+        let seen = Arc::new(Mutex::new(vec![]));
+        let s = seen.clone();
+        a.add_watch(":w".parse().unwrap(), move |_k, old, new| {
+            s.lock().unwrap().push((old.as_i64(), new.as_i64()));
+        });
+        a.set_validator(|x| x.as_i64() < 10);
+        a.reset(5.into());
+        a.swap(|x| Value::from(x.as_i64() * 2 - 1));
+        let stale: Value = 5.into();
+        assert!(!a.compare_and_set(&stale, 3.into()));
+        let current = a.deref();
+        assert!(a.compare_and_set(&current, 3.into()));
+        a.remove_watch(&":w".parse().unwrap());
+        a.reset(2.into());
+        assert_eq!(*seen.lock().unwrap(), vec![(1, 5), (5, 9), (9, 3)]);
+        let r = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| a.reset(10.into())));
+        assert!(r.is_err());
+        assert_eq!(a.deref(), 2.into());
+    }
+}
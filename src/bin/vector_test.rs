@@ -9,6 +9,7 @@ extern crate fress;
 
 use fress::Value;
 use fress::memory::{schedule, segment};
+#[cfg(not(feature = "anchor_non_atomic"))]
 use fress::vector::harness::{self, Op, Ops};
 
 use std::panic;
@@ -157,7 +158,8 @@ hash(\l) => EF71637F
     let ops = Ops(vec![Op::New(288)], vec![Op::Conj(7)], vec![Op::Conj(9)]);
     let ops1 = Ops(vec![Op::New(7)], vec![Op::Conj(7)], vec![Op::Conj(9)]);
     let ops2 = Ops(vec![Op::New(17)], vec![Op::Conj(7)], vec![Op::Conj(9)]);
-    harness::explore_schedules(ops3, 3);
+    let record = harness::explore_schedules(ops3, 3);
+    std::fs::write("table.csv", harness::table(&record)).unwrap();
     */
     /*
     let r = panic::catch_unwind(|| {
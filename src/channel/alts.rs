@@ -0,0 +1,157 @@
+// Copyright (c) Cole Frederick. All rights reserved.
+// The use and distribution terms for this software are covered by the
+// Eclipse Public License 1.0 (https://opensource.org/licenses/eclipse-1.0.php)
+// which can be found in the file epl-v10.html at the root of this distribution.
+// By using this software in any fashion, you are agreeing to be bound by the terms of this license.
+// You must not remove this notice, or any other, from this software.
+
+//! Select over several channel operations, completing exactly one.
+//!
+//! One waiter is offered to each channel in turn. The first operation that
+//! can complete commits the waiter, and every registration left behind in
+//! other channels is then stale, skipped by whoever finds it.
+
+use value::Value;
+use random::RngQuick;
+use super::{Attempt, Chan, Outcome, Waiter};
+
+pub enum AltOp {
+    Take(Chan),
+    Put(Chan, Value),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Alt {
+    /// Index of the take op, and the value taken, None if its channel closed
+    Took(usize, Option<Value>),
+    /// Index of the put op, and whether the value was put
+    Put(usize, bool),
+}
+
+fn attempt(op: &AltOp, w: &::std::sync::Arc<Waiter>, idx: usize, register: bool) -> Option<Alt> {
+    match *op {
+        AltOp::Take(ref c) => match c.take_attempt(w, idx, register) {
+            Attempt::Done(v) => Some(Alt::Took(idx, v)),
+            _ => None,
+        },
+        AltOp::Put(ref c, ref v) => match c.put_attempt(w, idx, v.split_out(), register) {
+            Attempt::Done(ok) => Some(Alt::Put(idx, ok)),
+            _ => None,
+        },
+    }
+}
+
+// With priority, ops are tried in order. Otherwise from a random start, so
+// no channel is starved.
+fn order(n: usize, priority: bool, w: &Waiter) -> impl Iterator<Item=usize> {
+    let start = if priority || n == 0 { 0 } else {
+        RngQuick::new(w.id).next_between(0, n as u32) as usize
+    };
+    (0..n).map(move |i| (start + i) % n)
+}
+
+/// Waits until one of ops completes, and returns which and how.
+pub fn alts(ops: &[AltOp], priority: bool) -> Alt {
+    assert!(!ops.is_empty(), "alts needs at least one op.");
+    let w = Waiter::new();
+    for i in order(ops.len(), priority, &w) {
+        if let Some(alt) = attempt(&ops[i], &w, i, true) {
+            return alt
+        }
+        if w.is_done() {
+            break
+        }
+    }
+    match w.wait() {
+        Outcome::Took(i, v) => Alt::Took(i, v),
+        Outcome::Put(i, ok) => Alt::Put(i, ok),
+        Outcome::Claimed => unreachable!(),
+    }
+}
+
+/// Completes one of ops if any can complete now, without waiting.
+pub fn alts_now(ops: &[AltOp], priority: bool) -> Option<Alt> {
+    let w = Waiter::new();
+    for i in order(ops.len(), priority, &w) {
+        if let Some(alt) = attempt(&ops[i], &w, i, false) {
+            return Some(alt)
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    use std::time::{Duration, Instant};
+    use channel::timeout::timeout;
+    use channel::buffer::Buffer;
+
+    #[test]
+    fn selects_one() {
+        let (a, b) = (Chan::new(), Chan::with_buffer(Buffer::fixed(1)));
+        let ops = [AltOp::Take(a.clone()), AltOp::Take(b.clone())];
+        assert_eq!(alts_now(&ops, true), None);
+        assert!(b.put(2.into()));
+        assert_eq!(alts(&ops, true), Alt::Took(1, Some(2.into())));
+
+        let a2 = a.clone();
+        let putter = thread::spawn(move || a2.put(1.into()));
+        assert_eq!(alts(&ops, false), Alt::Took(0, Some(1.into())));
+        assert!(putter.join().unwrap());
+
+        let puts = [AltOp::Put(a.clone(), 3.into()), AltOp::Put(b.clone(), 4.into())];
+        assert_eq!(alts(&puts, true), Alt::Put(1, true));
+        assert_eq!(alts_now(&puts, true), None);
+        assert_eq!(b.take(), Some(4.into()));
+
+        // Many selects racing on both ends, each value taken exactly once
+        let c = Chan::new();
+        let takers: Vec<_> = (0..4).map(|_| {
+            let (c, a) = (c.clone(), a.clone());
+            thread::spawn(move || {
+                let mut sum = 0;
+                loop {
+                    match alts(&[AltOp::Take(c.clone()), AltOp::Take(a.clone())], false) {
+                        Alt::Took(_, Some(v)) => sum += v.as_i64(),
+                        _ => return sum,
+                    }
+                }
+            })
+        }).collect();
+        for i in 1..=1000 {
+            let ops = [AltOp::Put(c.clone(), Value::from(i)), AltOp::Put(a.clone(), Value::from(i))];
+            assert!(match alts(&ops, false) { Alt::Put(_, ok) => ok, _ => false });
+        }
+        a.close();
+        let total: i64 = takers.into_iter().map(|t| t.join().unwrap()).sum();
+        assert_eq!(total, 500500);
+    }
+
+    #[test]
+    fn own_ops() {
+        // Putting and taking on one channel, the select waits for another party.
+        let c = Chan::new();
+        let d = c.clone();
+        let taker = thread::spawn(move || d.take());
+        assert_eq!(alts(&[AltOp::Put(c.clone(), 1.into()), AltOp::Take(c.clone())], true), Alt::Put(0, true));
+        assert_eq!(taker.join().unwrap(), Some(1.into()));
+        let ops = [AltOp::Take(c.clone()), AltOp::Take(timeout(10))];
+        for _ in 0..3 {
+            assert_eq!(alts(&ops, true), Alt::Took(1, None));
+        }
+        assert!(c.inner.state.lock().unwrap().takes.len() <= 1);
+    }
+
+    #[test]
+    fn timeouts() {
+        let start = Instant::now();
+        let work = Chan::new();
+        let ops = [AltOp::Take(work.clone()), AltOp::Take(timeout(30))];
+        assert_eq!(alts(&ops, false), Alt::Took(1, None));
+        assert!(start.elapsed() >= Duration::from_millis(30));
+        let t = timeout(10);
+        assert_eq!(t.take(), None);
+    }
+}
@@ -0,0 +1,67 @@
+// Copyright (c) Cole Frederick. All rights reserved.
+// The use and distribution terms for this software are covered by the
+// Eclipse Public License 1.0 (https://opensource.org/licenses/eclipse-1.0.php)
+// which can be found in the file epl-v10.html at the root of this distribution.
+// By using this software in any fashion, you are agreeing to be bound by the terms of this license.
+// You must not remove this notice, or any other, from this software.
+
+use std::collections::VecDeque;
+use value::Value;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Policy {
+    /// Holds up to n values, then puts wait
+    Fixed,
+    /// Holds up to n values, then drops new ones
+    Dropping,
+    /// Holds the latest n values, dropping the oldest
+    Sliding,
+    /// Holds the first value, given to every take
+    Promise,
+}
+
+pub struct Buffer {
+    pub policy: Policy,
+    pub n: usize,
+    pub items: VecDeque<Value>,
+}
+
+impl Buffer {
+    pub fn fixed(n: usize) -> Buffer { Buffer::new(Policy::Fixed, n) }
+    pub fn dropping(n: usize) -> Buffer { Buffer::new(Policy::Dropping, n) }
+    pub fn sliding(n: usize) -> Buffer { Buffer::new(Policy::Sliding, n) }
+    pub fn promise() -> Buffer { Buffer::new(Policy::Promise, 1) }
+    fn new(policy: Policy, n: usize) -> Buffer {
+        assert!(n != 0, "Buffer size must be positive.");
+        Buffer { policy, n, items: VecDeque::new() }
+    }
+
+    pub fn is_empty(&self) -> bool { self.items.is_empty() }
+    pub fn count(&self) -> usize { self.items.len() }
+    /// Only fixed buffers refuse values, the others drop them.
+    pub fn is_full(&self) -> bool { self.policy == Policy::Fixed && self.items.len() >= self.n }
+
+    /// Adds v by the policy. A fixed buffer may go past n, as a transducer
+    /// can make several values from one put.
+    pub fn add(&mut self, v: Value) {
+        match self.policy {
+            Policy::Fixed => { self.items.push_back(v); },
+            Policy::Dropping => { if self.items.len() < self.n { self.items.push_back(v); } },
+            Policy::Sliding => {
+                if self.items.len() == self.n {
+                    self.items.pop_front();
+                }
+                self.items.push_back(v);
+            },
+            Policy::Promise => { if self.items.is_empty() { self.items.push_back(v); } },
+        }
+    }
+
+    pub fn remove(&mut self) -> Option<Value> {
+        if self.policy == Policy::Promise {
+            self.items.front().map(|v| v.split_out())
+        } else {
+            self.items.pop_front()
+        }
+    }
+}
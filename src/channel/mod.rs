@@ -5,3 +5,416 @@
 // By using this software in any fashion, you are agreeing to be bound by the terms of this license.
 // You must not remove this notice, or any other, from this software.
 
+//! Channels, carrying values between threads.
+//!
+//! A put hands a value to a take. Without a buffer, the two meet: whichever
+//! comes first waits for the other. A buffer lets puts complete while it has
+//! room, see `buffer::Policy`. A transducer on a buffered channel runs on each
+//! put, adding its results to the buffer.
+//!
+//! A closed channel refuses puts. Takes drain the buffer and any waiting
+//! puts, then get None. Nil can't be put, it is the closed signal in `lib`.
+//!
+//! Every pending operation waits on a waiter. A waiter completes once: the
+//! first operation to commit it wins, later ones see it done and move on.
+//! That lets a waiter sit in several channels at once, as alts needs.
+//!
+//! Channels are built only without `anchor_non_atomic`, as values cross threads.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::sync::atomic::{AtomicU64, Ordering};
+use value::Value;
+use transduce::{self, entry, Process, Transducers};
+
+pub mod buffer;
+use self::buffer::Buffer;
+pub mod alts;
+pub mod timeout;
+pub mod pool;
+pub mod ops;
+
+pub enum Outcome {
+    /// Completed by the waiting thread itself
+    Claimed,
+    Took(usize, Option<Value>),
+    Put(usize, bool),
+}
+
+pub struct Waiter {
+    id: u64,
+    outcome: Mutex<Option<Outcome>>,
+    ready: Condvar,
+}
+
+static NEXT_WAITER: AtomicU64 = AtomicU64::new(0);
+
+impl Waiter {
+    pub fn new() -> Arc<Waiter> {
+        Arc::new(Waiter { id: NEXT_WAITER.fetch_add(1, Ordering::Relaxed),
+            outcome: Mutex::new(None), ready: Condvar::new() })
+    }
+    pub fn is_done(&self) -> bool { self.outcome.lock().unwrap().is_some() }
+    /// Completes the waiter, unless it is done already.
+    pub fn commit(&self, o: Outcome) -> bool {
+        let mut g = self.outcome.lock().unwrap();
+        if g.is_some() {
+            return false
+        }
+        *g = Some(o);
+        self.ready.notify_all();
+        true
+    }
+    pub fn wait(&self) -> Outcome {
+        let mut g = self.outcome.lock().unwrap();
+        while g.is_none() {
+            g = self.ready.wait(g).unwrap();
+        }
+        let o = g.take().unwrap();
+        *g = Some(Outcome::Claimed);
+        o
+    }
+}
+
+type Slot<'a> = MutexGuard<'a, Option<Outcome>>;
+// Locks both in id order, so two threads pairing the same waiters can't deadlock.
+fn lock_pair<'a>(a: &'a Waiter, b: &'a Waiter) -> (Slot<'a>, Slot<'a>) {
+    if a.id < b.id {
+        let ga = a.outcome.lock().unwrap();
+        (ga, b.outcome.lock().unwrap())
+    } else {
+        let gb = b.outcome.lock().unwrap();
+        (a.outcome.lock().unwrap(), gb)
+    }
+}
+
+pub enum Attempt<T> {
+    Done(T),
+    /// Registered to complete later, or not possible now when not registering
+    Pending,
+    /// The waiter was completed elsewhere
+    Lost,
+}
+
+struct Taker { waiter: Arc<Waiter>, idx: usize }
+struct Putter { waiter: Arc<Waiter>, idx: usize, v: Value }
+
+struct Add { buf: *mut Buffer }
+impl Process for Add {
+    fn ingest   (&mut self, _stack: &mut [Box<dyn Process>], v:  Value)            -> Option<Value> {
+        unsafe { (*self.buf).add(v); }
+        None
+    }
+    fn ingest_kv(&mut self, stack: &mut [Box<dyn Process>], k:  Value, v:  Value) -> Option<Value> {
+        self.ingest(stack, entry(k, v))
+    }
+    fn last_call(&mut self, _stack: &mut [Box<dyn Process>]) -> Value { Value::nil() }
+}
+
+// Processes come from Send + Sync transducers, and run only under the channel lock.
+struct Xform { stack: Vec<Box<dyn Process>> }
+unsafe impl Send for Xform { }
+
+struct State {
+    buf: Option<Buffer>,
+    xform: Option<Xform>,
+    takes: VecDeque<Taker>,
+    puts: VecDeque<Putter>,
+    closed: bool,
+}
+
+impl State {
+    // Adds to the buffer, through the transducer if any. Closes when it stops early.
+    fn add(&mut self, v: Value) {
+        let stop = match self.xform {
+            Some(ref mut xf) => transduce::ingest(&mut xf.stack, v).is_some(),
+            None => { self.buf.as_mut().unwrap().add(v); false },
+        };
+        if stop {
+            self.close();
+        }
+    }
+
+    // Hands buffered values to waiting takes.
+    fn deliver(&mut self) {
+        loop {
+            let has_value = self.buf.as_ref().map_or(false, |b| !b.is_empty());
+            if !has_value || self.takes.is_empty() {
+                return
+            }
+            let t = self.takes.pop_front().unwrap();
+            let mut g = t.waiter.outcome.lock().unwrap();
+            if g.is_none() {
+                *g = Some(Outcome::Took(t.idx, self.buf.as_mut().unwrap().remove()));
+                t.waiter.ready.notify_all();
+            }
+        }
+    }
+
+    // Moves waiting puts into the buffer while it has room.
+    fn refill(&mut self) {
+        while !self.closed && !self.buf.as_ref().unwrap().is_full() {
+            let p = match self.puts.pop_front() {
+                Some(p) => p,
+                None => return,
+            };
+            if p.waiter.commit(Outcome::Put(p.idx, true)) {
+                self.add(p.v);
+            }
+        }
+    }
+
+    fn close(&mut self) {
+        if self.closed {
+            return
+        }
+        self.closed = true;
+        if let Some(ref mut xf) = self.xform {
+            let _ = transduce::last_call(&mut xf.stack);
+        }
+        self.deliver();
+        for t in self.takes.drain(..) {
+            t.waiter.commit(Outcome::Took(t.idx, None));
+        }
+    }
+
+    fn take(&mut self, w: &Arc<Waiter>, idx: usize, register: bool) -> Attempt<Option<Value>> {
+        if self.buf.as_ref().map_or(false, |b| !b.is_empty()) {
+            if !w.commit(Outcome::Claimed) {
+                return Attempt::Lost
+            }
+            let v = self.buf.as_mut().unwrap().remove();
+            self.refill();
+            self.deliver();
+            return Attempt::Done(v)
+        }
+        let mut i = 0;
+        while i < self.puts.len() {
+            // An alts putting and taking on this channel can't meet itself.
+            if Arc::ptr_eq(&self.puts[i].waiter, w) {
+                i += 1;
+                continue
+            }
+            let p = self.puts.remove(i).unwrap();
+            let (mut gw, mut gp) = lock_pair(w, &p.waiter);
+            if gw.is_some() {
+                drop((gw, gp));
+                self.puts.insert(i, p);
+                return Attempt::Lost
+            }
+            if gp.is_some() {
+                continue
+            }
+            *gw = Some(Outcome::Claimed);
+            *gp = Some(Outcome::Put(p.idx, true));
+            p.waiter.ready.notify_all();
+            drop((gw, gp));
+            return Attempt::Done(Some(p.v))
+        }
+        if self.closed {
+            return if w.commit(Outcome::Claimed) { Attempt::Done(None) } else { Attempt::Lost }
+        }
+        if register {
+            self.takes.retain(|t| !t.waiter.is_done());
+            self.takes.push_back(Taker { waiter: w.clone(), idx });
+        }
+        Attempt::Pending
+    }
+
+    fn put(&mut self, w: &Arc<Waiter>, idx: usize, v: Value, register: bool) -> Attempt<bool> {
+        if v.is_nil() {
+            panic!("Can't put nil on a channel.");
+        }
+        if self.closed {
+            return if w.commit(Outcome::Claimed) { Attempt::Done(false) } else { Attempt::Lost }
+        }
+        if self.buf.as_ref().map_or(false, |b| !b.is_full()) {
+            if !w.commit(Outcome::Claimed) {
+                return Attempt::Lost
+            }
+            self.add(v);
+            self.deliver();
+            return Attempt::Done(true)
+        }
+        let mut i = 0;
+        while i < self.takes.len() {
+            if Arc::ptr_eq(&self.takes[i].waiter, w) {
+                i += 1;
+                continue
+            }
+            let t = self.takes.remove(i).unwrap();
+            let (mut gw, mut gt) = lock_pair(w, &t.waiter);
+            if gw.is_some() {
+                drop((gw, gt));
+                self.takes.insert(i, t);
+                return Attempt::Lost
+            }
+            if gt.is_some() {
+                continue
+            }
+            *gw = Some(Outcome::Claimed);
+            *gt = Some(Outcome::Took(t.idx, Some(v)));
+            t.waiter.ready.notify_all();
+            return Attempt::Done(true)
+        }
+        if register {
+            self.puts.retain(|p| !p.waiter.is_done());
+            self.puts.push_back(Putter { waiter: w.clone(), idx, v });
+        }
+        Attempt::Pending
+    }
+}
+
+struct Inner {
+    state: Mutex<State>,
+}
+
+/// A channel. Clones share the channel.
+#[derive(Clone)]
+pub struct Chan {
+    inner: Arc<Inner>,
+}
+
+impl Chan {
+    /// An unbuffered channel, where puts and takes meet.
+    pub fn new() -> Chan { Chan::make(None, None) }
+    pub fn with_buffer(buf: Buffer) -> Chan { Chan::make(Some(buf), None) }
+    /// A buffered channel running xf on each put.
+    pub fn with_xform<X: Into<Transducers>>(buf: Buffer, xf: X) -> Chan {
+        Chan::make(Some(buf), Some(xf.into()))
+    }
+    /// Takes all get the first value put, once there is one.
+    pub fn promise() -> Chan { Chan::make(Some(Buffer::promise()), None) }
+
+    fn make(buf: Option<Buffer>, xf: Option<Transducers>) -> Chan {
+        let c = Chan { inner: Arc::new(Inner { state: Mutex::new(State {
+            buf, xform: None, takes: VecDeque::new(), puts: VecDeque::new(), closed: false }) }) };
+        if let Some(xf) = xf {
+            let mut state = c.inner.state.lock().unwrap();
+            // The state sits still in its Arc, so the buffer's address holds.
+            let buf = state.buf.as_mut().unwrap() as *mut Buffer;
+            let base: Box<dyn Process> = Box::new(Add { buf });
+            state.xform = Some(Xform { stack: xf.apply(vec![base]) });
+        }
+        c
+    }
+
+    pub fn take_attempt(&self, w: &Arc<Waiter>, idx: usize, register: bool) -> Attempt<Option<Value>> {
+        self.inner.state.lock().unwrap().take(w, idx, register)
+    }
+    pub fn put_attempt(&self, w: &Arc<Waiter>, idx: usize, v: Value, register: bool) -> Attempt<bool> {
+        self.inner.state.lock().unwrap().put(w, idx, v, register)
+    }
+
+    /// Waits for a value, or None once the channel is closed and drained.
+    pub fn take(&self) -> Option<Value> {
+        let w = Waiter::new();
+        match self.take_attempt(&w, 0, true) {
+            Attempt::Done(v) => v,
+            _ => match w.wait() {
+                Outcome::Took(_, v) => v,
+                _ => unreachable!(),
+            },
+        }
+    }
+    /// Waits until v is taken or buffered. False if the channel is closed.
+    pub fn put(&self, v: Value) -> bool {
+        let w = Waiter::new();
+        match self.put_attempt(&w, 0, v, true) {
+            Attempt::Done(ok) => ok,
+            _ => match w.wait() {
+                Outcome::Put(_, ok) => ok,
+                _ => unreachable!(),
+            },
+        }
+    }
+    /// Takes a value only if one is ready now.
+    pub fn poll(&self) -> Option<Value> {
+        match self.take_attempt(&Waiter::new(), 0, false) {
+            Attempt::Done(v) => v,
+            _ => None,
+        }
+    }
+    /// Puts v only if it can complete now.
+    pub fn offer(&self, v: Value) -> bool {
+        match self.put_attempt(&Waiter::new(), 0, v, false) {
+            Attempt::Done(ok) => ok,
+            _ => false,
+        }
+    }
+    pub fn close(&self) { self.inner.state.lock().unwrap().close() }
+    pub fn is_closed(&self) -> bool { self.inner.state.lock().unwrap().closed }
+}
+
+impl PartialEq for Chan {
+    fn eq(&self, other: &Chan) -> bool { Arc::ptr_eq(&self.inner, &other.inner) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    use transduce::{map, partition};
+
+    #[test]
+    fn rendezvous() {
+        let c = Chan::new();
+        assert!(!c.offer(1.into()));
+        assert_eq!(c.poll(), None);
+        let d = c.clone();
+        let producer = thread::spawn(move || {
+            for i in 0..100 {
+                assert!(d.put(i.into()));
+            }
+            d.close();
+        });
+        let mut sum = 0;
+        while let Some(v) = c.take() {
+            sum += v.as_i64();
+        }
+        producer.join().unwrap();
+        assert_eq!(sum, 4950);
+        assert!(!c.put(1.into()));
+    }
+
+    #[test]
+    fn buffers() {
+        let f = Chan::with_buffer(Buffer::fixed(2));
+        assert!(f.offer(1.into()) && f.offer(2.into()));
+        assert!(!f.offer(3.into()));
+        let g = f.clone();
+        let waiting = thread::spawn(move || g.put(3.into()));
+        assert_eq!(f.take(), Some(1.into()));
+        assert!(waiting.join().unwrap());
+        f.close();
+        assert_eq!((f.take(), f.take(), f.take()), (Some(2.into()), Some(3.into()), None));
+
+        let d = Chan::with_buffer(Buffer::dropping(2));
+        let s = Chan::with_buffer(Buffer::sliding(2));
+        for i in 0..5 {
+            assert!(d.put(i.into()) && s.put(i.into()));
+        }
+        assert_eq!((d.poll(), d.poll(), d.poll()), (Some(0.into()), Some(1.into()), None));
+        assert_eq!((s.poll(), s.poll(), s.poll()), (Some(3.into()), Some(4.into()), None));
+
+        let p = Chan::promise();
+        let q = p.clone();
+        let taker = thread::spawn(move || q.take());
+        assert!(p.put(7.into()) && p.put(8.into()));
+        assert_eq!(taker.join().unwrap(), Some(7.into()));
+        p.close();
+        assert_eq!((p.take(), p.poll()), (Some(7.into()), Some(7.into())));
+    }
+
+    #[test]
+    fn transducers() {
+        let xf = Transducers::from(vec![map(|x: &Value| x.split_out().inc()), partition(2)]);
+        let c = Chan::with_xform(Buffer::fixed(4), xf);
+        for i in 0..3 {
+            c.put(i.into());
+        }
+        c.close();
+        let got: Vec<String> = ::std::iter::from_fn(|| c.take()).map(|v| v.to_string()).collect();
+        assert_eq!(got, vec!["[1 2]", "[3]"]);
+    }
+}
@@ -0,0 +1,292 @@
+// Copyright (c) Cole Frederick. All rights reserved.
+// The use and distribution terms for this software are covered by the
+// Eclipse Public License 1.0 (https://opensource.org/licenses/eclipse-1.0.php)
+// which can be found in the file epl-v10.html at the root of this distribution.
+// By using this software in any fashion, you are agreeing to be bound by the terms of this license.
+// You must not remove this notice, or any other, from this software.
+
+//! Fan-out and fan-in of channels.
+//!
+//! Each combinator runs as jobs on the worker pool, taking from its sources
+//! until they close. Where a close flag is given, the destinations are closed
+//! after the sources are.
+
+use std::collections::HashMap;
+use std::sync::{mpsc, Arc, Mutex};
+use value::Value;
+use transduce::{Fun, Transducers};
+use vector;
+use super::Chan;
+use super::alts::{alts, Alt, AltOp};
+use super::pool;
+
+/// Moves values from one channel to another. Stops when either closes.
+pub fn pipe(from: Chan, to: Chan, close: bool) -> Chan {
+    let out = to.clone();
+    pool::spawn(move || {
+        while let Some(v) = from.take() {
+            if !to.put(v) {
+                return
+            }
+        }
+        if close {
+            to.close();
+        }
+    });
+    out
+}
+
+/// Runs xf on each value from one channel with n workers, putting the results
+/// to the other in order. The transducer runs on each value by itself.
+pub fn pipeline<X: Into<Transducers>>(n: usize, to: Chan, xf: X, from: Chan, close: bool) -> Chan {
+    assert!(n != 0, "A pipeline needs at least one worker.");
+    let xf = xf.into();
+    let out = to.clone();
+    let (jobs_in, jobs_out) = mpsc::channel::<(Value, mpsc::Sender<Vec<Value>>)>();
+    let jobs_out = Arc::new(Mutex::new(jobs_out));
+    for _ in 0..n {
+        let (jobs_out, xf) = (jobs_out.clone(), xf.clone());
+        pool::spawn(move || {
+            loop {
+                let job = jobs_out.lock().unwrap().recv();
+                let (v, result) = match job {
+                    Ok(job) => job,
+                    Err(_) => return,
+                };
+                let rs = vector::new_value().conj(v).pour(xf.clone(), vector::new_value());
+                let _ = result.send(rs.iter().map(|r| r.split_out()).collect());
+            }
+        });
+    }
+    // Results wait in order; the bound keeps n values in flight.
+    let (order_in, order_out) = mpsc::sync_channel::<mpsc::Receiver<Vec<Value>>>(n);
+    pool::spawn(move || {
+        while let Some(v) = from.take() {
+            let (result, r) = mpsc::channel();
+            if order_in.send(r).is_err() {
+                return
+            }
+            jobs_in.send((v, result)).unwrap();
+        }
+    });
+    pool::spawn(move || {
+        for r in order_out {
+            for v in r.recv().unwrap_or(vec![]) {
+                if !to.put(v) {
+                    return
+                }
+            }
+        }
+        if close {
+            to.close();
+        }
+    });
+    out
+}
+
+/// Copies each value from a source channel to every tap.
+/// Values are put to the taps one after another, so a slow tap holds up the rest.
+/// A tap found closed by a put is removed.
+#[derive(Clone)]
+pub struct Mult {
+    taps: Arc<Mutex<Vec<(Chan, bool)>>>,
+}
+
+impl Mult {
+    pub fn new(source: Chan) -> Mult {
+        let m = Mult { taps: Arc::new(Mutex::new(vec![])) };
+        let taps = m.taps.clone();
+        pool::spawn(move || {
+            while let Some(v) = source.take() {
+                let current = taps.lock().unwrap().clone();
+                let closed: Vec<Chan> = current.into_iter()
+                    .filter_map(|(c, _)| if c.put(v.split_out()) { None } else { Some(c) })
+                    .collect();
+                if !closed.is_empty() {
+                    taps.lock().unwrap().retain(|t| !closed.contains(&t.0));
+                }
+            }
+            for (c, close) in taps.lock().unwrap().drain(..) {
+                if close {
+                    c.close();
+                }
+            }
+        });
+        m
+    }
+    pub fn tap(&self, c: Chan, close: bool) -> Chan {
+        self.taps.lock().unwrap().push((c.clone(), close));
+        c
+    }
+    pub fn untap(&self, c: &Chan) { self.taps.lock().unwrap().retain(|t| t.0 != *c); }
+    pub fn untap_all(&self) { self.taps.lock().unwrap().clear(); }
+}
+
+/// Sorts values from a source channel by topic, copying each to the
+/// subscribers of its topic. Values of a topic with no subscribers are dropped.
+#[derive(Clone)]
+pub struct Pub {
+    topics: Arc<Mutex<HashMap<Value, (Chan, Mult)>>>,
+}
+
+impl Pub {
+    pub fn new(source: Chan, topic_fn: impl Fn(&Value) -> Value + Send + Sync + 'static) -> Pub {
+        let topic_fn: Fun = Arc::new(topic_fn);
+        let p = Pub { topics: Arc::new(Mutex::new(HashMap::new())) };
+        let topics = p.topics.clone();
+        pool::spawn(move || {
+            while let Some(v) = source.take() {
+                let topic = topic_fn(&v);
+                let c = topics.lock().unwrap().get(&topic).map(|t| t.0.clone());
+                if let Some(c) = c {
+                    c.put(v);
+                }
+            }
+            for (_, (c, _)) in topics.lock().unwrap().drain() {
+                c.close();
+            }
+        });
+        p
+    }
+    pub fn sub(&self, topic: Value, c: Chan, close: bool) -> Chan {
+        let mut topics = self.topics.lock().unwrap();
+        let t = topics.entry(topic).or_insert_with(|| {
+            let c = Chan::new();
+            (c.clone(), Mult::new(c))
+        });
+        t.1.tap(c, close)
+    }
+    pub fn unsub(&self, topic: &Value, c: &Chan) {
+        if let Some(t) = self.topics.lock().unwrap().get(topic) {
+            t.1.untap(c);
+        }
+    }
+    /// Detaches every subscriber, leaving their channels open.
+    pub fn unsub_all(&self) {
+        for (_, (c, m)) in self.topics.lock().unwrap().drain() {
+            m.untap_all();
+            c.close();
+        }
+    }
+}
+
+/// One channel with the values of all of chans, closed once they all close.
+pub fn merge(chans: Vec<Chan>) -> Chan {
+    let out = Chan::new();
+    let to = out.clone();
+    pool::spawn(move || {
+        let mut ops: Vec<AltOp> = chans.into_iter().map(AltOp::Take).collect();
+        while !ops.is_empty() {
+            match alts(&ops, false) {
+                Alt::Took(_, Some(v)) => {
+                    if !to.put(v) {
+                        return
+                    }
+                },
+                Alt::Took(i, None) => { ops.remove(i); },
+                Alt::Put(..) => unreachable!(),
+            }
+        }
+        to.close();
+    });
+    out
+}
+
+/// Two channels, of the values from source that pass pred and those that don't.
+pub fn split(pred: impl Fn(&Value) -> bool + Send + 'static, source: Chan) -> (Chan, Chan) {
+    let (yes, no) = (Chan::new(), Chan::new());
+    let (y, n) = (yes.clone(), no.clone());
+    pool::spawn(move || {
+        while let Some(v) = source.take() {
+            if pred(&v) { y.put(v); } else { n.put(v); }
+        }
+        y.close();
+        n.close();
+    });
+    (yes, no)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use channel::buffer::Buffer;
+    use transduce::{filter, mapcat};
+
+    fn onto(c: &Chan, xs: ::std::ops::Range<i64>) {
+        for i in xs {
+            c.put(i.into());
+        }
+        c.close();
+    }
+    fn drain(c: &Chan) -> Vec<i64> {
+        ::std::iter::from_fn(|| c.take()).map(|v| v.as_i64()).collect()
+    }
+
+    #[test]
+    fn fan_in_and_out() {
+        let (a, b) = (Chan::new(), Chan::new());
+        let out = pipe(a.clone(), b.clone(), true);
+        pool::spawn({ let a = a.clone(); move || onto(&a, 0..5) });
+        assert_eq!(drain(&out), vec![0, 1, 2, 3, 4]);
+
+        let src = Chan::new();
+        let xf = Transducers::from(vec![filter(|x: &Value| x.as_i64() % 3 != 0),
+                                        mapcat(|x: &Value| vector::new_value().conj(x.split_out()).conj(x.split_out()))]);
+        let out = pipeline(4, Chan::with_buffer(Buffer::fixed(8)), xf, src.clone(), true);
+        pool::spawn({ let src = src.clone(); move || onto(&src, 0..8) });
+        assert_eq!(drain(&out), vec![1, 1, 2, 2, 4, 4, 5, 5, 7, 7]);
+
+        let src = Chan::new();
+        let m = Mult::new(src.clone());
+        let (t1, t2) = (m.tap(Chan::with_buffer(Buffer::fixed(10)), true),
+                        m.tap(Chan::with_buffer(Buffer::fixed(10)), true));
+        onto(&src, 0..3);
+        assert_eq!((drain(&t1), drain(&t2)), (vec![0, 1, 2], vec![0, 1, 2]));
+
+        let src = Chan::new();
+        let m = Mult::new(src.clone());
+        let (t1, t2) = (m.tap(Chan::with_buffer(Buffer::fixed(10)), true),
+                        m.tap(Chan::with_buffer(Buffer::fixed(10)), true));
+        t1.close();
+        src.put(0.into());
+        src.put(1.into());
+        assert_eq!(m.taps.lock().unwrap().len(), 1);
+        onto(&src, 2..3);
+        assert_eq!(drain(&t2), vec![0, 1, 2]);
+
+        let src = Chan::new();
+        let p = Pub::new(src.clone(), |x: &Value| Value::from(x.as_i64() % 2 == 0));
+        let evens = p.sub(Value::tru(), Chan::with_buffer(Buffer::fixed(10)), true);
+        let odds = p.sub(Value::fals(), Chan::with_buffer(Buffer::fixed(10)), true);
+        onto(&src, 0..6);
+        assert_eq!((drain(&evens), drain(&odds)), (vec![0, 2, 4], vec![1, 3, 5]));
+
+        let src = Chan::new();
+        let p = Pub::new(src.clone(), |x: &Value| Value::from(x.as_i64() % 2 == 0));
+        let evens = p.sub(Value::tru(), Chan::with_buffer(Buffer::fixed(10)), true);
+        src.put(0.into());
+        assert_eq!(evens.take().map(|v| v.as_i64()), Some(0));
+        p.unsub_all();
+        onto(&src, 2..4);
+        ::std::thread::sleep(::std::time::Duration::from_millis(50));
+        assert!(!evens.is_closed() && evens.poll().is_none());
+        assert!(evens.offer(8.into()) && evens.take().map(|v| v.as_i64()) == Some(8));
+
+        let cs: Vec<Chan> = (0..3).map(|_| Chan::new()).collect();
+        let all = merge(cs.clone());
+        for (i, c) in cs.into_iter().enumerate() {
+            pool::spawn(move || onto(&c, (i as i64 * 10)..(i as i64 * 10 + 3)));
+        }
+        let mut got = drain(&all);
+        got.sort();
+        assert_eq!(got, vec![0, 1, 2, 10, 11, 12, 20, 21, 22]);
+
+        let src = Chan::new();
+        let (small, big) = split(|x: &Value| x.as_i64() < 3, src.clone());
+        let (s, b) = (small.clone(), big.clone());
+        let smalls = ::std::thread::spawn(move || drain(&s));
+        let bigs = ::std::thread::spawn(move || drain(&b));
+        onto(&src, 0..6);
+        assert_eq!((smalls.join().unwrap(), bigs.join().unwrap()), (vec![0, 1, 2], vec![3, 4, 5]));
+    }
+}
@@ -0,0 +1,66 @@
+// Copyright (c) Cole Frederick. All rights reserved.
+// The use and distribution terms for this software are covered by the
+// Eclipse Public License 1.0 (https://opensource.org/licenses/eclipse-1.0.php)
+// which can be found in the file epl-v10.html at the root of this distribution.
+// By using this software in any fashion, you are agreeing to be bound by the terms of this license.
+// You must not remove this notice, or any other, from this software.
+
+//! Worker threads for channel jobs.
+//!
+//! Jobs block on channels, so a fixed pool could fill with jobs waiting on
+//! each other. Instead an idle worker takes the job if there is one, else a
+//! new worker starts. Workers idle for a while exit, keeping the pool small.
+
+use std::collections::VecDeque;
+use std::sync::{Condvar, Mutex, OnceLock};
+use std::thread;
+use std::time::Duration;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+struct Pool {
+    queue: Mutex<(VecDeque<Job>, usize /*idle*/)>,
+    ready: Condvar,
+}
+
+const IDLE_EXIT: Duration = Duration::from_secs(10);
+
+fn pool() -> &'static Pool {
+    static POOL: OnceLock<Pool> = OnceLock::new();
+    POOL.get_or_init(|| Pool { queue: Mutex::new((VecDeque::new(), 0)), ready: Condvar::new() })
+}
+
+fn work(p: &'static Pool, first: Job) {
+    first();
+    let mut q = p.queue.lock().unwrap();
+    loop {
+        if let Some(job) = q.0.pop_front() {
+            drop(q);
+            job();
+            q = p.queue.lock().unwrap();
+            continue
+        }
+        q.1 += 1;
+        let (g, t) = p.ready.wait_timeout(q, IDLE_EXIT).unwrap();
+        q = g;
+        q.1 -= 1;
+        if t.timed_out() && q.0.is_empty() {
+            return
+        }
+    }
+}
+
+/// Runs job on a worker thread.
+pub fn spawn(job: impl FnOnce() + Send + 'static) {
+    let p = pool();
+    let mut q = p.queue.lock().unwrap();
+    if q.1 > q.0.len() {
+        q.0.push_back(Box::new(job));
+        p.ready.notify_one();
+    } else {
+        drop(q);
+        let first: Job = Box::new(job);
+        thread::Builder::new().name("fress-worker".to_string())
+            .spawn(move || work(p, first)).unwrap();
+    }
+}
@@ -0,0 +1,71 @@
+// Copyright (c) Cole Frederick. All rights reserved.
+// The use and distribution terms for this software are covered by the
+// Eclipse Public License 1.0 (https://opensource.org/licenses/eclipse-1.0.php)
+// which can be found in the file epl-v10.html at the root of this distribution.
+// By using this software in any fashion, you are agreeing to be bound by the terms of this license.
+// You must not remove this notice, or any other, from this software.
+
+//! Channels that close after a delay.
+//!
+//! One timer thread, started on first use, sleeps until the earliest
+//! deadline and closes the channels that are due.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::sync::{Condvar, Mutex, Once, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+use super::Chan;
+
+struct Due { at: Instant, seq: u64, c: Chan }
+impl PartialEq for Due { fn eq(&self, o: &Due) -> bool { (self.at, self.seq) == (o.at, o.seq) } }
+impl Eq for Due { }
+impl PartialOrd for Due { fn partial_cmp(&self, o: &Due) -> Option<::std::cmp::Ordering> { Some(self.cmp(o)) } }
+impl Ord for Due { fn cmp(&self, o: &Due) -> ::std::cmp::Ordering { (self.at, self.seq).cmp(&(o.at, o.seq)) } }
+
+struct Timer {
+    queue: Mutex<(BinaryHeap<Reverse<Due>>, u64)>,
+    changed: Condvar,
+}
+
+fn timer() -> &'static Timer {
+    static TIMER: OnceLock<Timer> = OnceLock::new();
+    static START: Once = Once::new();
+    let t = TIMER.get_or_init(|| Timer { queue: Mutex::new((BinaryHeap::new(), 0)), changed: Condvar::new() });
+    START.call_once(|| {
+        thread::Builder::new().name("fress-timer".to_string()).spawn(move || run(t)).unwrap();
+    });
+    t
+}
+
+fn run(t: &Timer) {
+    let mut q = t.queue.lock().unwrap();
+    loop {
+        let now = Instant::now();
+        let wait = match q.0.peek() {
+            Some(&Reverse(ref d)) if d.at <= now => {
+                let Reverse(d) = q.0.pop().unwrap();
+                d.c.close();
+                continue
+            },
+            Some(&Reverse(ref d)) => Some(d.at - now),
+            None => None,
+        };
+        q = match wait {
+            Some(dur) => t.changed.wait_timeout(q, dur).unwrap().0,
+            None => t.changed.wait(q).unwrap(),
+        };
+    }
+}
+
+/// A channel that closes after ms milliseconds.
+pub fn timeout(ms: u64) -> Chan {
+    let c = Chan::new();
+    let t = timer();
+    let mut q = t.queue.lock().unwrap();
+    let seq = q.1;
+    q.1 += 1;
+    q.0.push(Reverse(Due { at: Instant::now() + Duration::from_millis(ms), seq, c: c.clone() }));
+    t.changed.notify_one();
+    c
+}
@@ -0,0 +1,466 @@
+// Copyright (c) Cole Frederick. All rights reserved.
+// The use and distribution terms for this software are covered by the
+// Eclipse Public License 1.0 (https://opensource.org/licenses/eclipse-1.0.php)
+// which can be found in the file epl-v10.html at the root of this distribution.
+// By using this software in any fashion, you are agreeing to be bound by the terms of this license.
+// You must not remove this notice, or any other, from this software.
+
+//! Arbitrary precision decimals, an unscaled integral and a scale, worth
+//! unscaled × 10^-scale. Read and printed with an M suffix, like 1.50M.
+//!
+//! Add, subtract and multiply are exact. Division and rounding take a
+//! Context, a count of significant digits (zero for unlimited) and a
+//! rounding mode, like java's MathContext. Decimals that differ only in
+//! trailing zeros, like 1.5M and 1.50M, are equal and hash alike.
+
+use std::fmt;
+use std::io;
+use std::cmp::Ordering;
+use memory::*;
+use dispatch::*;
+use value::*;
+use handle::Handle;
+use integral::{self, big::BigIntegral};
+use fressian;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Rounding { Up, Down, Ceiling, Floor, HalfUp, HalfDown, HalfEven, Unnecessary }
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Context {
+    pub precision: u32,
+    pub rounding: Rounding,
+}
+
+impl Context {
+    pub const UNLIMITED: Context = Context { precision: 0, rounding: Rounding::HalfUp };
+    pub const DECIMAL64: Context = Context { precision: 16, rounding: Rounding::HalfEven };
+    pub const DECIMAL128: Context = Context { precision: 34, rounding: Rounding::HalfEven };
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BigDecimal {
+    pub unscaled: BigIntegral,
+    pub scale: i32,
+}
+
+fn ten() -> BigIntegral { BigIntegral::from_i64(10) }
+
+/// The most digits a decimal is shifted by to align it with another.
+pub const MAX_SCALE_GAP: i64 = 1_000_000;
+
+// A scale within i32, else a panic.
+fn scale_of(x: i64) -> i32 {
+    if x < i32::min_value() as i64 || x > i32::max_value() as i64 {
+        panic!("Decimal scale {} is out of 32 bit bounds.", x);
+    }
+    x as i32
+}
+
+impl BigDecimal {
+    pub fn new(unscaled: BigIntegral, scale: i32) -> BigDecimal { BigDecimal { unscaled, scale } }
+
+    /// The unscaled value at a scale at least as large as ours,
+    /// at most `MAX_SCALE_GAP` larger.
+    pub fn rescaled(&self, scale: i32) -> BigIntegral {
+        let gap = scale as i64 - self.scale as i64;
+        if gap > MAX_SCALE_GAP {
+            panic!("Decimal scales {} and {} are too far apart to align.", self.scale, scale);
+        }
+        self.unscaled.mul(&BigIntegral::pow10(gap as u32))
+    }
+
+    pub fn add(&self, other: &BigDecimal) -> BigDecimal {
+        let scale = self.scale.max(other.scale);
+        BigDecimal::new(self.rescaled(scale).add(&other.rescaled(scale)), scale)
+    }
+    pub fn sub(&self, other: &BigDecimal) -> BigDecimal { self.add(&other.neg()) }
+    pub fn mul(&self, other: &BigDecimal) -> BigDecimal {
+        BigDecimal::new(self.unscaled.mul(&other.unscaled), scale_of(self.scale as i64 + other.scale as i64))
+    }
+    pub fn neg(&self) -> BigDecimal { BigDecimal::new(self.unscaled.neg(), self.scale) }
+
+    /// Drops trailing zeros, down to the given scale.
+    pub fn strip_to(&self, scale: i32) -> BigDecimal {
+        let mut d = self.clone();
+        while d.scale > scale {
+            let (q, r) = d.unscaled.divmod(&ten());
+            if !r.is_zero() || q.is_zero() {
+                break
+            }
+            d = BigDecimal::new(q, d.scale - 1);
+        }
+        d
+    }
+    pub fn stripped(&self) -> BigDecimal {
+        if self.unscaled.is_zero() {
+            return BigDecimal::new(BigIntegral::zero(), 0)
+        }
+        self.strip_to(i32::min_value())
+    }
+
+    // Drops digits, rounding by mode. Sticky tells of nonzero digits
+    // already dropped past the ones dropped here.
+    fn round_off(&self, drop: u32, mode: Rounding, sticky: bool) -> Result<BigDecimal, String> {
+        let unit = BigIntegral::pow10(drop);
+        let (q, r) = self.unscaled.divmod(&unit);
+        let scale = self.scale - drop as i32;
+        if r.is_zero() && !sticky {
+            return Ok(BigDecimal::new(q, scale))
+        }
+        let negative = self.unscaled.negative;
+        let half = match r.abs().mul(&BigIntegral::from_i64(2)).cmp(&unit) {
+            Ordering::Equal if sticky => Ordering::Greater,
+            c => c,
+        };
+        let away = match mode {
+            Rounding::Up => true,
+            Rounding::Down => false,
+            Rounding::Ceiling => !negative,
+            Rounding::Floor => negative,
+            Rounding::HalfUp => half != Ordering::Less,
+            Rounding::HalfDown => half == Ordering::Greater,
+            Rounding::HalfEven => half == Ordering::Greater ||
+                (half == Ordering::Equal && q.mag.first().map_or(false, |x| x & 1 == 1)),
+            Rounding::Unnecessary => {
+                return Err(format!("Rounding {} to fewer digits isn't exact.", self))
+            },
+        };
+        let q = if away { q.add(&BigIntegral::from_i64(if negative { -1 } else { 1 })) } else { q };
+        Ok(BigDecimal::new(q, scale))
+    }
+
+    fn round_sticky(&self, ctx: &Context, sticky: bool) -> Result<BigDecimal, String> {
+        let p = ctx.precision;
+        let digits = self.unscaled.digits();
+        if p == 0 || (digits <= p && !sticky) {
+            return Ok(self.clone())
+        }
+        let d = self.round_off(digits.saturating_sub(p), ctx.rounding, sticky)?;
+        // Rounding up can carry into a new digit, as 999.9 to 1000.
+        if d.unscaled.digits() > p {
+            return d.round_off(1, ctx.rounding, false)
+        }
+        Ok(d)
+    }
+    /// Rounds to the precision of the context.
+    pub fn round(&self, ctx: &Context) -> Result<BigDecimal, String> { self.round_sticky(ctx, false) }
+
+    /// The quotient, rounded to the precision of the context. With unlimited
+    /// precision, a quotient without a finite decimal expansion is an error.
+    pub fn div(&self, other: &BigDecimal, ctx: &Context) -> Result<BigDecimal, String> {
+        let (x, y) = (&self.unscaled, &other.unscaled);
+        if y.is_zero() {
+            return Err(format!("Divide by zero, {} / {}.", self, other))
+        }
+        let preferred = scale_of(self.scale as i64 - other.scale as i64);
+        if x.is_zero() {
+            return Ok(BigDecimal::new(BigIntegral::zero(), preferred))
+        }
+        if ctx.precision == 0 {
+            // Exact when the reduced divisor has no factors but 2 and 5.
+            let mut d = y.divmod(&x.gcd(y)).0.abs();
+            let mut shift = [0u32; 2];
+            for (i, f) in [2i64, 5].iter().enumerate() {
+                let f = BigIntegral::from_i64(*f);
+                loop {
+                    let (q, r) = d.divmod(&f);
+                    if !r.is_zero() { break }
+                    d = q;
+                    shift[i] += 1;
+                }
+            }
+            if d != BigIntegral::from_i64(1) {
+                return Err(format!("Non-terminating decimal expansion of {} / {}, \
+                                    divide with a precision.", self, other))
+            }
+            let k = shift[0].max(shift[1]);
+            let q = x.mul(&BigIntegral::pow10(k)).divmod(y).0;
+            return Ok(BigDecimal::new(q, scale_of(preferred as i64 + k as i64)).strip_to(preferred))
+        }
+        // Enough digits in the quotient for rounding to the precision.
+        let k = (ctx.precision as i64 + 1 + y.digits() as i64 - x.digits() as i64).max(0) as u32;
+        let (q, r) = x.mul(&BigIntegral::pow10(k)).divmod(y);
+        let d = BigDecimal::new(q, scale_of(preferred as i64 + k as i64)).round_sticky(ctx, !r.is_zero())?;
+        Ok(d.strip_to(preferred))
+    }
+
+    /// Parses digits on either side of a decimal point, and an exponent.
+    /// An error when the scale doesn't fit in 32 bits.
+    pub fn parse(negate: bool, whole: &[u8], part: &[u8], exp: i64) -> Result<BigDecimal, String> {
+        let digits: Vec<u8> = whole.iter().chain(part.iter()).cloned().collect();
+        let unscaled = BigIntegral::from_radix(negate, &digits, 10).unwrap();
+        let part_digits = part.iter().filter(|&&b| b != b'_').count() as i64;
+        let scale = part_digits - exp;
+        if scale < i32::min_value() as i64 || scale > i32::max_value() as i64 {
+            return Err(format!("Decimal exponent {} is out of bounds.", exp))
+        }
+        Ok(BigDecimal::new(unscaled, scale as i32))
+    }
+}
+
+impl PartialOrd for BigDecimal {
+    fn partial_cmp(&self, other: &BigDecimal) -> Option<Ordering> {
+        let (x, y) = (&self.unscaled, &other.unscaled);
+        let zero = BigIntegral::zero();
+        let (sx, sy) = (x.cmp(&zero), y.cmp(&zero));
+        if sx != sy || sx == Ordering::Equal {
+            return Some(sx.cmp(&sy))
+        }
+        // The place of the leading digit orders magnitudes. When it ties,
+        // the scales differ by no more than the digits at hand.
+        let lead = |d: &BigDecimal| d.unscaled.digits() as i64 - d.scale as i64;
+        let by_lead = lead(self).cmp(&lead(other));
+        if by_lead != Ordering::Equal {
+            return Some(if sx == Ordering::Less { by_lead.reverse() } else { by_lead })
+        }
+        let scale = self.scale.max(other.scale);
+        let up = |d: &BigDecimal| d.unscaled.mul(&BigIntegral::pow10((scale as i64 - d.scale as i64) as u32));
+        Some(up(self).cmp(&up(other)))
+    }
+}
+
+impl fmt::Display for BigDecimal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.scale < 0 {
+            return write!(f, "{}E+{}", self.unscaled, -(self.scale as i64))
+        }
+        let digits = format!("{}", self.unscaled.abs());
+        let scale = self.scale as usize;
+        if self.unscaled.negative {
+            write!(f, "-")?;
+        }
+        if scale == 0 {
+            return write!(f, "{}", digits)
+        }
+        let padded = format!("{:0>width$}", digits, width = scale + 1);
+        let point = padded.len() - scale;
+        write!(f, "{}.{}", &padded[..point], &padded[point..])
+    }
+}
+
+// Layout: [prism unscaled scale], unscaled is an integral and scale an i32
+pub struct BigDecimal_ { }
+pub fn prism_unit() -> Unit { mechanism::prism::<BigDecimal_>() }
+pub fn is_prism(prism: AnchoredLine) -> bool { prism[0] == prism_unit() }
+pub fn find_prism(h: Handle) -> Option<AnchoredLine> { h.find_prism(prism_unit()) }
+pub fn is_decimal(h: Handle) -> bool { find_prism(h).is_some() }
+
+pub fn new(d: &BigDecimal) -> Handle {
+    let s = Segment::new(3 /*prism unscaled scale*/);
+    s.set(0, prism_unit());
+    s.set(1, integral::demoted(&d.unscaled));
+    s.set(2, Unit::from(d.scale));
+    s.unit().handle()
+}
+pub fn new_value(d: &BigDecimal) -> Value { new(d).value() }
+
+pub fn decimal(prism: AnchoredLine) -> BigDecimal {
+    let unscaled = integral::to_big(integral::find_prism(prism[1].handle()).unwrap());
+    BigDecimal::new(unscaled, prism[2].u32() as i32)
+}
+
+pub fn parse(negate: bool, whole: &[u8], part: &[u8]) -> Handle {
+    new(&BigDecimal::parse(negate, whole, part, 0).unwrap())
+}
+pub fn parse_exp(negate: bool, whole: &[u8], part: &[u8], exp_negate: bool, exp: &[u8]) -> Result<Handle, String> {
+    let mut e: i64 = 0;
+    for &b in exp {
+        e = match e.checked_mul(10).and_then(|e| e.checked_add((b - b'0') as i64)) {
+            Some(e) if e <= u32::max_value() as i64 => e,
+            _ => return Err(format!("Decimal exponent {} is out of bounds.", String::from_utf8_lossy(exp))),
+        };
+    }
+    if exp_negate { e = -e; }
+    BigDecimal::parse(negate, whole, part, e).map(|d| new(&d))
+}
+
+fn decimal_of(v: &Value) -> BigDecimal {
+    match find_prism(v._handle()) {
+        Some(prism) => decimal(prism),
+        None => panic!("Not a decimal: {}", v),
+    }
+}
+pub fn add(x: &Value, y: &Value) -> Value { new_value(&decimal_of(x).add(&decimal_of(y))) }
+pub fn sub(x: &Value, y: &Value) -> Value { new_value(&decimal_of(x).sub(&decimal_of(y))) }
+pub fn mul(x: &Value, y: &Value) -> Value { new_value(&decimal_of(x).mul(&decimal_of(y))) }
+pub fn div(x: &Value, y: &Value, ctx: &Context) -> Result<Value, String> {
+    decimal_of(x).div(&decimal_of(y), ctx).map(|d| new_value(&d))
+}
+pub fn round(x: &Value, ctx: &Context) -> Result<Value, String> {
+    decimal_of(x).round(ctx).map(|d| new_value(&d))
+}
+
+/// Reads a decimal written by fressian, returning it and the bytes used.
+pub fn read_fressian(bytes: &[u8]) -> Result<(Handle, usize), String> {
+    if bytes.get(0) != Some(&fressian::Code::BIGDEC) {
+        return Err(format!("Not a fressian decimal."))
+    }
+    let (b, used) = match fressian::read_bytes(&bytes[1..]) {
+        Some(x) => x,
+        None => return Err(format!("Bad fressian decimal, the unscaled value should be bytes.")),
+    };
+    match fressian::read_int(&bytes[(1 + used)..]) {
+        Some((scale, n)) => Ok((new(&BigDecimal::new(BigIntegral::from_bytes(&b), scale as i32)), 1 + used + n)),
+        None => Err(format!("Bad fressian decimal, the scale should be an int.")),
+    }
+}
+
+// Consumes both operands.
+fn binary(prism: AnchoredLine, other: Unit, op: fn(&BigDecimal, &BigDecimal) -> BigDecimal) -> Unit {
+    let o = other.handle();
+    let o_dec = match find_prism(o) {
+        Some(p) => p,
+        None => panic!("Can't do arithmetic on BigDecimal and {}.", o.type_name()),
+    };
+    let res = new(&op(&decimal(prism), &decimal(o_dec)));
+    prism.segment().unit().handle().retire();
+    o.retire();
+    res.unit()
+}
+
+fn step(prism: AnchoredLine, by: i64) -> Unit {
+    let res = new(&decimal(prism).add(&BigDecimal::new(BigIntegral::from_i64(by), 0)));
+    prism.segment().unit().handle().retire();
+    res.unit()
+}
+
+impl Dispatch for BigDecimal_ {
+    fn tear_down(&self, prism: AnchoredLine) {
+        let seg = prism.segment();
+        assert_eq!(0, seg.anchor().aliases());
+        prism[1].handle().retire();
+        Segment::free(seg)
+    }
+    fn alias_components(&self, prism: AnchoredLine) { prism[1].handle().split(); }
+}
+impl Identification for BigDecimal_ {
+    fn type_name(&self) -> &'static str { "BigDecimal" }
+}
+impl Distinguish for BigDecimal_ {
+    fn hash(&self, prism: AnchoredLine) -> u32 {
+        use hash::hash_128;
+        let d = decimal(prism).stripped();
+        d.unscaled.to_twos().iter().fold(d.scale as u32, |h, limb| hash_128(h as u64, *limb, 16))
+    }
+    fn eq(&self, prism: AnchoredLine, other: Unit) -> bool {
+        match find_prism(other.handle()) {
+            Some(o_prism) => decimal(prism).partial_cmp(&decimal(o_prism)) == Some(Ordering::Equal),
+            None => false,
+        }
+    }
+    fn cmp(&self, prism: AnchoredLine, other: Unit) -> Option<Ordering> {
+        let o = other.handle();
+        if let Some(o_prism) = find_prism(o) {
+            return decimal(prism).partial_cmp(&decimal(o_prism))
+        }
+        if o.is_ref() {
+            let o_prism_unit = o.logical_value()[0];
+            Some(prism_unit().cmp(&o_prism_unit))
+        } else {
+            Some(Ordering::Greater)
+        }
+    }
+}
+impl Aggregate for BigDecimal_ { }
+impl Sequential for BigDecimal_ { }
+impl Associative for BigDecimal_ { }
+impl Reversible for BigDecimal_ { }
+impl Sorted for BigDecimal_ { }
+impl Notation for BigDecimal_ {
+    fn edn(&self, prism: AnchoredLine, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}M", decimal(prism))
+    }
+    fn fressian(&self, prism: AnchoredLine, w: &mut dyn io::Write) -> io::Result<usize> {
+        let d = decimal(prism);
+        let mut buf = vec![fressian::Code::BIGDEC];
+        fressian::write_bytes(&mut buf, &d.unscaled.to_bytes());
+        fressian::write_int(&mut buf, d.scale as i64);
+        w.write_all(&buf)?;
+        Ok(buf.len())
+    }
+}
+impl Numeral for BigDecimal_ {
+    fn inc(&self, prism: AnchoredLine) -> Unit { step(prism, 1) }
+    fn dec(&self, prism: AnchoredLine) -> Unit { step(prism, -1) }
+    fn add(&self, prism: AnchoredLine, other: Unit) -> Unit { binary(prism, other, BigDecimal::add) }
+    fn subtract(&self, prism: AnchoredLine, other: Unit) -> Unit { binary(prism, other, BigDecimal::sub) }
+    fn multiply(&self, prism: AnchoredLine, other: Unit) -> Unit { binary(prism, other, BigDecimal::mul) }
+    fn divide(&self, prism: AnchoredLine, other: Unit) -> Unit {
+        binary(prism, other, |x, y| match x.div(y, &Context::UNLIMITED) {
+            Ok(d) => d,
+            Err(msg) => panic!("{}", msg),
+        })
+    }
+    fn neg(&self, prism: AnchoredLine) -> Unit {
+        let res = new(&decimal(prism).neg());
+        prism.segment().unit().handle().retire();
+        res.unit()
+    }
+}
+impl Callable for BigDecimal_ { }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn d(s: &str) -> Value { s.parse().unwrap() }
+
+    #[test]
+    fn reading_and_arithmetic() {
+        assert_eq!(format!("{}", d("[1.50M -0.05M 7M 12E+3M 1.5e-3M]")), "[1.50M -0.05M 7M 12E+3M 0.0015M]");
+        assert_eq!(d("1.5M"), d("1.50M"));
+        assert_eq!(d("1.5M").hash(), d("1.500M").hash());
+        assert!(d("0.1M") < d("0.11M") && d("-2M") < d("1E+1M"));
+        assert_eq!(format!("{}", d("0.1M") + d("0.2M")), "0.3M");
+        assert_eq!(format!("{}", d("19.99M") * d("3M")), "59.97M");
+        assert_eq!(format!("{}", d("1M") - d("0.001M")), "0.999M");
+        assert_eq!(format!("{}", -d("2.5M")), "-2.5M");
+        assert_eq!(format!("{}", d("1M") / d("8M")), "0.125M");
+        assert_eq!(format!("{}", d("6.0M") / d("2M")), "3.0M");
+    }
+
+    #[test]
+    fn extreme_scales() {
+        assert!("1E99999999999M".parse::<Value>().is_err());
+        assert!("1E-2147483648M".parse::<Value>().is_err());
+        assert_eq!(format!("{}", d("1E2147483648M")), "1E+2147483648M");
+        let (huge, tiny) = (d("1E+2000000000M"), d("1E-2000000000M"));
+        assert!(d("1M") < huge && tiny < d("1M") && -(&huge) < tiny && d("0M") < tiny);
+        assert!(huge != d("1M") && d("-5E+3M") < d("-49.99E+2M"));
+        assert!(::std::panic::catch_unwind(|| huge.split_out() + d("1M")).is_err());
+        assert!(::std::panic::catch_unwind(|| huge.split_out() * d("1E+2000000000M")).is_err());
+    }
+
+    #[test]
+    fn contexts() {
+        let third = div(&d("1M"), &d("3M"), &Context { precision: 5, rounding: Rounding::HalfUp }).unwrap();
+        assert_eq!(format!("{}", third), "0.33333M");
+        assert!(div(&d("1M"), &d("3M"), &Context::UNLIMITED).is_err());
+        let two_thirds = div(&d("-2M"), &d("3M"), &Context::DECIMAL64).unwrap();
+        assert_eq!(format!("{}", two_thirds), "-0.6666666666666667M");
+        let r = |s: &str, mode| format!("{}", round(&d(s), &Context { precision: 1, rounding: mode }).unwrap());
+        assert_eq!(r("2.5M", Rounding::HalfEven), "2M");
+        assert_eq!(r("3.5M", Rounding::HalfEven), "4M");
+        assert_eq!(r("2.5M", Rounding::HalfDown), "2M");
+        assert_eq!(r("-2.5M", Rounding::HalfUp), "-3M");
+        assert_eq!(r("-2.1M", Rounding::Ceiling), "-2M");
+        assert_eq!(r("-2.1M", Rounding::Floor), "-3M");
+        assert_eq!(r("9.5M", Rounding::Up), "1E+1M");
+        assert!(round(&d("2.5M"), &Context { precision: 1, rounding: Rounding::Unnecessary }).is_err());
+    }
+
+    #[test]
+    fn fressian_round_trip() {
+        for s in ["123456789012345678901234.5678M", "-0.001M", "0M", "4E+2M"].iter() {
+            let x = d(s);
+            let prism = find_prism(x._handle()).unwrap();
+            let mut buf = vec![];
+            let used = mechanism::as_dispatch(&prism[0]).fressian(prism, &mut buf).unwrap();
+            assert_eq!(used, buf.len());
+            let (back, n) = read_fressian(&buf).unwrap();
+            assert_eq!(n, buf.len());
+            assert_eq!(format!("{}", back.value()), format!("{}", x));
+        }
+    }
+}
@@ -65,6 +65,11 @@ pub trait Sequential {
     fn is_sequential(&self, prism: AnchoredLine) -> bool { false }
     // TODO return AnchoredLine instead
     fn nth(&self, prism: AnchoredLine, idx: u32) -> *const Unit { unimplemented!() }
+    /// Owned element at idx, for sequences that compute their elements rather than store them
+    fn nth_out(&self, prism: AnchoredLine, idx: u32) -> Unit {
+        let x = self.nth(prism, idx);
+        unsafe { (*x).handle().split().unit() }
+    }
     fn nth_set(&self, prism: AnchoredLine, idx: u32, v: Unit) -> Unit { unimplemented!() }
     fn swap_idx(&self, prism: AnchoredLine, i: u32, j: u32) -> Unit { unimplemented!() }
 }
@@ -122,6 +122,32 @@ pub fn read(reader: &mut EdnReader, bytes: &[u8]) -> ReadResult {
                         res => { return res },
                     }
                 }
+                if d == b'"' {
+                    let re_start = &bytes[(i + 1)..];
+                    let quote_index = match string_end_quote_index(re_start) {
+                        Some(q) => q,
+                        None => { return more(reader, bytes, bytes.len() - i) },
+                    };
+                    let pattern = match from_utf8(&re_start[1..quote_index]) {
+                        Ok(x) => { x },
+                        Err(e) => {
+                            return err(reader, format!("Invalid utf-8 in regex pattern."))
+                        },
+                    };
+                    use regex;
+                    match regex::new(pattern) {
+                        Ok(h) => {
+                            reader.counter = reader.counter.add_ascii(2)
+                                .count(pattern).add_ascii(1);
+                            ready = h.unit();
+                            i += 1 + quote_index + 1 /*end quote*/;
+                            break 'ready;
+                        },
+                        Err(msg) => {
+                            return err(reader, msg)
+                        },
+                    }
+                }
                 if d == b'_' {
                     reader.pending.push_discard();
                     reader.counter = reader.counter.add_ascii(2);
@@ -449,6 +475,7 @@ pub fn tagged(reader: &mut EdnReader, bytes: &[u8], i: usize) -> ReadResult {
     if tag_sym.len() < 6 {
         if tag_sym == b"inst" { return tagged_inst(reader, bytes, i) }
         if tag_sym == b"uuid" { return tagged_uuid(reader, bytes, i) }
+        if tag_sym == b"uri" { return tagged_uri(reader, bytes, i) }
         if tag_sym == b"nil" || tag_sym == b"true" || tag_sym == b"false" {
             return err(reader, format!("Bad reader tag. \
                     Tag must be a valid symbol (not true/false/nil)."))
@@ -534,6 +561,43 @@ pub fn tagged_uuid(reader: &mut EdnReader, bytes: &[u8], i: usize) -> ReadResult
     };
 }
 
+pub fn tagged_uri(reader: &mut EdnReader, bytes: &[u8], i: usize) -> ReadResult {
+    let after_tag = &bytes[(i + 4 /*#uri*/)..];
+    let printing = match not_whitespace_index(after_tag) {
+        Some(p) => p,
+        None => { return more(reader, bytes, bytes.len() - i) },
+    };
+    let ws = &after_tag[..printing];
+    let form = &after_tag[printing..];
+    if form[0] != b'"' {
+        return err(reader, format!("Bad uri. The content should be \
+                             a string, like: #uri \"http://example.com/a?b#c\"."))
+    }
+    let end_quote = match string_end_quote_index(form) {
+        Some(e) => e,
+        None => { return more(reader, bytes, bytes.len() - i) },
+    };
+    let content = &form[1..end_quote];
+    let str_content = match from_utf8(content) {
+        Ok(s) => s,
+        Err(e) => {
+            return err(reader, format!("Invalid utf-8 in uri contents."))
+        }
+    };
+    use uri;
+    return match uri::new_parsed(str_content) {
+        Err(msg) => { err(reader, msg) },
+        Ok(h) => {
+            let ctr = reader.counter.add_ascii(4 /*#uri*/)
+                .count_ascii(ws).add_ascii(1)
+                .count(str_content).add_ascii(1);
+            reader.counter = ctr;
+            let bytes_used = (4 + ws.len() + 1 + content.len() + 1) as u32;
+            ReadResult::Ok { bytes_used, value: h.unit() }
+        }
+    };
+}
+
 pub fn symbolic_numbers(reader: &mut EdnReader, bytes: &[u8], i: usize) -> ReadResult {
     if (i + 2) >= bytes.len() {
         return more(reader, bytes, 2)
@@ -98,7 +98,7 @@ pub fn parse_numeric(s: &[u8]) -> Result<Handle, String> {
                     }
                     let part = &after_point[..e];
                     use float_point;
-                    return Ok(float_point::parse_exp(negate, whole, part, exp_negate, exp, promote))
+                    return float_point::parse_exp(negate, whole, part, exp_negate, exp, promote)
                 }
                 // TODO
                 return Err(format!("Bad fractional part in floating point number ({}).",
@@ -135,7 +135,7 @@ pub fn parse_numeric(s: &[u8]) -> Result<Handle, String> {
             let whole = &body[..d];
             let part = &b""[..];
             use float_point;
-            return Ok(float_point::parse_exp(negate, whole, part, exp_negate, exp, promote))
+            return float_point::parse_exp(negate, whole, part, exp_negate, exp, promote)
         }
         if db == b'x' {
             if d != 1 || body[0] != b'0' {
@@ -211,7 +211,7 @@ pub fn parse_numeric(s: &[u8]) -> Result<Handle, String> {
                                    from_utf8(s).unwrap()))
             }
             use rational;
-            return Ok(rational::parse(negate, numer, denom))
+            return rational::parse(negate, numer, denom)
         }
         return Err(format!("Bad number ({}), character {} at position {} makes no sense.",
                            from_utf8(s).unwrap(), char::from(db), d + if explicit_sign { 1 } else { 0 }))
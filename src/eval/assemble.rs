@@ -211,9 +211,10 @@ pub fn name_pair_to_buf(buf: &mut Vec<u8>, name_pair: &Value) {
 pub fn name_to_buf(buf: &mut Vec<u8>, name: &Value) {
     use string;
     let prism = string::find_prism(name._handle()).unwrap();
-    let bytes = string::byte_slice(&prism);
-    wasm::uleb128(buf, bytes.len() as u64);
-    buf.extend_from_slice(bytes);
+    wasm::uleb128(buf, string::byte_count(prism) as u64);
+    for chunk in string::chunks(prism) {
+        buf.extend_from_slice(chunk.as_bytes());
+    }
 }
 
 pub fn declare_section(sigs: Vec<u32>) -> Vec<u8> {
@@ -47,9 +47,12 @@ pub fn blank() -> Guide {
 
 pub fn parse(negate: bool, whole: &[u8], part: &[u8], promote: bool) -> Handle {
     use std::str::from_utf8;
+    if promote {
+        use decimal;
+        return decimal::parse(negate, whole, part)
+    }
     // TODO remove underscores from whole, allowing floats like 42_000.0
     let b = format!("{}.{}", from_utf8(whole).unwrap(), from_utf8(part).unwrap());
-    if promote { unimplemented!() }
     let mut x = b.parse::<f64>().unwrap();
     if negate { x = -x; }
     let guide = {
@@ -61,11 +64,14 @@ pub fn parse(negate: bool, whole: &[u8], part: &[u8], promote: bool) -> Handle {
 }
 
 pub fn parse_exp(negate: bool, whole: &[u8], part: &[u8],
-                 exp_negate: bool, exp: &[u8], promote: bool) -> Handle {
+                 exp_negate: bool, exp: &[u8], promote: bool) -> Result<Handle, String> {
     use std::str::from_utf8;
+    if promote {
+        use decimal;
+        return decimal::parse_exp(negate, whole, part, exp_negate, exp)
+    }
     let b = format!("{}.{}e{}{}", from_utf8(whole).unwrap(), from_utf8(part).unwrap(),
                     if exp_negate { "-" } else { "" }, from_utf8(exp).unwrap());
-    if promote { unimplemented!() }
     let mut x = b.parse::<f64>().unwrap();
     if negate { x = -x; }
     let guide = {
@@ -73,7 +79,7 @@ pub fn parse_exp(negate: bool, whole: &[u8], part: &[u8],
         if promote { g.set_big() } else { g }
     };
     store(guide.root, x);
-    guide.store().segment().unit().handle()
+    Ok(guide.store().segment().unit().handle())
 }
 
 pub fn inf() -> Handle {
@@ -100,6 +106,8 @@ pub fn store(line: AnchoredLine, x: f64) {
     }
 }
 
+pub fn as_f64(prism: AnchoredLine) -> f64 { hydrate(Guide::hydrate(prism).root) }
+
 pub fn hydrate(line: AnchoredLine) -> f64 {
     let x = if cfg!(target_pointer_width = "32") {
         let low: u32 = line[0].into();
@@ -112,6 +120,23 @@ pub fn hydrate(line: AnchoredLine) -> f64 {
     f64_from_u64(x)
 }
 
+fn binary(prism: AnchoredLine, other: Unit, f: fn(f64, f64) -> f64) -> Unit {
+    let o = other.handle();
+    let o_float = match find_prism(o) {
+        Some(p) => p,
+        None => panic!("Can't do arithmetic on FloatPoint and {}.", o.type_name()),
+    };
+    let res = new(f(as_f64(prism), as_f64(o_float)));
+    prism.segment().unit().handle().retire();
+    o.retire();
+    res
+}
+fn unary(prism: AnchoredLine, f: fn(f64) -> f64) -> Unit {
+    let res = new(f(as_f64(prism)));
+    prism.segment().unit().handle().retire();
+    res
+}
+
 impl Dispatch for FloatPoint_ { }
 impl Identification for FloatPoint_ {
     fn type_name(&self) -> &'static str { "FloatPoint" }
@@ -184,7 +209,22 @@ impl Notation for FloatPoint_ {
         }
     }
 }
-impl Numeral for FloatPoint_ { }
+impl Numeral for FloatPoint_ {
+    fn inc(&self, prism: AnchoredLine) -> Unit { unary(prism, |x| x + 1.0) }
+    fn dec(&self, prism: AnchoredLine) -> Unit { unary(prism, |x| x - 1.0) }
+    fn add(&self, prism: AnchoredLine, other: Unit) -> Unit { binary(prism, other, |x, y| x + y) }
+    fn subtract(&self, prism: AnchoredLine, other: Unit) -> Unit { binary(prism, other, |x, y| x - y) }
+    fn multiply(&self, prism: AnchoredLine, other: Unit) -> Unit { binary(prism, other, |x, y| x * y) }
+    fn divide(&self, prism: AnchoredLine, other: Unit) -> Unit { binary(prism, other, |x, y| x / y) }
+    fn remainder(&self, prism: AnchoredLine, other: Unit) -> Unit { binary(prism, other, |x, y| x % y) }
+    fn modulus(&self, prism: AnchoredLine, other: Unit) -> Unit {
+        binary(prism, other, |x, y| {
+            let r = x % y;
+            if r != 0.0 && (r < 0.0) != (y < 0.0) { r + y } else { r }
+        })
+    }
+    fn neg(&self, prism: AnchoredLine) -> Unit { unary(prism, |x| -x) }
+}
 impl Callable for FloatPoint_ { }
 
 #[cfg(test)]
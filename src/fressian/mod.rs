@@ -110,3 +110,217 @@ pub fn sleb128(buf: &mut Vec<u8>, x: i64) {
     }
 }
 
+
+/// Writes an int in its smallest packed form.
+pub fn write_int(buf: &mut Vec<u8>, x: i64) {
+    let width = bit_width(x);
+    let (zero, extra) = match width {
+        0..=7 => {
+            if x >= -1 {
+                buf.push(x as u8);
+                return
+            }
+            (Code::I13.1, 1)
+        },
+        8..=13 => (Code::I13.1, 1),
+        14..=20 => (Code::I20.1, 2),
+        21..=26 => (Code::I26.1, 3),
+        27..=34 => (Code::I34.1, 4),
+        35..=42 => (Code::I42.1, 5),
+        43..=50 => (Code::I50.1, 6),
+        _ => {
+            buf.push(Code::I64);
+            buf.extend_from_slice(&x.to_be_bytes());
+            return
+        },
+    };
+    buf.push(zero.wrapping_add((x >> (8 * extra)) as u8));
+    for i in (0..extra).rev() {
+        buf.push((x >> (8 * i)) as u8);
+    }
+}
+
+/// Reads an int, returning it and the bytes used.
+pub fn read_int(bytes: &[u8]) -> Option<(i64, usize)> {
+    let code = *bytes.get(0)?;
+    let (zero, extra) = match code {
+        0x00..=0x3F | 0xFF => return Some(((code as i8) as i64, 1)),
+        0x40..=0x5F => (Code::I13.1, 1),
+        0x60..=0x6F => (Code::I20.1, 2),
+        0x70..=0x73 => (Code::I26.1, 3),
+        0x74..=0x77 => (Code::I34.1, 4),
+        0x78..=0x7B => (Code::I42.1, 5),
+        0x7C..=0x7F => (Code::I50.1, 6),
+        Code::I64 => {
+            let b = bytes.get(1..9)?;
+            let mut x = [0u8; 8];
+            x.copy_from_slice(b);
+            return Some((i64::from_be_bytes(x), 9))
+        },
+        _ => return None,
+    };
+    let mut x = (code as i64) - (zero as i64);
+    for b in bytes.get(1..(1 + extra))? {
+        x = (x << 8) | (*b as i64);
+    }
+    Some((x, 1 + extra))
+}
+
+// Strings go as java does, utf-8 over utf-16 code units, so characters
+// outside the basic plane take two three byte surrogates.
+fn encode_java_utf8(s: &str) -> Vec<u8> {
+    let mut b = vec![];
+    for u in s.encode_utf16() {
+        if u < 0x80 {
+            b.push(u as u8);
+        } else if u < 0x800 {
+            b.push(0xC0 | (u >> 6) as u8);
+            b.push(0x80 | (u & 0x3F) as u8);
+        } else {
+            b.push(0xE0 | (u >> 12) as u8);
+            b.push(0x80 | ((u >> 6) & 0x3F) as u8);
+            b.push(0x80 | (u & 0x3F) as u8);
+        }
+    }
+    b
+}
+fn decode_java_utf8(b: &[u8]) -> Option<String> {
+    let mut units = vec![];
+    let mut i = 0;
+    while i < b.len() {
+        let c = b[i] as u16;
+        let (u, n) = if c < 0x80 {
+            (c, 1)
+        } else if c & 0xE0 == 0xC0 {
+            (((c & 0x1F) << 6) | (*b.get(i + 1)? as u16 & 0x3F), 2)
+        } else if c & 0xF0 == 0xE0 {
+            (((c & 0x0F) << 12) | ((*b.get(i + 1)? as u16 & 0x3F) << 6) | (*b.get(i + 2)? as u16 & 0x3F), 3)
+        } else {
+            return None
+        };
+        units.push(u);
+        i += n;
+    }
+    String::from_utf16(&units).ok()
+}
+
+/// Writes a string, in chunks when it's long.
+pub fn write_string(buf: &mut Vec<u8>, s: &str) {
+    let b = encode_java_utf8(s);
+    let mut rest = &b[..];
+    while rest.len() > BYTE_CHUNK_SIZE as usize {
+        let mut cut = BYTE_CHUNK_SIZE as usize;
+        while rest[cut] & 0xC0 == 0x80 {
+            cut -= 1;
+        }
+        buf.push(Code::STRING_CHUNK);
+        write_int(buf, cut as i64);
+        buf.extend_from_slice(&rest[..cut]);
+        rest = &rest[cut..];
+    }
+    let small = Code::SMALL_STRING;
+    if rest.len() < (small.1 - small.0) as usize {
+        buf.push(small.0 + rest.len() as u8);
+    } else {
+        buf.push(Code::STRING);
+        write_int(buf, rest.len() as i64);
+    }
+    buf.extend_from_slice(rest);
+}
+
+/// Reads a string, returning it and the bytes used.
+pub fn read_string(bytes: &[u8]) -> Option<(String, usize)> {
+    let mut b = vec![];
+    let mut i = 0;
+    loop {
+        let code = *bytes.get(i)?;
+        let (len, used) = match code {
+            Code::STRING | Code::STRING_CHUNK => {
+                let (n, used) = read_int(&bytes[(i + 1)..])?;
+                (n as usize, 1 + used)
+            },
+            c if Code::SMALL_STRING.0 <= c && c < Code::SMALL_STRING.1 => ((c - Code::SMALL_STRING.0) as usize, 1),
+            _ => return None,
+        };
+        i += used;
+        b.extend_from_slice(bytes.get(i..(i + len))?);
+        i += len;
+        if code != Code::STRING_CHUNK {
+            return decode_java_utf8(&b).map(|s| (s, i))
+        }
+    }
+}
+
+/// Writes bytes, in chunks when there are many.
+pub fn write_bytes(buf: &mut Vec<u8>, b: &[u8]) {
+    let mut rest = b;
+    while rest.len() > BYTE_CHUNK_SIZE as usize {
+        buf.push(Code::BYTES_CHUNK);
+        write_int(buf, BYTE_CHUNK_SIZE as i64);
+        buf.extend_from_slice(&rest[..(BYTE_CHUNK_SIZE as usize)]);
+        rest = &rest[(BYTE_CHUNK_SIZE as usize)..];
+    }
+    let small = Code::SMALL_BYTES;
+    if rest.len() < (small.1 - small.0) as usize {
+        buf.push(small.0 + rest.len() as u8);
+    } else {
+        buf.push(Code::BYTES);
+        write_int(buf, rest.len() as i64);
+    }
+    buf.extend_from_slice(rest);
+}
+
+/// Reads bytes, returning them and the bytes used.
+pub fn read_bytes(bytes: &[u8]) -> Option<(Vec<u8>, usize)> {
+    let mut b = vec![];
+    let mut i = 0;
+    loop {
+        let code = *bytes.get(i)?;
+        let (len, used) = match code {
+            Code::BYTES | Code::BYTES_CHUNK => {
+                let (n, used) = read_int(&bytes[(i + 1)..])?;
+                (n as usize, 1 + used)
+            },
+            c if Code::SMALL_BYTES.0 <= c && c < Code::SMALL_BYTES.1 => ((c - Code::SMALL_BYTES.0) as usize, 1),
+            _ => return None,
+        };
+        i += used;
+        b.extend_from_slice(bytes.get(i..(i + len))?);
+        i += len;
+        if code != Code::BYTES_CHUNK {
+            return Some((b, i))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ints_and_strings() {
+        for &x in [0i64, 63, -1, -2, 64, -64, 4095, -4096, 1 << 20, -(1 << 33), 1 << 49, i64::MIN, i64::MAX].iter() {
+            let mut buf = vec![];
+            write_int(&mut buf, x);
+            assert_eq!(read_int(&buf), Some((x, buf.len())));
+        }
+        let mut buf = vec![];
+        write_int(&mut buf, 5);
+        write_int(&mut buf, 300);
+        assert_eq!(buf, vec![0x05, 0x51, 0x2C]);
+        for s in ["", "abc", "héllo 𝄞", &"xλ".repeat(40000)].iter() {
+            let mut buf = vec![];
+            write_string(&mut buf, s);
+            assert_eq!(read_string(&buf), Some((s.to_string(), buf.len())));
+        }
+        for n in [0usize, 7, 8, 70000].iter() {
+            let b: Vec<u8> = (0..*n).map(|i| i as u8).collect();
+            let mut buf = vec![];
+            write_bytes(&mut buf, &b);
+            assert_eq!(read_bytes(&buf), Some((b, buf.len())));
+        }
+        let mut buf = vec![];
+        write_string(&mut buf, "𝄞");
+        assert_eq!(buf, vec![0xE0, 0xED, 0xA0, 0xB4, 0xED, 0xB4, 0x9E]);
+    }
+}
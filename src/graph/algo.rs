@@ -0,0 +1,420 @@
+// Copyright (c) Cole Frederick. All rights reserved.
+// The use and distribution terms for this software are covered by the
+// Eclipse Public License 1.0 (https://opensource.org/licenses/eclipse-1.0.php)
+// which can be found in the file epl-v10.html at the root of this distribution.
+// By using this software in any fashion, you are agreeing to be bound by the terms of this license.
+// You must not remove this notice, or any other, from this software.
+
+//! Graph algorithms.
+//!
+//! Each algorithm numbers the nodes first, see `Indexed`, and works on
+//! those numbers, turning results back into values at the end.
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+use value::Value;
+use {vector, map, set};
+use super::*;
+
+/// The nodes of a graph by number, and out edges with their weights.
+pub struct Indexed {
+    pub nodes: Vec<Value>,
+    pub index: HashMap<Value, usize>,
+    pub out: Vec<Vec<(usize, f64)>>,
+}
+
+impl Indexed {
+    pub fn new(g: &Value) -> Indexed {
+        let nodes: Vec<Value> = adjacency(g).iter().map(|n| n.split_out()).collect();
+        let index: HashMap<Value, usize> = nodes.iter().enumerate()
+            .map(|(i, n)| (n.split_out(), i)).collect();
+        let out = nodes.iter().map(|n| {
+            successors(g, n).iter().map(|m| (index[m], weight(g, n, m).unwrap())).collect()
+        }).collect();
+        Indexed { nodes, index, out }
+    }
+    pub fn len(&self) -> usize { self.nodes.len() }
+    fn id(&self, n: &Value) -> usize {
+        match self.index.get(n) {
+            Some(&i) => i,
+            None => panic!("Not a node of the graph: {}", n),
+        }
+    }
+}
+
+/// Strongly connected components, as a vector of sets, by Tarjan's algorithm.
+/// A component comes before any component with an edge into it.
+pub fn scc(g: &Value) -> Value {
+    let ix = Indexed::new(g);
+    let n = ix.len();
+    let mut order = vec![usize::MAX; n];
+    let mut low = vec![0; n];
+    let mut on_stack = vec![false; n];
+    let mut stack = vec![];
+    let mut count = 0;
+    let mut components = vector::new_value();
+    for root in 0..n {
+        if order[root] != usize::MAX {
+            continue
+        }
+        let mut calls: Vec<(usize, usize)> = vec![(root, 0)];
+        order[root] = count; low[root] = count; count += 1;
+        stack.push(root); on_stack[root] = true;
+        while let Some(&(v, pos)) = calls.last() {
+            if pos < ix.out[v].len() {
+                calls.last_mut().unwrap().1 += 1;
+                let w = ix.out[v][pos].0;
+                if order[w] == usize::MAX {
+                    order[w] = count; low[w] = count; count += 1;
+                    stack.push(w); on_stack[w] = true;
+                    calls.push((w, 0));
+                } else if on_stack[w] {
+                    low[v] = low[v].min(order[w]);
+                }
+                continue
+            }
+            calls.pop();
+            if let Some(&(u, _)) = calls.last() {
+                low[u] = low[u].min(low[v]);
+            }
+            if low[v] == order[v] {
+                let mut c = set::new_value();
+                loop {
+                    let w = stack.pop().unwrap();
+                    on_stack[w] = false;
+                    c = c.conj(ix.nodes[w].split_out());
+                    if w == v { break }
+                }
+                components = components.conj(c);
+            }
+        }
+    }
+    components
+}
+
+/// Nodes in an order where every edge goes forward, or None if there is a cycle.
+pub fn topo_sort(g: &Value) -> Option<Value> {
+    let ix = Indexed::new(g);
+    let mut incoming = vec![0; ix.len()];
+    for es in ix.out.iter() {
+        for &(w, _) in es.iter() {
+            incoming[w] += 1;
+        }
+    }
+    let mut ready: VecDeque<usize> = (0..ix.len()).filter(|&v| incoming[v] == 0).collect();
+    let mut sorted = vector::new_value();
+    while let Some(v) = ready.pop_front() {
+        sorted = sorted.conj(ix.nodes[v].split_out());
+        for &(w, _) in ix.out[v].iter() {
+            incoming[w] -= 1;
+            if incoming[w] == 0 {
+                ready.push_back(w);
+            }
+        }
+    }
+    if sorted.count() as usize == ix.len() { Some(sorted) } else { None }
+}
+
+#[derive(PartialEq)]
+struct Dist(f64, usize);
+impl Eq for Dist { }
+impl PartialOrd for Dist {
+    fn partial_cmp(&self, o: &Dist) -> Option<Ordering> { Some(self.cmp(o)) }
+}
+// Reversed, so the binary heap pops the nearest first.
+impl Ord for Dist {
+    fn cmp(&self, o: &Dist) -> Ordering {
+        o.0.partial_cmp(&self.0).unwrap_or(Ordering::Equal).then(o.1.cmp(&self.1))
+    }
+}
+
+fn check_weight(w: f64) {
+    if w < 0.0 {
+        panic!("Negative edge weight: {}", w);
+    }
+}
+
+/// Distance from a node to every node reachable from it, by Dijkstra's algorithm.
+pub fn shortest_distances(g: &Value, from: &Value) -> Value {
+    let ix = Indexed::new(g);
+    let mut dist = vec![f64::INFINITY; ix.len()];
+    let s = ix.id(from);
+    dist[s] = 0.0;
+    let mut heap = BinaryHeap::new();
+    heap.push(Dist(0.0, s));
+    while let Some(Dist(d, v)) = heap.pop() {
+        if d > dist[v] {
+            continue
+        }
+        for &(w, wt) in ix.out[v].iter() {
+            check_weight(wt);
+            if d + wt < dist[w] {
+                dist[w] = d + wt;
+                heap.push(Dist(d + wt, w));
+            }
+        }
+    }
+    let mut m = map::new_value();
+    for (v, d) in dist.into_iter().enumerate() {
+        if d.is_finite() {
+            m = m.assoc(ix.nodes[v].split_out(), d.into());
+        }
+    }
+    m
+}
+
+/// A shortest path as a vector of nodes, and its length, by A* search.
+/// The estimate h must not overestimate the distance to the goal.
+pub fn a_star(g: &Value, from: &Value, to: &Value, h: impl Fn(&Value) -> f64) -> Option<(Value, f64)> {
+    let ix = Indexed::new(g);
+    let (s, t) = (ix.id(from), ix.id(to));
+    let mut dist = vec![f64::INFINITY; ix.len()];
+    let mut prev = vec![usize::MAX; ix.len()];
+    dist[s] = 0.0;
+    let mut heap = BinaryHeap::new();
+    heap.push(Dist(h(from), s));
+    while let Some(Dist(_, v)) = heap.pop() {
+        if v == t {
+            let mut path = vec![t];
+            while *path.last().unwrap() != s {
+                path.push(prev[*path.last().unwrap()]);
+            }
+            let p = path.into_iter().rev().fold(vector::new_value(), |p, v| p.conj(ix.nodes[v].split_out()));
+            return Some((p, dist[t]))
+        }
+        for &(w, wt) in ix.out[v].iter() {
+            check_weight(wt);
+            let d = dist[v] + wt;
+            if d < dist[w] {
+                dist[w] = d;
+                prev[w] = v;
+                heap.push(Dist(d + h(&ix.nodes[w]), w));
+            }
+        }
+    }
+    None
+}
+
+/// A shortest path as a vector of nodes, and its length, by Dijkstra's algorithm.
+pub fn shortest_path(g: &Value, from: &Value, to: &Value) -> Option<(Value, f64)> {
+    a_star(g, from, to, |_| 0.0)
+}
+
+// An undirected graph of the given edges, over all the nodes of g.
+fn forest(g: &Value, ix: &Indexed, edges: &[(usize, usize)]) -> Value {
+    let mut t = ix.nodes.iter().fold(new_value(false), |t, n| add_node(&t, n.split_out()));
+    for &(u, v) in edges.iter() {
+        let (a, b) = (&ix.nodes[u], &ix.nodes[v]);
+        t = match weight_value(g, a, b) {
+            Some(w) => add_weighted_edge(&t, a, b, w),
+            None => add_edge(&t, a, b),
+        };
+    }
+    t
+}
+
+fn find(parent: &mut Vec<usize>, mut v: usize) -> usize {
+    while parent[v] != v {
+        parent[v] = parent[parent[v]];
+        v = parent[v];
+    }
+    v
+}
+
+/// A minimum spanning forest by Kruskal's algorithm, as an undirected graph.
+/// Edge directions are ignored.
+pub fn mst_kruskal(g: &Value) -> Value {
+    let ix = Indexed::new(g);
+    let mut es: Vec<(f64, usize, usize)> = vec![];
+    for (u, out) in ix.out.iter().enumerate() {
+        for &(v, w) in out.iter() {
+            es.push((w, u, v));
+        }
+    }
+    es.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+    let mut parent: Vec<usize> = (0..ix.len()).collect();
+    let mut tree = vec![];
+    for (_, u, v) in es {
+        let (ru, rv) = (find(&mut parent, u), find(&mut parent, v));
+        if ru != rv {
+            parent[ru] = rv;
+            tree.push((u, v));
+        }
+    }
+    forest(g, &ix, &tree)
+}
+
+/// A minimum spanning forest by Prim's algorithm, as an undirected graph.
+/// Edge directions are ignored.
+pub fn mst_prim(g: &Value) -> Value {
+    let ix = Indexed::new(g);
+    let n = ix.len();
+    let mut both: Vec<Vec<(usize, f64)>> = ix.out.clone();
+    for (u, out) in ix.out.iter().enumerate() {
+        for &(v, w) in out.iter() {
+            both[v].push((u, w));
+        }
+    }
+    let mut in_tree = vec![false; n];
+    let mut tree = vec![];
+    for root in 0..n {
+        if in_tree[root] {
+            continue
+        }
+        let mut heap = BinaryHeap::new();
+        heap.push((Dist(0.0, root), usize::MAX));
+        while let Some((Dist(_, v), from)) = heap.pop() {
+            if in_tree[v] {
+                continue
+            }
+            in_tree[v] = true;
+            if from != usize::MAX {
+                tree.push((from, v));
+            }
+            for &(w, wt) in both[v].iter() {
+                if !in_tree[w] {
+                    heap.push((Dist(wt, w), v));
+                }
+            }
+        }
+    }
+    forest(g, &ix, &tree)
+}
+
+/// Total of the edge weights.
+pub fn total_weight(g: &Value) -> f64 {
+    let ix = Indexed::new(g);
+    let sum: f64 = ix.out.iter().flat_map(|es| es.iter().map(|e| e.1)).sum();
+    if is_directed(g) { sum } else { sum / 2.0 }
+}
+
+/// The maximum flow from source to sink, with edge weights as capacities,
+/// by the Edmonds-Karp algorithm.
+pub fn max_flow(g: &Value, source: &Value, sink: &Value) -> f64 {
+    let ix = Indexed::new(g);
+    let (s, t) = (ix.id(source), ix.id(sink));
+    let n = ix.len();
+    let mut residual: Vec<HashMap<usize, f64>> = vec![HashMap::new(); n];
+    for (u, out) in ix.out.iter().enumerate() {
+        for &(v, w) in out.iter() {
+            check_weight(w);
+            *residual[u].entry(v).or_insert(0.0) += w;
+            residual[v].entry(u).or_insert(0.0);
+        }
+    }
+    let mut flow = 0.0;
+    loop {
+        let mut prev = vec![usize::MAX; n];
+        prev[s] = s;
+        let mut q = VecDeque::new();
+        q.push_back(s);
+        while let Some(u) = q.pop_front() {
+            if u == t { break }
+            for (&v, &c) in residual[u].iter() {
+                if c > 0.0 && prev[v] == usize::MAX {
+                    prev[v] = u;
+                    q.push_back(v);
+                }
+            }
+        }
+        if prev[t] == usize::MAX || s == t {
+            return flow
+        }
+        let mut bottleneck = f64::INFINITY;
+        let mut v = t;
+        while v != s {
+            let u = prev[v];
+            bottleneck = bottleneck.min(residual[u][&v]);
+            v = u;
+        }
+        let mut v = t;
+        while v != s {
+            let u = prev[v];
+            *residual[u].get_mut(&v).unwrap() -= bottleneck;
+            *residual[v].get_mut(&u).unwrap() += bottleneck;
+            v = u;
+        }
+        flow += bottleneck;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(x: i64) -> Value { x.into() }
+    fn directed(es: &[(i64, i64)]) -> Value {
+        es.iter().fold(new_value(true), |g, &(a, b)| add_edge(&g, &v(a), &v(b)))
+    }
+    fn weighted(directed: bool, es: &[(i64, i64, i64)]) -> Value {
+        es.iter().fold(new_value(directed), |g, &(a, b, w)| add_weighted_edge(&g, &v(a), &v(b), v(w)))
+    }
+
+    #[test]
+    fn structure() {
+        let g = weighted(false, &[(1, 2, 5), (2, 3, 1)]);
+        assert!(has_edge(&g, &v(2), &v(1)) && has_edge(&g, &v(3), &v(2)));
+        assert_eq!(weight(&g, &v(1), &v(2)), Some(5.0));
+        let n = |s: &str| s.parse::<Value>().unwrap();
+        let t = add_weighted_edge(&new_value(true), &v(1), &v(2), n("3/2"));
+        let t = add_weighted_edge(&t, &v(2), &v(3), n("0.25M"));
+        let t = add_weighted_edge(&t, &v(3), &v(4), n("100000000000000000000"));
+        assert_eq!((weight(&t, &v(1), &v(2)), weight(&t, &v(2), &v(3))), (Some(1.5), Some(0.25)));
+        assert_eq!(weight(&t, &v(3), &v(4)), Some(1e20));
+        assert_eq!(g.count(), 3);
+        let h = remove_node(&g, &v(2));
+        assert_eq!(h.count(), 2);
+        assert!(!has_edge(&h, &v(1), &v(2)));
+        assert!(edges(&h).is_empty());
+        assert_eq!(g, weighted(false, &[(2, 3, 1), (2, 1, 5)]));
+        let d = directed(&[(1, 2)]);
+        assert_eq!(format!("{}", d), "#digraph {1 #{2}, 2 #{}}");
+        assert_eq!(remove_edge(&d, &v(1), &v(2)), add_node(&add_node(&new_value(true), v(2)), v(1)));
+    }
+
+    #[test]
+    fn components_and_order() {
+        let g = directed(&[(1, 2), (2, 3), (3, 1), (3, 4), (4, 5), (5, 4), (6, 6)]);
+        let cs = scc(&g);
+        assert_eq!(cs.count(), 3);
+        let sizes: Vec<u32> = cs.iter().map(|c| c.count()).collect();
+        assert!(sizes.contains(&3) && sizes.contains(&2) && sizes.contains(&1));
+        // {4 5} comes before {1 2 3}, which has an edge into it
+        let pos = |n: i64| cs.iter().position(|c| c.contains(&v(n))).unwrap();
+        assert!(pos(4) < pos(1));
+        assert_eq!(topo_sort(&g), None);
+        let dag = directed(&[(1, 2), (1, 3), (3, 2), (2, 4)]);
+        let order = topo_sort(&dag).unwrap();
+        let at = |n: i64| order.iter().position(|x| *x == v(n)).unwrap();
+        assert!(at(1) < at(3) && at(3) < at(2) && at(2) < at(4));
+    }
+
+    #[test]
+    fn paths() {
+        let g = weighted(true, &[(1, 2, 7), (1, 3, 9), (1, 6, 14), (2, 3, 10), (2, 4, 15),
+                                 (3, 4, 11), (3, 6, 2), (4, 5, 6), (6, 5, 9)]);
+        let d = shortest_distances(&g, &v(1));
+        assert_eq!(*d.get(&v(5)), Value::from(20.0));
+        assert_eq!(*d.get(&v(4)), Value::from(20.0));
+        let (p, len) = shortest_path(&g, &v(1), &v(5)).unwrap();
+        assert_eq!(len, 20.0);
+        assert_eq!(format!("{}", p), "[1 3 6 5]");
+        let (q, len) = a_star(&g, &v(1), &v(5), |n| if *n == v(5) { 0.0 } else { 6.0 }).unwrap();
+        assert_eq!((format!("{}", q), len), ("[1 3 6 5]".to_string(), 20.0));
+        assert!(shortest_path(&g, &v(5), &v(1)).is_none());
+    }
+
+    #[test]
+    fn spanning_trees_and_flow() {
+        let g = weighted(false, &[(1, 2, 4), (1, 3, 1), (2, 3, 2), (2, 4, 5), (3, 4, 8), (4, 5, 3),
+                                  (6, 7, 1)]);
+        let (k, p) = (mst_kruskal(&g), mst_prim(&g));
+        assert_eq!((total_weight(&k), total_weight(&p)), (12.0, 12.0));
+        assert_eq!(edges(&k).len(), 2 * 5);
+        assert_eq!(k, p);
+
+        let f = weighted(true, &[(0, 1, 16), (0, 2, 13), (1, 2, 10), (2, 1, 4), (1, 3, 12),
+                                 (3, 2, 9), (2, 4, 14), (4, 3, 7), (3, 5, 20), (4, 5, 4)]);
+        assert_eq!(max_flow(&f, &v(0), &v(5)), 23.0);
+    }
+}
@@ -5,9 +5,254 @@
 // By using this software in any fashion, you are agreeing to be bound by the terms of this license.
 // You must not remove this notice, or any other, from this software.
 
+//! Persistent graphs, directed or undirected.
+//!
+//! A graph is an adjacency map, from each node to the set of its successors,
+//! and a weight map, from each node to a map of successor to edge weight.
+//! Edges without a weight count as weight one. An undirected edge is stored
+//! in both directions. Changes return a new graph, sharing the maps.
+//!
+//! Algorithms are in `algo`: strongly connected components, topological
+//! sort, shortest paths, minimum spanning trees and max flow.
 
-// strongly connected components
-// minimum spanning tree
-// shortest paths
-// max flow
+use std::fmt;
+use std::cmp::Ordering;
+use memory::*;
+use dispatch::*;
+use value::*;
+use handle::Handle;
+use transduce::{inges, last_call, Process};
+use {map, set, numeric};
 
+pub mod algo;
+
+// Layout: [prism directed adj weights]
+pub struct Graph_ { }
+pub fn prism_unit() -> Unit { mechanism::prism::<Graph_>() }
+pub fn is_prism(prism: AnchoredLine) -> bool { prism[0] == prism_unit() }
+pub fn find_prism(h: Handle) -> Option<AnchoredLine> { h.find_prism(prism_unit()) }
+pub fn is_graph(h: Handle) -> bool { find_prism(h).is_some() }
+
+pub fn new(directed: bool, adj: Handle, weights: Handle) -> Unit {
+    let s = Segment::new(4 /*prism directed adj weights*/);
+    s.set(0, prism_unit());
+    s.set(1, Unit::from(directed as usize));
+    s.set(2, adj.unit());
+    s.set(3, weights.unit());
+    s.unit()
+}
+pub fn new_value(directed: bool) -> Value {
+    new(directed, map::new().handle(), map::new().handle()).handle().value()
+}
+
+fn prism_of(g: &Value) -> AnchoredLine {
+    match find_prism(g._handle()) {
+        Some(prism) => prism,
+        None => panic!("Not a graph: {}", g),
+    }
+}
+pub fn is_directed(g: &Value) -> bool { prism_of(g)[1].u() != 0 }
+/// Map of node to the set of its successors.
+pub fn adjacency(g: &Value) -> &Value { unsafe { &*(prism_of(g).offset(2).line().star() as *const Value) } }
+/// Map of node to a map of successor to edge weight.
+pub fn weights(g: &Value) -> &Value { unsafe { &*(prism_of(g).offset(3).line().star() as *const Value) } }
+
+fn rebuild(g: &Value, adj: Value, weights: Value) -> Value {
+    new(is_directed(g), adj._consume(), weights._consume()).handle().value()
+}
+
+pub fn nodes(g: &Value) -> Value {
+    adjacency(g).iter().fold(set::new_value(), |s, n| s.conj(n.split_out()))
+}
+pub fn has_node(g: &Value, n: &Value) -> bool { adjacency(g).contains(n) }
+pub fn successors<'a>(g: &'a Value, n: &Value) -> &'a Value { adjacency(g).get(n) }
+pub fn has_edge(g: &Value, from: &Value, to: &Value) -> bool {
+    let s = successors(g, from);
+    !s.is_nil() && s.contains(to)
+}
+
+/// Weight of the edge as a float, if there is such an edge.
+pub fn weight(g: &Value, from: &Value, to: &Value) -> Option<f64> {
+    if !has_edge(g, from, to) {
+        return None
+    }
+    let ws = weights(g).get(from);
+    let w = if ws.is_nil() { ws } else { ws.get(to) };
+    Some(if w.is_nil() { 1.0 } else { number(w) })
+}
+/// The weight value of an edge, None when the edge has no weight.
+pub fn weight_value(g: &Value, from: &Value, to: &Value) -> Option<Value> {
+    let ws = weights(g).get(from);
+    let w = if ws.is_nil() { ws } else { ws.get(to) };
+    if w.is_nil() { None } else { Some(w.split_out()) }
+}
+
+pub fn number(v: &Value) -> f64 {
+    match numeric::Num::of(v._handle()) {
+        Some(n) => n.to_f64(),
+        None => panic!("Not a number: {}", v),
+    }
+}
+
+pub fn add_node(g: &Value, n: Value) -> Value {
+    if has_node(g, &n) {
+        return g.split_out()
+    }
+    rebuild(g, adjacency(g).split_out().assoc(n, set::new_value()), weights(g).split_out())
+}
+
+fn link(adj: Value, from: &Value, to: &Value) -> Value {
+    let s = adj.get(from).split_out();
+    let s = if s.is_nil() { set::new_value() } else { s };
+    let adj = if adj.contains(to) { adj } else { adj.assoc(to.split_out(), set::new_value()) };
+    adj.assoc(from.split_out(), s.conj(to.split_out()))
+}
+fn unlink(adj: Value, from: &Value, to: &Value) -> Value {
+    let s = adj.get(from).split_out();
+    if s.is_nil() { adj } else { adj.assoc(from.split_out(), s.dissoc(to)) }
+}
+fn set_weight(ws: Value, from: &Value, to: &Value, w: Option<&Value>) -> Value {
+    let m = ws.get(from).split_out();
+    let m = match w {
+        Some(w) => {
+            let m = if m.is_nil() { map::new_value() } else { m };
+            m.assoc(to.split_out(), w.split_out())
+        },
+        None => {
+            if m.is_nil() { return ws }
+            m.dissoc(to)
+        },
+    };
+    if m.is_empty() { ws.dissoc(from) } else { ws.assoc(from.split_out(), m) }
+}
+
+fn edge(g: &Value, from: &Value, to: &Value, w: Option<&Value>) -> Value {
+    let mut adj = link(adjacency(g).split_out(), from, to);
+    let mut ws = set_weight(weights(g).split_out(), from, to, w);
+    if !is_directed(g) {
+        adj = link(adj, to, from);
+        ws = set_weight(ws, to, from, w);
+    }
+    rebuild(g, adj, ws)
+}
+/// Adds an edge, and its nodes. Replaces any weight on the edge.
+pub fn add_edge(g: &Value, from: &Value, to: &Value) -> Value { edge(g, from, to, None) }
+pub fn add_weighted_edge(g: &Value, from: &Value, to: &Value, w: Value) -> Value {
+    edge(g, from, to, Some(&w))
+}
+
+pub fn remove_edge(g: &Value, from: &Value, to: &Value) -> Value {
+    let mut adj = unlink(adjacency(g).split_out(), from, to);
+    let mut ws = set_weight(weights(g).split_out(), from, to, None);
+    if !is_directed(g) {
+        adj = unlink(adj, to, from);
+        ws = set_weight(ws, to, from, None);
+    }
+    rebuild(g, adj, ws)
+}
+
+/// Removes a node and every edge to or from it.
+pub fn remove_node(g: &Value, n: &Value) -> Value {
+    let mut adj = adjacency(g).split_out().dissoc(n);
+    let mut ws = weights(g).split_out().dissoc(n);
+    let from: Vec<Value> = adj.iter().map(|m| m.split_out()).collect();
+    for m in from {
+        if adj.get(&m).contains(n) {
+            adj = unlink(adj, &m, n);
+            ws = set_weight(ws, &m, n, None);
+        }
+    }
+    rebuild(g, adj, ws)
+}
+
+/// Edges as [from to] vectors. An undirected edge appears in both directions.
+pub fn edges(g: &Value) -> Vec<Value> {
+    let mut es = vec![];
+    for (from, tos) in adjacency(g).iter_kv() {
+        for to in tos.iter() {
+            es.push(::vector::new_value().conj(from.split_out()).conj(to.split_out()));
+        }
+    }
+    es
+}
+
+impl Dispatch for Graph_ {
+    fn tear_down(&self, prism: AnchoredLine) {
+        let seg = prism.segment();
+        assert_eq!(0, seg.anchor().aliases());
+        prism[2].handle().retire();
+        prism[3].handle().retire();
+        Segment::free(seg)
+    }
+    fn alias_components(&self, prism: AnchoredLine) {
+        prism[2].handle().split();
+        prism[3].handle().split();
+    }
+}
+impl Identification for Graph_ {
+    fn type_name(&self) -> &'static str { "Graph" }
+}
+impl Distinguish for Graph_ {
+    fn hash(&self, prism: AnchoredLine) -> u32 {
+        use random::{PI, cycle_abc};
+        let mut y = cycle_abc(7, PI[87].wrapping_add(prism[1].u() as u64));
+        y = cycle_abc(34, y.wrapping_add(prism[2].handle().hash() as u64));
+        y = cycle_abc(34, y.wrapping_add(prism[3].handle().hash() as u64));
+        cycle_abc(210, y) as u32
+    }
+    fn eq(&self, prism: AnchoredLine, other: Unit) -> bool {
+        let o = other.handle();
+        if let Some(o_prism) = find_prism(o) {
+            prism[1] == o_prism[1] && prism[2].handle().eq(o_prism[2].handle())
+                && prism[3].handle().eq(o_prism[3].handle())
+        } else {
+            false
+        }
+    }
+    fn cmp(&self, prism: AnchoredLine, other: Unit) -> Option<Ordering> {
+        let o = other.handle();
+        if find_prism(o).is_some() {
+            return None
+        }
+        if o.is_ref() {
+            let o_prism_unit = o.logical_value()[0];
+            Some(prism_unit().cmp(&o_prism_unit))
+        } else {
+            Some(Ordering::Greater)
+        }
+    }
+}
+impl Aggregate for Graph_ {
+    fn is_aggregate(&self, prism: AnchoredLine) -> bool { true }
+    fn count(&self, prism: AnchoredLine) -> u32 { prism[2].handle().count() }
+    fn empty(&self, prism: AnchoredLine) -> Unit {
+        new(prism[1].u() != 0, map::new().handle(), map::new().handle())
+    }
+    /// Reduces over the nodes.
+    fn reduce(&self, prism: AnchoredLine, process_stack: &mut [Box<dyn Process>]) -> Value {
+        let adj = unsafe { &*(prism.offset(2).line().star() as *const Value) };
+        for n in adj.iter() {
+            if let Some(ret) = inges(process_stack, n) {
+                return ret;
+            }
+        }
+        last_call(process_stack)
+    }
+}
+impl Sequential for Graph_ { }
+impl Associative for Graph_ { }
+impl Reversible for Graph_ { }
+impl Sorted for Graph_ { }
+impl Notation for Graph_ {
+    fn edn(&self, prism: AnchoredLine, f: &mut fmt::Formatter) -> fmt::Result {
+        let tag = if prism[1].u() != 0 { "#digraph" } else { "#graph" };
+        let ws = prism[3].handle();
+        if ws.count() == 0 {
+            write!(f, "{} {}", tag, prism[2].handle())
+        } else {
+            write!(f, "{} {{:adj {}, :weights {}}}", tag, prism[2].handle(), ws)
+        }
+    }
+}
+impl Numeral for Graph_ { }
+impl Callable for Graph_ { }
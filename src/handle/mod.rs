@@ -12,6 +12,7 @@ use dispatch::*;
 use value::*;
 use transduce::{Transducers, Process};
 use meta;
+use numeric;
 
 #[derive(Copy, Clone)]
 pub struct Handle {
@@ -36,7 +37,7 @@ impl From<Value> for Handle {
 impl Handle {
     pub fn unit(self) -> Unit { self.unit }
     pub fn segment(self) -> Segment { self.unit.segment() }
-    pub fn value(self) -> Value { Value { handle: self } }
+    pub fn value(self) -> Value { Value::from(self) }
 
     pub const NIL:   Unit = Unit { word: 0x07 };
     pub const TRUE:  Unit = Unit { word: !0x00usize };
@@ -146,6 +147,9 @@ impl Handle {
     }
 
     pub fn cmp(self, other: Handle) -> Option<cmp::Ordering> {
+        if let Some(ord) = numeric::mixed_cmp(self, other) {
+            return ord
+        }
         if self.is_ref() {
             let prism = self.prism();
             let p = prism[0];
@@ -338,6 +342,13 @@ impl Handle {
             elem as *const Handle
         } else { unimplemented!() }
     }
+    pub fn nth_out(self, idx: u32) -> Handle {
+        if self.is_ref() {
+            let prism = self.prism();
+            let p = prism[0];
+            mechanism::as_dispatch(&p).nth_out(prism, idx).handle()
+        } else { unimplemented!() }
+    }
     pub fn nth_set(self, idx: u32, v: Handle) -> Handle {
         if self.is_ref() {
             let prism = self.prism();
@@ -464,6 +475,13 @@ impl Handle {
             mechanism::as_dispatch(&p).is_aggregate(prism)
         } else { false }
     }
+    pub fn is_sequential(self) -> bool {
+        if self.is_ref() {
+            let prism = self.prism();
+            let p = prism[0];
+            mechanism::as_dispatch(&p).is_sequential(prism)
+        } else { false }
+    }
 }
 
 impl Handle {
@@ -480,6 +498,9 @@ impl Handle {
 
 impl Handle {
     pub fn add(self, rhs: Handle) -> Handle {
+        if let Some(x) = numeric::mixed(self, rhs, numeric::Op::Add) {
+            return x
+        }
         if self.is_ref() {
             let prism = self.prism();
             let p = prism[0];
@@ -489,6 +510,9 @@ impl Handle {
     }
 
     pub fn sub(self, rhs: Handle) -> Handle {
+        if let Some(x) = numeric::mixed(self, rhs, numeric::Op::Sub) {
+            return x
+        }
         if self.is_ref() {
             let prism = self.prism();
             let p = prism[0];
@@ -498,6 +522,9 @@ impl Handle {
     }
 
     pub fn mul(self, rhs: Handle) -> Handle {
+        if let Some(x) = numeric::mixed(self, rhs, numeric::Op::Mul) {
+            return x
+        }
         if self.is_ref() {
             let prism = self.prism();
             let p = prism[0];
@@ -507,6 +534,9 @@ impl Handle {
     }
 
     pub fn div(self, rhs: Handle) -> Handle {
+        if let Some(x) = numeric::mixed(self, rhs, numeric::Op::Div) {
+            return x
+        }
         if self.is_ref() {
             let prism = self.prism();
             let p = prism[0];
@@ -516,6 +546,9 @@ impl Handle {
     }
 
     pub fn rem(self, rhs: Handle) -> Handle {
+        if let Some(x) = numeric::mixed(self, rhs, numeric::Op::Rem) {
+            return x
+        }
         if self.is_ref() {
             let prism = self.prism();
             let p = prism[0];
@@ -525,6 +558,9 @@ impl Handle {
     }
 
     pub fn modulus(self, rhs: Handle) -> Handle {
+        if let Some(x) = numeric::mixed(self, rhs, numeric::Op::Mod) {
+            return x
+        }
         if self.is_ref() {
             let prism = self.prism();
             let p = prism[0];
@@ -0,0 +1,500 @@
+// Copyright (c) Cole Frederick. All rights reserved.
+// The use and distribution terms for this software are covered by the
+// Eclipse Public License 1.0 (https://opensource.org/licenses/eclipse-1.0.php)
+// which can be found in the file epl-v10.html at the root of this distribution.
+// By using this software in any fashion, you are agreeing to be bound by the terms of this license.
+// You must not remove this notice, or any other, from this software.
+
+//! Arbitrary precision integers, as a sign and a magnitude of 64 bit limbs,
+//! least significant first, with no leading zero limbs (zero has none).
+//!
+//! In a segment, the limbs are stored in two's complement, see `to_twos`.
+
+use std::cmp::Ordering;
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BigIntegral {
+    pub negative: bool,
+    pub mag: Vec<u64>,
+}
+
+// Below this many limbs, schoolbook multiplication is quicker.
+const KARATSUBA_CUTOFF: usize = 32;
+
+fn trim(mut v: Vec<u64>) -> Vec<u64> {
+    while v.last() == Some(&0) {
+        v.pop();
+    }
+    v
+}
+
+fn trimmed(v: &[u64]) -> &[u64] {
+    let mut n = v.len();
+    while n > 0 && v[n - 1] == 0 {
+        n -= 1;
+    }
+    &v[..n]
+}
+
+pub fn cmp_mag(a: &[u64], b: &[u64]) -> Ordering {
+    if a.len() != b.len() {
+        return a.len().cmp(&b.len())
+    }
+    for i in (0..a.len()).rev() {
+        if a[i] != b[i] {
+            return a[i].cmp(&b[i])
+        }
+    }
+    Ordering::Equal
+}
+
+pub fn add_mag(a: &[u64], b: &[u64]) -> Vec<u64> {
+    let (a, b) = if a.len() < b.len() { (b, a) } else { (a, b) };
+    let mut r = Vec::with_capacity(a.len() + 1);
+    let mut carry = 0u64;
+    for i in 0..a.len() {
+        let (s, c1) = a[i].overflowing_add(*b.get(i).unwrap_or(&0));
+        let (s, c2) = s.overflowing_add(carry);
+        r.push(s);
+        carry = (c1 as u64) + (c2 as u64);
+    }
+    if carry != 0 {
+        r.push(carry);
+    }
+    r
+}
+
+/// a - b, where a is at least b.
+pub fn sub_mag(a: &[u64], b: &[u64]) -> Vec<u64> {
+    let mut r = Vec::with_capacity(a.len());
+    let mut borrow = 0u64;
+    for i in 0..a.len() {
+        let (d, b1) = a[i].overflowing_sub(*b.get(i).unwrap_or(&0));
+        let (d, b2) = d.overflowing_sub(borrow);
+        r.push(d);
+        borrow = (b1 as u64) + (b2 as u64);
+    }
+    assert_eq!(borrow, 0);
+    trim(r)
+}
+
+// Adds x, shifted up by shift limbs, into r.
+fn add_into(r: &mut Vec<u64>, x: &[u64], shift: usize) {
+    if r.len() < shift + x.len() {
+        r.resize(shift + x.len(), 0);
+    }
+    let mut carry = 0u64;
+    let mut i = 0;
+    while i < x.len() || carry != 0 {
+        if shift + i == r.len() {
+            r.push(0);
+        }
+        let (s, c1) = r[shift + i].overflowing_add(*x.get(i).unwrap_or(&0));
+        let (s, c2) = s.overflowing_add(carry);
+        r[shift + i] = s;
+        carry = (c1 as u64) + (c2 as u64);
+        i += 1;
+    }
+}
+
+fn mul_school(a: &[u64], b: &[u64]) -> Vec<u64> {
+    if a.is_empty() || b.is_empty() {
+        return vec![]
+    }
+    let mut r = vec![0u64; a.len() + b.len()];
+    for i in 0..a.len() {
+        let mut carry = 0u128;
+        for j in 0..b.len() {
+            let t = r[i + j] as u128 + (a[i] as u128) * (b[j] as u128) + carry;
+            r[i + j] = t as u64;
+            carry = t >> 64;
+        }
+        r[i + b.len()] = carry as u64;
+    }
+    trim(r)
+}
+
+fn karatsuba(a: &[u64], b: &[u64]) -> Vec<u64> {
+    let (a, b) = (trimmed(a), trimmed(b));
+    if a.len() < KARATSUBA_CUTOFF || b.len() < KARATSUBA_CUTOFF {
+        return mul_school(a, b)
+    }
+    let half = (a.len().max(b.len()) + 1) / 2;
+    let (a0, a1) = a.split_at(half.min(a.len()));
+    let (b0, b1) = b.split_at(half.min(b.len()));
+    let z0 = karatsuba(a0, b0);
+    let z2 = karatsuba(a1, b1);
+    let z1 = {
+        let m = karatsuba(&add_mag(trimmed(a0), a1), &add_mag(trimmed(b0), b1));
+        sub_mag(&sub_mag(&m, &z0), &z2)
+    };
+    let mut r = z0;
+    add_into(&mut r, &z1, half);
+    add_into(&mut r, &z2, 2 * half);
+    trim(r)
+}
+
+pub fn mul_mag(a: &[u64], b: &[u64]) -> Vec<u64> { karatsuba(a, b) }
+
+fn div_small(a: &[u64], d: u64) -> (Vec<u64>, u64) {
+    let mut q = vec![0u64; a.len()];
+    let mut rem = 0u128;
+    for i in (0..a.len()).rev() {
+        let cur = (rem << 64) | a[i] as u128;
+        q[i] = (cur / d as u128) as u64;
+        rem = cur % d as u128;
+    }
+    (trim(q), rem as u64)
+}
+
+fn shl_bits(a: &[u64], s: u32) -> Vec<u64> {
+    if s == 0 {
+        return a.to_vec()
+    }
+    let mut r = Vec::with_capacity(a.len() + 1);
+    let mut carry = 0u64;
+    for &x in a {
+        r.push((x << s) | carry);
+        carry = x >> (64 - s);
+    }
+    r.push(carry);
+    r
+}
+
+/// Quotient and remainder of magnitudes, by Knuth's algorithm D.
+pub fn divmod_mag(a: &[u64], b: &[u64]) -> (Vec<u64>, Vec<u64>) {
+    if b.is_empty() {
+        panic!("Divide by zero.");
+    }
+    if cmp_mag(a, b) == Ordering::Less {
+        return (vec![], a.to_vec())
+    }
+    if b.len() == 1 {
+        let (q, r) = div_small(a, b[0]);
+        return (q, trim(vec![r]))
+    }
+    let n = b.len();
+    let m = a.len() - n;
+    let s = b[n - 1].leading_zeros();
+    let bn = trim(shl_bits(b, s));
+    let mut an = shl_bits(a, s);
+    if an.len() == a.len() {
+        an.push(0);
+    }
+    let base = 1u128 << 64;
+    let mut q = vec![0u64; m + 1];
+    for j in (0..(m + 1)).rev() {
+        let num = ((an[j + n] as u128) << 64) | an[j + n - 1] as u128;
+        let mut qhat = num / bn[n - 1] as u128;
+        let mut rhat = num % bn[n - 1] as u128;
+        while qhat >= base || qhat * bn[n - 2] as u128 > ((rhat << 64) | an[j + n - 2] as u128) {
+            qhat -= 1;
+            rhat += bn[n - 1] as u128;
+            if rhat >= base {
+                break
+            }
+        }
+        let mut carry = 0u128;
+        let mut borrow = 0u64;
+        for i in 0..n {
+            let p = qhat * bn[i] as u128 + carry;
+            carry = p >> 64;
+            let (t, b1) = an[i + j].overflowing_sub(p as u64);
+            let (t, b2) = t.overflowing_sub(borrow);
+            an[i + j] = t;
+            borrow = (b1 as u64) + (b2 as u64);
+        }
+        let (t, b1) = an[j + n].overflowing_sub(carry as u64);
+        let (t, b2) = t.overflowing_sub(borrow);
+        an[j + n] = t;
+        if b1 || b2 {
+            qhat -= 1;
+            let mut c = 0u128;
+            for i in 0..n {
+                let sum = an[i + j] as u128 + bn[i] as u128 + c;
+                an[i + j] = sum as u64;
+                c = sum >> 64;
+            }
+            an[j + n] = an[j + n].wrapping_add(c as u64);
+        }
+        q[j] = qhat as u64;
+    }
+    let mut r = vec![0u64; n];
+    for i in 0..n {
+        r[i] = if s == 0 { an[i] } else { (an[i] >> s) | (an[i + 1] << (64 - s)) };
+    }
+    (trim(q), trim(r))
+}
+
+impl BigIntegral {
+    pub fn zero() -> BigIntegral { BigIntegral { negative: false, mag: vec![] } }
+    pub fn from_i64(x: i64) -> BigIntegral {
+        BigIntegral { negative: x < 0, mag: trim(vec![x.unsigned_abs()]) }
+    }
+    fn signed(negative: bool, mag: Vec<u64>) -> BigIntegral {
+        let mag = trim(mag);
+        BigIntegral { negative: negative && !mag.is_empty(), mag }
+    }
+    pub fn is_zero(&self) -> bool { self.mag.is_empty() }
+    pub fn to_i64(&self) -> Option<i64> {
+        match self.mag.len() {
+            0 => Some(0),
+            1 if self.negative => {
+                if self.mag[0] <= 1 << 63 { Some((self.mag[0] as i64).wrapping_neg()) } else { None }
+            },
+            1 => if self.mag[0] < 1 << 63 { Some(self.mag[0] as i64) } else { None },
+            _ => None,
+        }
+    }
+
+    pub fn neg(&self) -> BigIntegral { BigIntegral::signed(!self.negative, self.mag.clone()) }
+    pub fn abs(&self) -> BigIntegral { BigIntegral::signed(false, self.mag.clone()) }
+
+    pub fn add(&self, other: &BigIntegral) -> BigIntegral {
+        if self.negative == other.negative {
+            return BigIntegral::signed(self.negative, add_mag(&self.mag, &other.mag))
+        }
+        match cmp_mag(&self.mag, &other.mag) {
+            Ordering::Less => BigIntegral::signed(other.negative, sub_mag(&other.mag, &self.mag)),
+            _ => BigIntegral::signed(self.negative, sub_mag(&self.mag, &other.mag)),
+        }
+    }
+    pub fn sub(&self, other: &BigIntegral) -> BigIntegral { self.add(&other.neg()) }
+    pub fn mul(&self, other: &BigIntegral) -> BigIntegral {
+        BigIntegral::signed(self.negative != other.negative, mul_mag(&self.mag, &other.mag))
+    }
+
+    /// Quotient truncated toward zero, and a remainder with the sign of self.
+    pub fn divmod(&self, other: &BigIntegral) -> (BigIntegral, BigIntegral) {
+        let (q, r) = divmod_mag(&self.mag, &other.mag);
+        (BigIntegral::signed(self.negative != other.negative, q), BigIntegral::signed(self.negative, r))
+    }
+    /// Remainder with the sign of the divisor.
+    pub fn modulo(&self, other: &BigIntegral) -> BigIntegral {
+        let r = self.divmod(other).1;
+        if !r.is_zero() && r.negative != other.negative { r.add(other) } else { r }
+    }
+
+    pub fn pow(&self, mut exp: u32) -> BigIntegral {
+        let mut base = self.clone();
+        let mut r = BigIntegral::from_i64(1);
+        while exp != 0 {
+            if exp & 1 == 1 {
+                r = r.mul(&base);
+            }
+            exp >>= 1;
+            if exp != 0 {
+                base = base.mul(&base);
+            }
+        }
+        r
+    }
+    /// self to the power exp, modulo m, for a non-negative exp and positive m.
+    pub fn modpow(&self, exp: &BigIntegral, m: &BigIntegral) -> BigIntegral {
+        assert!(!exp.negative, "Negative exponent in modpow.");
+        assert!(!m.negative && !m.is_zero(), "Modpow needs a positive modulus.");
+        let mut base = self.modulo(m);
+        let mut r = BigIntegral::from_i64(1).modulo(m);
+        for (i, &limb) in exp.mag.iter().enumerate() {
+            let last = i + 1 == exp.mag.len();
+            let bits = if last { 64 - limb.leading_zeros() } else { 64 };
+            for k in 0..bits {
+                if (limb >> k) & 1 == 1 {
+                    r = r.mul(&base).modulo(m);
+                }
+                base = base.mul(&base).modulo(m);
+            }
+        }
+        r
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        let x = self.mag.iter().rev().fold(0f64, |x, &limb| x * 18446744073709551616.0 + limb as f64);
+        if self.negative { -x } else { x }
+    }
+
+    /// Greatest common divisor, never negative.
+    pub fn gcd(&self, other: &BigIntegral) -> BigIntegral {
+        let (mut a, mut b) = (self.abs(), other.abs());
+        while !b.is_zero() {
+            let r = a.divmod(&b).1;
+            a = b;
+            b = r;
+        }
+        a
+    }
+    pub fn pow10(exp: u32) -> BigIntegral { BigIntegral::from_i64(10).pow(exp) }
+    /// Count of decimal digits in the magnitude, zero having one.
+    pub fn digits(&self) -> u32 {
+        let s = format!("{}", self.abs());
+        s.len() as u32
+    }
+
+    /// Minimal big-endian two's complement bytes, as java's BigInteger.toByteArray.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = self.to_twos().iter().rev().flat_map(|limb| limb.to_be_bytes().to_vec()).collect();
+        let fill = if self.negative { 0xFF } else { 0x00 };
+        while bytes.len() > 1 && bytes[0] == fill && (bytes[1] & 0x80 == fill & 0x80) {
+            bytes.remove(0);
+        }
+        bytes
+    }
+    pub fn from_bytes(bytes: &[u8]) -> BigIntegral {
+        let fill = if bytes.first().map_or(false, |&b| b & 0x80 != 0) { 0xFF } else { 0x00 };
+        let mut padded = vec![fill; (8 - bytes.len() % 8) % 8];
+        padded.extend_from_slice(bytes);
+        let limbs: Vec<u64> = padded.chunks(8).rev().map(|c| {
+            c.iter().fold(0u64, |x, &b| (x << 8) | b as u64)
+        }).collect();
+        BigIntegral::from_twos(&limbs)
+    }
+
+    /// Parses digits in a radix (2 to 36), skipping underscores.
+    pub fn from_radix(negative: bool, digits: &[u8], radix: u32) -> Option<BigIntegral> {
+        let mut mag: Vec<u64> = vec![];
+        for b in digits.iter() {
+            if *b == b'_' {
+                continue
+            }
+            let d = (*b as char).to_digit(radix)? as u64;
+            let mut carry = d as u128;
+            for limb in mag.iter_mut() {
+                let t = (*limb as u128) * radix as u128 + carry;
+                *limb = t as u64;
+                carry = t >> 64;
+            }
+            if carry != 0 {
+                mag.push(carry as u64);
+            }
+        }
+        Some(BigIntegral::signed(negative, mag))
+    }
+
+    /// Limbs in two's complement, with just enough to hold the sign.
+    pub fn to_twos(&self) -> Vec<u64> {
+        let mut v = self.mag.clone();
+        if self.negative {
+            let mut carry = true;
+            for limb in v.iter_mut() {
+                let (x, c) = (!*limb).overflowing_add(carry as u64);
+                *limb = x;
+                carry = c;
+            }
+            if v.last().map_or(true, |&top| top >> 63 == 0) {
+                v.push(u64::max_value());
+            }
+        } else if v.last().map_or(true, |&top| top >> 63 == 1) {
+            v.push(0);
+        }
+        v
+    }
+    pub fn from_twos(limbs: &[u64]) -> BigIntegral {
+        let negative = limbs.last().map_or(false, |&top| top >> 63 == 1);
+        if !negative {
+            return BigIntegral::signed(false, limbs.to_vec())
+        }
+        let mut carry = true;
+        let mag = limbs.iter().map(|&limb| {
+            let (x, c) = (!limb).overflowing_add(carry as u64);
+            carry = c;
+            x
+        }).collect();
+        BigIntegral::signed(true, mag)
+    }
+}
+
+impl PartialOrd for BigIntegral {
+    fn partial_cmp(&self, other: &BigIntegral) -> Option<Ordering> { Some(self.cmp(other)) }
+}
+impl Ord for BigIntegral {
+    fn cmp(&self, other: &BigIntegral) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => cmp_mag(&self.mag, &other.mag),
+            (true, true) => cmp_mag(&other.mag, &self.mag),
+        }
+    }
+}
+
+impl fmt::Display for BigIntegral {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Peels off 19 decimal digits at a time.
+        const CHUNK: u64 = 10_000_000_000_000_000_000;
+        let mut chunks = vec![];
+        let mut mag = self.mag.clone();
+        while !mag.is_empty() {
+            let (q, r) = div_small(&mag, CHUNK);
+            chunks.push(r);
+            mag = q;
+        }
+        if self.negative {
+            write!(f, "-")?;
+        }
+        match chunks.pop() {
+            None => write!(f, "0"),
+            Some(top) => {
+                write!(f, "{}", top)?;
+                for c in chunks.iter().rev() {
+                    write!(f, "{:019}", c)?;
+                }
+                Ok(())
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn big(s: &str) -> BigIntegral {
+        let (negative, digits) = if s.starts_with('-') { (true, &s[1..]) } else { (false, s) };
+        BigIntegral::from_radix(negative, digits.as_bytes(), 10).unwrap()
+    }
+
+    #[test]
+    fn arithmetic() {
+        let a = big("123456789012345678901234567890");
+        let b = big("-987654321098765432109876543210");
+        assert_eq!(format!("{}", a.add(&b)), "-864197532086419753208641975320");
+        assert_eq!(format!("{}", a.sub(&b)), "1111111110111111111011111111100");
+        assert_eq!(format!("{}", a.mul(&b)),
+                   "-121932631137021795226185032733622923332237463801111263526900");
+        let (q, r) = b.divmod(&a);
+        assert_eq!((format!("{}", q), format!("{}", r)), ("-8".to_string(), "-9000000000900000000090".to_string()));
+        assert_eq!(format!("{}", b.modulo(&a)), "123456780012345678001234567800");
+        assert_eq!(format!("{}", big("2").pow(100)), "1267650600228229401496703205376");
+        assert_eq!(big("4").modpow(&big("13"), &big("497")), big("445"));
+        assert!(b < a && a.neg() == big("-123456789012345678901234567890"));
+        assert_eq!(BigIntegral::from_i64(i64::min_value()).to_i64(), Some(i64::min_value()));
+        assert_eq!(big("9223372036854775808").to_i64(), None);
+        assert_eq!(big("-84").gcd(&big("36")), big("12"));
+        assert_eq!(big("-129").to_bytes(), vec![0xFF, 0x7F]);
+        assert_eq!(big("128").to_bytes(), vec![0x00, 0x80]);
+        assert_eq!(big("-1").to_bytes(), vec![0xFF]);
+        assert_eq!(BigIntegral::from_bytes(&a.to_bytes()), a);
+        assert_eq!(BigIntegral::from_bytes(&b.to_bytes()), b);
+        for x in [0i64, 1, -1, i64::max_value(), i64::min_value()].iter() {
+            let b = BigIntegral::from_i64(*x);
+            assert_eq!(BigIntegral::from_twos(&b.to_twos()), b);
+        }
+    }
+
+    #[test]
+    fn large_multiply_and_divide() {
+        // Enough limbs for karatsuba, checked against schoolbook.
+        let a = big("7").pow(2000).add(&big("12345"));
+        let b = big("-3").pow(1801).sub(&big("99"));
+        let p = a.mul(&b);
+        assert_eq!(p.mag, mul_school(&a.mag, &b.mag));
+        let (q, r) = p.sub(&big("5")).divmod(&a);
+        assert_eq!(q, b);
+        assert_eq!(r, big("-5"));
+        let (q, r) = a.divmod(&b);
+        assert_eq!(q.mul(&b).add(&r), a);
+        assert!(cmp_mag(&r.mag, &b.mag) == Ordering::Less);
+        assert_eq!(big(&format!("{}", p)), p);
+    }
+}
@@ -12,10 +12,12 @@ use memory::*;
 /// precision, and if so, how many units hold the significant bits.
 
 /// `Top 32 bits  [                    Hash  (32) ]`
-/// `Bottom bits  [ Big? | Significant units (16) ]`
+/// `Bottom bits  [ Big? | Significant units (31) ]`
 ///
 
 
+const COUNT_MASK: u32 = 0x7FFF_FFFF;
+
 #[derive(Copy, Clone, Debug)]
 pub struct Guide {
     pub hash: u32,
@@ -45,6 +47,7 @@ impl Guide {
     }
     pub fn is_big(&self) -> bool { self.is_big_bit == 1 }
     pub fn set_count(mut self, count: u32) -> Guide {
+        assert!(count <= COUNT_MASK, "Integral of {} units is too big.", count);
         self.unit_count = count;
         self.clear_hash()
     }
@@ -67,7 +70,7 @@ impl Guide {
     pub fn hydrate_top_bot(prism: AnchoredLine, top: u32, bot: u32) -> Guide {
         let hash = top;
         let is_big_bit = (bot >> 31) & 1;
-        let unit_count = bot & 0xFFFF;
+        let unit_count = bot & COUNT_MASK;
 
         let root_offset = 1 /*prism*/ + Guide::units();
         let root = prism.offset(root_offset as i32);
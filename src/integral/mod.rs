@@ -14,6 +14,8 @@ use Value;
 
 pub mod guide;
 use self::guide::Guide;
+pub mod big;
+use self::big::BigIntegral;
 
 // Numbers. immediate i60 (28), f60 (28). boxed integral, rational, float point.
 // Layout: i60I f60F, [prism guide{chunk_count} contents]
@@ -57,18 +59,18 @@ pub fn parse(negate: bool, m: &[u8], promote: bool) -> Handle {
         if *b == b'_' {
             continue
         }
-        x = x * 10 + (*b - b'0') as i64;
+        x = match x.checked_mul(10).and_then(|y| y.checked_add((*b - b'0') as i64)) {
+            Some(y) => y,
+            None => return parsed(BigIntegral::from_radix(negate, m, 10).unwrap(), false),
+        };
     }
     if negate { x = -x; }
-    let guide = {
-        let g = blank();
-        if promote { g.set_big() } else { g }
-    };
-    store(guide.root, x);
-    guide.store().segment().unit().handle()
+    new(x).handle()
 }
 pub fn parse_hex(negate: bool, m: &[u8], promote: bool) -> Handle {
-    if promote { unimplemented!() }
+    if promote || m.len() > 15 {
+        return parsed(BigIntegral::from_radix(negate, m, 16).unwrap(), promote)
+    }
     let mut x = 0i64;
     for b in m.iter() {
         if *b == b'_' {
@@ -81,12 +83,7 @@ pub fn parse_hex(negate: bool, m: &[u8], promote: bool) -> Handle {
         x = (x << 4) + d as i64;
     }
     if negate { x = -x; }
-    let guide = {
-        let g = blank();
-        if promote { g.set_big() } else { g }
-    };
-    store(guide.root, x);
-    guide.store().segment().unit().handle()
+    new(x).handle()
 }
 pub fn parse_radix(negate: bool, radix: u32, m: &[u8]) -> Option<Handle> {
     let mut x = 0i64;
@@ -98,14 +95,63 @@ pub fn parse_radix(negate: bool, radix: u32, m: &[u8]) -> Option<Handle> {
         else if *b <= b'Z' { *b - b'A' + 10 }
         else { *b - b'a' + 10 };
         if d >= radix as u8 { return None }
-        x = x * radix as i64 + d as i64;
+        x = match x.checked_mul(radix as i64).and_then(|y| y.checked_add(d as i64)) {
+            Some(y) => y,
+            None => return BigIntegral::from_radix(negate, m, radix).map(|b| parsed(b, false)),
+        };
     }
     if negate { x = -x; }
     Some(new(x).handle())
 }
+// Overflowing digits are promoted, and a promoted number stays big even if it fits.
+fn parsed(b: BigIntegral, promote: bool) -> Handle {
+    match b.to_i64() {
+        Some(x) if !promote => new(x).handle(),
+        _ => new_big(&b).handle(),
+    }
+}
+
+fn units_per_limb() -> u32 { if cfg!(target_pointer_width = "32") { 2 } else { 1 } }
+
+/// Stores the limbs of b in two's complement, after the guide.
+pub fn new_big(b: &BigIntegral) -> Unit {
+    let limbs = b.to_twos();
+    let per = units_per_limb();
+    let count = limbs.len() as u32 * per;
+    let s = Segment::new(1 /*prism*/ + Guide::units() + count);
+    let prism = s.line_at(0);
+    prism.set(0, prism_unit());
+    let guide = Guide::new(prism).set_big().set_count(count);
+    for (i, limb) in limbs.iter().enumerate() {
+        store(guide.root.offset((i as u32 * per) as i32), *limb as i64);
+    }
+    guide.store().segment().unit()
+}
+pub fn new_big_value(b: &BigIntegral) -> Value { new_big(b).handle().value() }
+
+pub fn is_big(prism: AnchoredLine) -> bool { Guide::hydrate(prism).is_big() }
+
+/// The value of an integral as a BigIntegral, big or not.
+pub fn to_big(prism: AnchoredLine) -> BigIntegral {
+    let guide = Guide::hydrate(prism);
+    if !guide.is_big() {
+        return BigIntegral::from_i64(hydrate(guide.root))
+    }
+    let per = units_per_limb();
+    let limbs: Vec<u64> = (0..(guide.unit_count / per))
+        .map(|i| hydrate(guide.root.offset((i * per) as i32)) as u64).collect();
+    BigIntegral::from_twos(&limbs)
+}
+
 pub fn as_i64(prism: AnchoredLine) -> i64 {
     assert!(is_prism(prism));
     let guide = Guide::hydrate(prism);
+    if guide.is_big() {
+        return match to_big(prism).to_i64() {
+            Some(x) => x,
+            None => panic!("Integral {} doesn't fit in 64 bits.", to_big(prism)),
+        }
+    }
     let x = hydrate(guide.root);
     x
 }
@@ -140,9 +186,18 @@ impl Distinguish for Integral_ {
         if guide.has_hash() { return guide.hash; }
 
         let h = {
-            use hash::hash_64;
-            let x = hydrate(guide.root) as u64;
-            hash_64(x, 8)
+            use hash::{hash_64, hash_128};
+            // A big that fits in 64 bits hashes as the small integral it equals.
+            if guide.is_big() {
+                let b = to_big(prism);
+                match b.to_i64() {
+                    Some(x) => hash_64(x as u64, 8),
+                    None => b.to_twos().iter().fold(0, |h, limb| hash_128(h as u64, *limb, 16)),
+                }
+            } else {
+                let x = hydrate(guide.root) as u64;
+                hash_64(x, 8)
+            }
         };
         //log!("Hash integral {} {:#08X}", prism.segment().unit().handle(), h);
         guide.set_hash(h).store_hash().hash
@@ -152,6 +207,9 @@ impl Distinguish for Integral_ {
         if let Some(o_int) = find_prism(o) {
             let guide = Guide::hydrate(prism);
             let guide2 = Guide::hydrate(o_int);
+            if guide.is_big() || guide2.is_big() {
+                return to_big(prism) == to_big(o_int)
+            }
             let x = hydrate(guide.root);
             let y = hydrate(guide2.root);
             return x == y
@@ -163,6 +221,9 @@ impl Distinguish for Integral_ {
         if let Some(o_int) = find_prism(o) {
             let guide = Guide::hydrate(prism);
             let guide2 = Guide::hydrate(o_int);
+            if guide.is_big() || guide2.is_big() {
+                return Some(to_big(prism).cmp(&to_big(o_int)))
+            }
             let x = hydrate(guide.root);
             let y = hydrate(guide2.root);
             return Some(x.cmp(&y))
@@ -183,13 +244,63 @@ impl Sorted for Integral_ {}
 impl Notation for Integral_ {
     fn edn(&self, prism: AnchoredLine, f: &mut fmt::Formatter) -> fmt::Result {
         let guide = Guide::hydrate(prism);
+        if guide.is_big() {
+            return write!(f, "{}N", to_big(prism))
+        }
         let x = hydrate(guide.root);
         write!(f, "{}", x)
     }
 }
+
+/// A big result that fits in 64 bits is demoted to a small integral.
+pub fn demoted(b: &BigIntegral) -> Unit {
+    match b.to_i64() {
+        Some(x) => new(x),
+        None => new_big(b),
+    }
+}
+
+// Applies op to two integrals, as i64s when both are small and the result
+// fits, else as bigs. Consumes both operands.
+fn binary(prism: AnchoredLine, other: Unit,
+          small: fn(i64, i64) -> Option<i64>,
+          big: fn(&BigIntegral, &BigIntegral) -> BigIntegral) -> Unit {
+    let o = other.handle();
+    let o_int = match find_prism(o) {
+        Some(p) => p,
+        None => panic!("Can't do arithmetic on Integral and {}.", o.type_name()),
+    };
+    let guide = Guide::hydrate(prism);
+    let guide2 = Guide::hydrate(o_int);
+    let fast = if guide.is_big() || guide2.is_big() { None } else {
+        small(hydrate(guide.root), hydrate(guide2.root))
+    };
+    let res = match fast {
+        Some(z) => new(z),
+        None => demoted(&big(&to_big(prism), &to_big(o_int))),
+    };
+    guide.segment().unit().handle().retire();
+    o.retire();
+    res
+}
+fn unary(prism: AnchoredLine, small: fn(i64) -> Option<i64>,
+         big: fn(&BigIntegral) -> BigIntegral) -> Unit {
+    let guide = Guide::hydrate(prism);
+    let fast = if guide.is_big() { None } else { small(hydrate(guide.root)) };
+    let res = match fast {
+        Some(z) => new(z),
+        None => demoted(&big(&to_big(prism))),
+    };
+    guide.segment().unit().handle().retire();
+    res
+}
+
 impl Numeral for Integral_ {
     fn inc(&self, prism: AnchoredLine) -> Unit {
         let guide = Guide::hydrate(prism);
+        if guide.is_big() || hydrate(guide.root) == i64::max_value() {
+            return unary(prism, |x| x.checked_add(1), |b| b.add(&BigIntegral::from_i64(1)))
+        }
         let x = hydrate(guide.root);
         let s = guide.segment();
         if s.is_aliased() {
@@ -203,62 +314,82 @@ impl Numeral for Integral_ {
         }
     }
     fn dec(&self, prism: AnchoredLine) -> Unit {
-        unimplemented!()
+        unary(prism, |x| x.checked_sub(1), |b| b.sub(&BigIntegral::from_i64(1)))
     }
     fn add(&self, prism: AnchoredLine, other: Unit) -> Unit {
-        let o = other.handle();
-        if let Some(o_int) = find_prism(o) {
-            let guide = Guide::hydrate(prism);
-            let guide2 = Guide::hydrate(o_int);
-            let x = hydrate(guide.root);
-            let y = hydrate(guide2.root);
-            guide.segment().unit().handle().retire();
-            guide2.segment().unit().handle().retire();
-            if let Some(sum) = x.checked_add(y) {
-                return new(sum)
-            } else {
-                panic!("Overflowing add of {} + {}", x, y);
-            }
-        }
-        unimplemented!()
+        binary(prism, other, |x, y| x.checked_add(y), |a, b| a.add(b))
     }
     fn subtract(&self, prism: AnchoredLine, other: Unit) -> Unit {
-        unimplemented!()
+        binary(prism, other, |x, y| x.checked_sub(y), |a, b| a.sub(b))
     }
     fn neg(&self, prism: AnchoredLine) -> Unit {
-        unimplemented!()
+        unary(prism, |x| x.checked_neg(), |b| b.neg())
     }
     fn multiply(&self, prism: AnchoredLine, other: Unit) -> Unit {
-        unimplemented!()
+        binary(prism, other, |x, y| x.checked_mul(y), |a, b| a.mul(b))
     }
     fn divide(&self, prism: AnchoredLine, other: Unit) -> Unit {
-        unimplemented!()
+        // An inexact quotient is a rational.
+        let o = other.handle();
+        let o_int = match find_prism(o) {
+            Some(p) => p,
+            None => panic!("Can't do arithmetic on Integral and {}.", o.type_name()),
+        };
+        use rational;
+        let res = rational::ratio(&to_big(prism), &to_big(o_int)).unit();
+        prism.segment().unit().handle().retire();
+        o.retire();
+        res
     }
     fn remainder(&self, prism: AnchoredLine, other: Unit) -> Unit {
-        unimplemented!()
+        binary(prism, other, |x, y| x.checked_rem(y), |a, b| a.divmod(b).1)
     }
     fn modulus(&self, prism: AnchoredLine, other: Unit) -> Unit {
-        unimplemented!()
+        binary(prism, other, |x, y| x.checked_rem(y).map(|r| {
+            if r != 0 && (r < 0) != (y < 0) { r + y } else { r }
+        }), BigIntegral::modulo)
     }
 }
 impl Callable for Integral_ {}
 
-
 pub fn big_int(negate: bool, m: &[u8]) -> Handle {
-    /*
-    use std::str::from_utf8;
-    let temp = format!("{}{}N", if negate { "-" } else { "" }, from_utf8(m).unwrap());
-    let t = string::new_from_str(&temp).unit();
-    let needed = 1 /*prism*/ + 1 /*string*/;
-    let s = Segment::new(needed);
-    let prism = s.line_at(0);
-    prism.set(0, mechanism::prism::<BigInt>());
-    prism.set(1, t);
-    s.unit().handle()
-    */
-    unimplemented!()
+    new_big(&BigIntegral::from_radix(negate, m, 10).unwrap()).handle()
+}
+
+fn big_of(v: &Value) -> BigIntegral {
+    match find_prism(v._handle()) {
+        Some(prism) => to_big(prism),
+        None => panic!("Not an integral: {}", v),
+    }
+}
+fn result(b: BigIntegral) -> Value { demoted(&b).handle().value() }
+
+/// Quotient truncated toward zero, and the remainder, with the sign of x.
+pub fn divmod(x: &Value, y: &Value) -> (Value, Value) {
+    let (quot, rem) = big_of(x).divmod(&big_of(y));
+    (result(quot), result(rem))
+}
+pub fn pow(x: &Value, exp: u32) -> Value { result(big_of(x).pow(exp)) }
+/// x to the power exp, modulo m.
+pub fn modpow(x: &Value, exp: &Value, m: &Value) -> Value {
+    result(big_of(x).modpow(&big_of(exp), &big_of(m)))
 }
 
+// Strict arithmetic stays in 64 bits, overflow is an error instead of a promotion.
+fn strict(x: &Value, y: &Value, name: &str, op: fn(i64, i64) -> Option<i64>) -> Result<Value, String> {
+    let (a, b) = (big_of(x), big_of(y));
+    match (a.to_i64(), b.to_i64()) {
+        (Some(p), Some(q)) => match op(p, q) {
+            Some(z) => Ok(new_value(z)),
+            None => Err(format!("Integer overflow in {} of {} and {}.", name, p, q)),
+        },
+        _ => Err(format!("Integer overflow, {} of {} and {} has an operand past 64 bits.", name, a, b)),
+    }
+}
+pub fn add_strict(x: &Value, y: &Value) -> Result<Value, String> { strict(x, y, "add", |p, q| p.checked_add(q)) }
+pub fn sub_strict(x: &Value, y: &Value) -> Result<Value, String> { strict(x, y, "subtract", |p, q| p.checked_sub(q)) }
+pub fn mul_strict(x: &Value, y: &Value) -> Result<Value, String> { strict(x, y, "multiply", |p, q| p.checked_mul(q)) }
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -273,6 +404,56 @@ mod tests {
         assert_eq!(v.inc(), 6.into());
     }
     #[test]
+    fn bigs() {
+        let x: Value = "123456789012345678901234567890".parse().unwrap();
+        assert_eq!(format!("{}", x), "123456789012345678901234567890N");
+        assert_eq!(format!("{}", "-0x1_0000_0000_0000_0000".parse::<Value>().unwrap()), "-18446744073709551616N");
+        assert_eq!(format!("{}", "36r1234567890ABCDEF".parse::<Value>().unwrap()), "233887183639219338830295N");
+        let n: Value = "7N".parse().unwrap();
+        assert_eq!(format!("{}", n), "7N");
+        assert_eq!(n, 7.into());
+        assert_eq!(n.hash(), Value::from(7).hash());
+        assert_eq!("-9223372036854775808".parse::<Value>(), Ok(i64::min_value().into()));
+        let y = &x * &x - &x + n.split_out();
+        assert_eq!(format!("{}", y), "15241578753238836750495351562412741998489559520973784484217N");
+        assert!(x < y && -(&x) < n);
+        let (q, r) = divmod(&y, &x);
+        assert_eq!((format!("{}", q), format!("{}", r)), ("123456789012345678901234567889N".to_string(), "7".to_string()));
+        assert_eq!(format!("{}", pow(&Value::from(3), 50)), "717897987691852588770249N");
+        assert_eq!(modpow(&Value::from(4), &Value::from(13), &Value::from(497)), 445.into());
+    }
+    #[test]
+    fn huge() {
+        // More limbs than a 16 bit count holds
+        let mut mag = vec![0u64; 65_536];
+        mag[0] = 5;
+        mag[65_535] = 1;
+        let b = BigIntegral { negative: true, mag };
+        let v = new_big_value(&b);
+        assert_eq!(to_big(find_prism(v._handle()).unwrap()), b);
+        assert_eq!(v, new_big_value(&b));
+        assert!(v < -(&v));
+    }
+    #[test]
+    fn promotion() {
+        let max: Value = i64::max_value().into();
+        let big = &max + &Value::from(1);
+        assert_eq!(format!("{}", big), "9223372036854775808N");
+        assert_eq!(format!("{}", max.split_out().inc()), "9223372036854775808N");
+        let back = big - Value::from(1);
+        assert_eq!(format!("{}", back), "9223372036854775807");
+        assert!(find_prism(back._handle()).map_or(false, |p| !is_big(p)));
+        let min: Value = i64::min_value().into();
+        assert_eq!(format!("{}", -(&min)), "9223372036854775808N");
+        assert_eq!(format!("{}", &min * &Value::from(2)), "-18446744073709551616N");
+        assert_eq!(format!("{}", (&min - &Value::from(1)).dec()), "-9223372036854775810N");
+        assert_eq!(Value::from(3) * Value::from(4), 12.into());
+        assert_eq!(add_strict(&Value::from(1), &Value::from(2)), Ok(3.into()));
+        assert!(add_strict(&max, &Value::from(1)).is_err());
+        assert!(mul_strict(&min, &Value::from(-1)).is_err());
+        assert_eq!(sub_strict(&min, &Value::from(-1)), Ok((i64::min_value() + 1).into()));
+    }
+    #[test]
     fn add() {
         let v: Value = 1.into();
         let w: Value = 2.into();
@@ -6,6 +6,13 @@
 // You must not remove this notice, or any other, from this software.
 
 //! A cohesive fressian library for rust
+//!
+//! The default build has the `anchor_non_atomic` feature, counting aliases
+//! without atomics, so values stay on the thread that made them. Channels
+//! carry values between threads: the `channel` module and `chan`, `chan_take`,
+//! `chan_put`, `offer`, `poll`, `promise_chan`, `close` and friends are only
+//! present in the atomic build, without default features:
+//! `--no-default-features --features "eval trace segment"`.
 #![doc(html_logo_url = "/images/logo/shield.svg")]
 #![doc(html_favicon_url = "/favicon.ico")]
 #![allow(unused_variables)]
@@ -19,9 +26,11 @@ pub mod trace;
 
 pub mod array;
 pub mod atom;
+#[cfg(not(feature = "anchor_non_atomic"))]
 pub mod channel;
 pub mod character;
 pub mod compress;
+pub mod decimal;
 pub mod dispatch;
 pub mod edn;
 #[cfg(feature = "eval")]
@@ -39,6 +48,7 @@ pub mod list;
 pub mod map;
 pub mod memory;
 pub mod meta;
+pub mod numeric;
 pub mod queue;
 pub mod random;
 pub mod range;
@@ -99,6 +109,7 @@ pub fn conj(c: Value, v: Value) -> Value { c.conj(v) }
 pub fn pop(c: Value) -> (Value, Value) { c.pop() }
 pub fn peek(c: &Value) -> &Value { c.peek() }
 pub fn nth(c: &Value, idx: u32) -> &Value { c.nth(idx) }
+pub fn nth_out(c: &Value, idx: u32) -> Value { c.nth_out(idx) }
 pub fn nth_set(c: Value, idx: u32, v: Value) -> Value { c.nth_set(idx, v) }
 pub fn swap_idx(c: Value, i: u32, j: u32) -> Value { c.swap_idx(i, j) }
 pub fn mth(c: &Value, idx: i32) -> &Value { c.mth(idx) } // modular nth
@@ -109,21 +120,27 @@ pub fn get<'a>(c: &'a Value, k: &Value) -> &'a Value { c.get(k) }
 
 pub fn inc(x: Value) -> Value { x.inc() }
 pub fn dec(x: Value) -> Value { x.dec() }
-pub fn max(x: Value, y: Value) -> Value { unimplemented!() }
-pub fn min(x: Value, y: Value) -> Value { unimplemented!() }
+pub fn max(x: Value, y: Value) -> Value { numeric::max(x, y) }
+pub fn min(x: Value, y: Value) -> Value { numeric::min(x, y) }
 pub fn neg(x: Value) -> Value { -x }
-pub fn abs(x: Value) -> Value { unimplemented!() }
-pub fn is_zero(x: &Value) -> bool { unimplemented!() }
-pub fn is_pos(x: &Value) -> bool { unimplemented!() }
-pub fn is_neg(x: &Value) -> bool { unimplemented!() }
+pub fn abs(x: Value) -> Value { numeric::abs(x) }
+pub fn is_zero(x: &Value) -> bool { numeric::is_zero(x) }
+pub fn is_pos(x: &Value) -> bool { numeric::is_pos(x) }
+pub fn is_neg(x: &Value) -> bool { numeric::is_neg(x) }
 pub fn is_nat(x: &Value) -> bool { !is_neg(x) }
 pub fn add(x: Value, y: Value) -> Value { x + y }
 pub fn sub(x: Value, y: Value) -> Value { x - y }
 pub fn mul(x: Value, y: Value) -> Value { x * y }
 pub fn div(x: Value, y: Value) -> Value { x / y }
-pub fn quot(x: Value, y: Value) -> Value { unimplemented!() }
+pub fn quot(x: Value, y: Value) -> Value { numeric::quot(&x, &y) }
 pub fn rem(x: Value, y: Value) -> Value { x % y }
 pub fn modulus(x: Value, y: Value) -> Value { x.modulus(y) }
+pub fn add_strict(x: &Value, y: &Value) -> Result<Value, String> { integral::add_strict(x, y) }
+pub fn sub_strict(x: &Value, y: &Value) -> Result<Value, String> { integral::sub_strict(x, y) }
+pub fn mul_strict(x: &Value, y: &Value) -> Result<Value, String> { integral::mul_strict(x, y) }
+pub fn is_decimal(v: &Value) -> bool { decimal::is_decimal(v._handle()) }
+pub fn decimal_div(x: &Value, y: &Value, ctx: &decimal::Context) -> Result<Value, String> { decimal::div(x, y, ctx) }
+pub fn decimal_round(x: &Value, ctx: &decimal::Context) -> Result<Value, String> { decimal::round(x, ctx) }
 pub fn shl(x: Value, shift: u32) -> Value { x << shift }
 pub fn shr(x: Value, shift: u32) -> Value { x >> shift }
 
@@ -134,6 +151,12 @@ pub fn vector()   -> Value { vector::new_value() }
 pub fn list()     -> Value { list::new_value() }
 pub fn hash_map() -> Value { map::new_value() }
 pub fn hash_set() -> Value { set::new_value() }
+pub fn queue()    -> Value { queue::new_value() }
+pub fn priority_queue() -> Value { queue::priority::new_value() }
+pub fn priority_queue_by(key: impl Fn(&Value) -> Value + transduce::Shared + 'static) -> Value {
+    queue::priority::new_value_by(key)
+}
+pub fn pq_merge(p: Value, q: Value) -> Value { queue::priority::merge(p._consume(), q._consume()).value() }
 pub fn sort_map() -> Value { unimplemented!() }
 pub fn sort_set() -> Value { unimplemented!() }
 
@@ -143,22 +166,33 @@ pub fn difference(s: Value, t: Value) -> Value { unimplemented!() }
 pub fn intersection(s: Value, t: Value) -> Value { unimplemented!() }
 pub fn is_subset(s: &Value, t: &Value) -> bool { unimplemented!() }
 pub fn is_superset(s: &Value, t: &Value) -> bool { unimplemented!() }
-pub fn into(sink: Value, xf: transduce::Transducers, source: Value) -> Value { source.pour(xf, sink) }
+pub fn into<X: Into<transduce::Transducers>>(sink: Value, xf: X, source: Value) -> Value { source.pour(xf.into(), sink) }
 pub fn right_into(sink: Value, source: Value) -> Value { into(sink, transduce::Transducers::new(), source) }
-pub fn reduce(red: u32, xf: u32, f: u32) -> Value { unimplemented!() }
-pub fn educe(red: u32, xf: u32) -> u32 { unimplemented!() }
-pub fn max_key(c: &Value, f: u32) -> &Value { unimplemented!() }
-pub fn min_key(c: &Value, f: u32) -> &Value { unimplemented!() }
+pub fn reduce(f: impl Fn(Value, Value) -> Value + 'static, init: Value, coll: Value) -> Value {
+    transduce::reduce(f, init, coll)
+}
+pub fn transduce<X: Into<transduce::Transducers>>(xf: X, f: impl Fn(Value, Value) -> Value + 'static,
+                                                  init: Value, coll: Value) -> Value {
+    transduce::transduce(xf, f, init, coll)
+}
+pub fn educe<X: Into<transduce::Transducers>>(xf: X, coll: Value) -> Value { transduce::educe(xf, coll) }
+pub fn fold<X: Into<transduce::Transducers>>(coll: Value, n: u32,
+                                            combine: impl Fn(Value, Value) -> Value + transduce::Shared + 'static,
+                                            xf: X) -> Value {
+    transduce::fold::fold(coll, n, combine, xf)
+}
+pub fn max_key(c: &Value, f: impl Fn(&Value) -> Value + 'static) -> Value { transduce::max_key(c, f) }
+pub fn min_key(c: &Value, f: impl Fn(&Value) -> Value + 'static) -> Value { transduce::min_key(c, f) }
 pub fn zipmap(ks: Value, vs: Value) -> Value { unimplemented!() }
-pub fn group_by(f: u32, red: u32) -> Value { unimplemented!() }
+pub fn group_by(f: impl Fn(&Value) -> Value + 'static, coll: Value) -> Value { transduce::group_by(f, coll) }
 
-pub fn is_number(v: &Value) -> bool { unimplemented!() }
-pub fn is_integral(v: &Value) -> bool { unimplemented!() }
-pub fn is_rational(v: &Value) -> bool { unimplemented!() }
-pub fn is_float_point(v: &Value) -> bool { unimplemented!() }
+pub fn is_number(v: &Value) -> bool { numeric::is_number(v._handle()) }
+pub fn is_integral(v: &Value) -> bool { integral::is_integral(v._handle()) }
+pub fn is_rational(v: &Value) -> bool { rational::is_rational(v._handle()) || integral::is_integral(v._handle()) }
+pub fn is_float_point(v: &Value) -> bool { float_point::is_float(v._handle()) }
 pub fn is_keyword(v: &Value) -> bool { unimplemented!() }
 pub fn is_symbol(v: &Value) -> bool { unimplemented!() }
-pub fn is_string(v: &Value) -> bool { unimplemented!() }
+pub fn is_string(v: &Value) -> bool { string::is_string(v._handle()) }
 pub fn is_boolean(v: &Value) -> bool { unimplemented!() }
 pub fn is_char(v: &Value) -> bool { unimplemented!() }
 pub fn is_vector(v: &Value) -> bool { v.is_vector() }
@@ -167,6 +201,9 @@ pub fn is_map(v: &Value) -> bool { v.is_map() }
 pub fn is_set(v: &Value) -> bool { v.is_set() }
 pub fn is_hash_map(v: &Value) -> bool { unimplemented!() }
 pub fn is_hash_set(v: &Value) -> bool { unimplemented!() }
+pub fn is_queue(v: &Value) -> bool { queue::is_queue(v._handle()) }
+pub fn is_range(v: &Value) -> bool { range::is_range(v._handle()) }
+pub fn is_priority_queue(v: &Value) -> bool { queue::priority::is_priority(v._handle()) }
 pub fn is_sort_map(v: &Value) -> bool { unimplemented!() }
 pub fn is_sort_set(v: &Value) -> bool { unimplemented!() }
 pub fn is_aggregate(v: &Value) -> bool { v.is_aggregate() }
@@ -175,20 +212,42 @@ pub fn is_associative(v: &Value) -> bool { unimplemented!() }
 pub fn is_inst(v: &Value) -> bool { unimplemented!() }
 pub fn is_uuid(v: &Value) -> bool { unimplemented!() }
 
-pub fn atom(v: Value) -> u64 { unimplemented!() }
-pub fn swap(a: u64, f: &dyn Fn(Value) -> Value) -> Value { unimplemented!() }
-pub fn reset(a: u64, v: Value) -> Value { unimplemented!() }
+pub fn atom(v: Value) -> atom::Atom { atom::Atom::new(v) }
+pub fn deref(a: &atom::Atom) -> Value { a.deref() }
+pub fn swap(a: &atom::Atom, f: impl Fn(&Value) -> Value) -> Value { a.swap(f) }
+pub fn reset(a: &atom::Atom, v: Value) -> Value { a.reset(v) }
+pub fn compare_and_set(a: &atom::Atom, old: &Value, new: Value) -> bool { a.compare_and_set(old, new) }
 
 pub fn str_new(source: &str) -> Value { source.into() }
-pub fn str(s: Value, t: Value) -> Value { unimplemented!() }
-pub fn substr(s: Value, r: std::ops::Range<u32>) -> Value { unimplemented!() }
-pub fn str_split(s: Value, sep: Value) -> Value { unimplemented!() }
-pub fn trim(s: Value) -> Value { unimplemented!() }
-pub fn starts_with(s: &Value, t: &Value) -> bool { unimplemented!() }
-pub fn ends_with  (s: &Value, t: &Value) -> bool { unimplemented!() }
+pub fn str(s: Value, t: Value) -> Value { string::ops::str(s, t) }
+pub fn substr(s: Value, r: std::ops::Range<u32>) -> Value { string::substring(&s, r.start, r.end) }
+pub fn str_split(s: Value, sep: Value) -> Value { string::ops::split(&s, &sep) }
+pub fn str_join(sep: &Value, coll: &Value) -> Value { string::ops::join(sep, coll) }
+pub fn str_replace(s: Value, pat: &Value, rep: &Value) -> Value { string::ops::replace(&s, pat, rep) }
+pub fn index_of(s: &Value, t: &Value) -> Option<u32> { string::ops::index_of(s, t) }
+pub fn index_of_from(s: &Value, t: &Value, from: u32) -> Option<u32> { string::ops::index_of_from(s, t, from) }
+pub fn trim(s: Value) -> Value { string::ops::trim(&s) }
+pub fn upper_case(s: &Value) -> Value { string::ops::upper_case(s) }
+pub fn lower_case(s: &Value) -> Value { string::ops::lower_case(s) }
+pub fn starts_with(s: &Value, t: &Value) -> bool { string::ops::starts_with(s, t) }
+pub fn ends_with  (s: &Value, t: &Value) -> bool { string::ops::ends_with(s, t) }
 pub fn name     (s: &Value) -> &str { unimplemented!() }
 pub fn namespace(s: &Value) -> &str { unimplemented!() }
 
+pub fn regex(source: &str) -> Result<Value, String> { regex::new(source).map(|h| h.value()) }
+pub fn is_regex(v: &Value) -> bool { regex::is_regex(v._handle()) }
+pub fn re_find(re: &Value, s: &Value) -> Value { regex::re_find(re, s) }
+pub fn re_matches(re: &Value, s: &Value) -> Value { regex::re_matches(re, s) }
+pub fn re_seq(re: &Value, s: &Value) -> Value { regex::re_seq(re, s) }
+pub fn re_split(re: &Value, s: &Value) -> Value { regex::re_split(re, s) }
+
+pub fn uri(source: &str) -> Result<Value, String> { uri::new_parsed(source).map(|h| h.value()) }
+pub fn is_uri(v: &Value) -> bool { uri::is_uri(v._handle()) }
+pub fn uri_resolve(base: &Value, reference: &Value) -> Value { uri::resolve(base, reference) }
+pub fn uri_normalize(u: &Value) -> Value { uri::normalize(u) }
+pub fn uri_encode(s: &str) -> String { uri::encode(s) }
+pub fn uri_decode(s: &str) -> Result<String, String> { uri::decode(s) }
+
 pub fn bools(n: u32) -> Value { unimplemented!() }
 pub fn i32s(n: u32) -> Value { unimplemented!() }
 pub fn i64s(n: u32) -> Value { unimplemented!() }
@@ -203,10 +262,38 @@ pub fn u64s(n: u32) -> Value { unimplemented!() }
 pub fn varray(n: u32) -> Value { unimplemented!() }
 
 
-pub fn filter(pred: fn(&Value) -> bool) -> transduce::Transducer { transduce::filter(pred) }
-pub fn take(n: u32) -> transduce::Transducer { unimplemented!() }
-pub fn drop(n: u32) -> transduce::Transducer { unimplemented!() }
-pub fn range(r: std::ops::Range<i64>) -> Value { unimplemented!() }
+pub fn map(f: impl Fn(&Value) -> Value + transduce::Shared + 'static) -> transduce::Transducer { transduce::map(f) }
+pub fn filter(pred: impl Fn(&Value) -> bool + transduce::Shared + 'static) -> transduce::Transducer { transduce::filter(pred) }
+pub fn remove(pred: impl Fn(&Value) -> bool + transduce::Shared + 'static) -> transduce::Transducer { transduce::remove(pred) }
+pub fn keys() -> transduce::Transducer { transduce::keys() }
+pub fn vals() -> transduce::Transducer { transduce::vals() }
+pub fn cat() -> transduce::Transducer { transduce::cat() }
+pub fn mapcat(f: impl Fn(&Value) -> Value + transduce::Shared + 'static) -> transduce::Transducer { transduce::mapcat(f) }
+pub fn take(n: u32) -> transduce::Transducer { transduce::take(n) }
+pub fn drop(n: u32) -> transduce::Transducer { transduce::drop(n) }
+pub fn take_nth(n: u32) -> transduce::Transducer { transduce::take_nth(n) }
+pub fn take_while(pred: impl Fn(&Value) -> bool + transduce::Shared + 'static) -> transduce::Transducer { transduce::take_while(pred) }
+pub fn drop_while(pred: impl Fn(&Value) -> bool + transduce::Shared + 'static) -> transduce::Transducer { transduce::drop_while(pred) }
+pub fn take_last(n: u32) -> transduce::Transducer { transduce::take_last(n) }
+pub fn drop_last(n: u32) -> transduce::Transducer { transduce::drop_last(n) }
+pub fn distinct() -> transduce::Transducer { transduce::distinct() }
+pub fn dedupe() -> transduce::Transducer { transduce::dedupe() }
+pub fn interpose(sep: Value) -> transduce::Transducer { transduce::interpose(sep) }
+pub fn replace(smap: Value) -> transduce::Transducer { transduce::replace(smap) }
+pub fn partition(n: u32) -> transduce::Transducer { transduce::partition(n) }
+pub fn partition_by(f: impl Fn(&Value) -> Value + transduce::Shared + 'static) -> transduce::Transducer { transduce::partition_by(f) }
+pub fn range(r: std::ops::Range<i64>) -> Value { range::new_value(r.start, r.end, 1) }
+pub fn range_step(start: i64, end: i64, step: i64) -> Value { range::new_value(start, end, step) }
+pub fn range_from(start: i64) -> Value { range::new_infinite_value(start, 1) }
+pub fn repeat(x: Value) -> Value { range::repeat::new(x._consume()).handle().value() }
+pub fn repeat_n(n: u32, x: Value) -> Value { range::repeat::new_n(n, x._consume()).handle().value() }
+pub fn cycle(c: Value) -> Value { range::cycle::new(c._consume()).handle().value() }
+pub fn iterate(f: impl Fn(&Value) -> Value + transduce::Shared + 'static, x: Value) -> Value {
+    range::iterate::new(f, x._consume()).handle().value()
+}
+pub fn repeatedly(f: impl Fn() -> Value + transduce::Shared + 'static) -> Value {
+    range::iterate::new_repeatedly(f).handle().value()
+}
 
 // reducible: repeat, cycle, range, iterate, repeatedly
 // transducers: keys, vals, map, filter, take, drop, cat, mapcat
@@ -219,15 +306,51 @@ pub fn slurp(filename: String) -> Value { unimplemented!() }
 pub fn sha3(filename: String) -> (u64, u64, u64, u64) { unimplemented!() }
 pub fn k12(filename: String, customization: String) -> (u64, u64, u64, u64) { unimplemented!() }
 
-pub fn reduced(v: Value) -> Value { unimplemented!() }
-pub fn is_reduced(v: &Value) -> bool { unimplemented!() }
+pub fn reduced(v: Value) -> Value { transduce::reduced::new_value(v) }
+pub fn is_reduced(v: &Value) -> bool { transduce::reduced::is_reduced(v._handle()) }
+pub fn unreduced(v: Value) -> Value { transduce::reduced::unreduced(v) }
+
+// Channels carry values between threads, which needs atomic alias counts.
+#[cfg(not(feature = "anchor_non_atomic"))]
+pub use self::chans::*;
+#[cfg(not(feature = "anchor_non_atomic"))]
+mod chans {
+    use super::*;
 
-pub fn chan() -> u64 { unimplemented!() }
-pub fn chan_take(c: u64) -> Value { unimplemented!() }
-pub fn chan_put(c: u64, v: Value) { unimplemented!() }
-pub fn alts() { unimplemented!() }
-pub fn promise_chan() -> u64 { unimplemented!() }
-pub fn offer(c: u64, v: Value) { unimplemented!() }
-pub fn poll(c: u64) -> Value { unimplemented!() }
+    pub fn chan() -> channel::Chan { channel::Chan::new() }
+    pub fn chan_buffered(buf: channel::buffer::Buffer) -> channel::Chan { channel::Chan::with_buffer(buf) }
+    pub fn chan_xform<X: Into<transduce::Transducers>>(buf: channel::buffer::Buffer, xf: X) -> channel::Chan {
+        channel::Chan::with_xform(buf, xf)
+    }
+    pub fn buffer(n: usize) -> channel::buffer::Buffer { channel::buffer::Buffer::fixed(n) }
+    pub fn dropping_buffer(n: usize) -> channel::buffer::Buffer { channel::buffer::Buffer::dropping(n) }
+    pub fn sliding_buffer(n: usize) -> channel::buffer::Buffer { channel::buffer::Buffer::sliding(n) }
+    pub fn chan_take(c: &channel::Chan) -> Value { c.take().unwrap_or(Value::nil()) }
+    pub fn chan_put(c: &channel::Chan, v: Value) -> bool { c.put(v) }
+    pub fn alts(ops: &[channel::alts::AltOp], priority: bool) -> channel::alts::Alt {
+        channel::alts::alts(ops, priority)
+    }
+    pub fn timeout(ms: u64) -> channel::Chan { channel::timeout::timeout(ms) }
+    pub fn pipe(from: channel::Chan, to: channel::Chan) -> channel::Chan { channel::ops::pipe(from, to, true) }
+    pub fn pipeline<X: Into<transduce::Transducers>>(n: usize, to: channel::Chan, xf: X, from: channel::Chan) -> channel::Chan {
+        channel::ops::pipeline(n, to, xf, from, true)
+    }
+    pub fn mult(source: channel::Chan) -> channel::ops::Mult { channel::ops::Mult::new(source) }
+    pub fn tap(m: &channel::ops::Mult, c: channel::Chan) -> channel::Chan { m.tap(c, true) }
+    pub fn publication(source: channel::Chan, topic_fn: impl Fn(&Value) -> Value + Send + Sync + 'static)
+                       -> channel::ops::Pub {
+        channel::ops::Pub::new(source, topic_fn)
+    }
+    pub fn subscribe(p: &channel::ops::Pub, topic: Value, c: channel::Chan) -> channel::Chan { p.sub(topic, c, true) }
+    pub fn merge_chans(chans: Vec<channel::Chan>) -> channel::Chan { channel::ops::merge(chans) }
+    pub fn split_chan(pred: impl Fn(&Value) -> bool + Send + 'static, source: channel::Chan)
+                      -> (channel::Chan, channel::Chan) {
+        channel::ops::split(pred, source)
+    }
+    pub fn promise_chan() -> channel::Chan { channel::Chan::promise() }
+    pub fn offer(c: &channel::Chan, v: Value) -> bool { c.offer(v) }
+    pub fn poll(c: &channel::Chan) -> Value { c.poll().unwrap_or(Value::nil()) }
+    pub fn close(c: &channel::Chan) { c.close() }
+}
 
 
@@ -0,0 +1,127 @@
+// Copyright (c) Cole Frederick. All rights reserved.
+// The use and distribution terms for this software are covered by the
+// Eclipse Public License 1.0 (https://opensource.org/licenses/eclipse-1.0.php)
+// which can be found in the file epl-v10.html at the root of this distribution.
+// By using this software in any fashion, you are agreeing to be bound by the terms of this license.
+// You must not remove this notice, or any other, from this software.
+
+use std::marker::PhantomData;
+use super::*;
+use vector::tear_down::{NodeRecord, BLANK};
+
+// Walks the trie with an explicit stack of records, in the same order
+// as reduce. Each node contributes a run of keys (with their values,
+// when has_vals is 1), then its children are visited.
+pub struct Iter<'a> {
+    stack: [NodeRecord; 8],
+    depth: u32,
+    keys: AnchoredLine,
+    at: u32,
+    len: u32,
+    has_vals: u32,
+    life: PhantomData<&'a Value>,
+}
+
+impl<'a> Iter<'a> {
+    pub fn new(prism: AnchoredLine, has_vals: u32) -> Iter<'a> {
+        let guide = Guide::hydrate(prism);
+        let (child_count, key_count) = {
+            let p = Pop::from(guide.root[-1]);
+            (p.child_count(), p.key_count())
+        };
+        let mut stack = [BLANK; 8];
+        stack[0] = NodeRecord {
+            first_child: guide.root,
+            child_count,
+            height: 0,
+            on_boundary: false,
+            current_child: None,
+        };
+        Iter { stack, depth: 1, keys: guide.root.offset((child_count << 1) as i32),
+            at: 0, len: key_count, has_vals, life: PhantomData }
+    }
+
+    fn next_run(&mut self) -> bool {
+        while self.depth != 0 {
+            let top = &mut self.stack[(self.depth - 1) as usize];
+            let next = match top.current_child {
+                Some(i) => { i + 1 },
+                None    => { 0 },
+            };
+            if next == top.child_count {
+                self.depth -= 1;
+                continue;
+            }
+            top.current_child = Some(next);
+            let idx = (next << 1) as i32;
+            let c = top.first_child[idx + 1].segment();
+            if top.height == MAX_LEVELS - 1 {
+                self.keys = c.line_at(0);
+                self.len = top.first_child[idx].u32();
+                self.at = 0;
+                return true;
+            }
+            let (child_count, key_count) = {
+                let p = Pop::from(top.first_child[idx]);
+                (p.child_count(), p.key_count())
+            };
+            let height = top.height + 1;
+            if child_count != 0 {
+                self.stack[self.depth as usize] = NodeRecord {
+                    first_child: c.line_at(0),
+                    child_count,
+                    height,
+                    on_boundary: false,
+                    current_child: None,
+                };
+                self.depth += 1;
+            }
+            if key_count != 0 {
+                self.keys = c.line_at(child_count << 1);
+                self.len = key_count;
+                self.at = 0;
+                return true;
+            }
+        }
+        false
+    }
+
+    fn next_entry(&mut self) -> Option<AnchoredLine> {
+        if self.at == self.len && !self.next_run() {
+            return None;
+        }
+        let key = self.keys.offset((self.at << self.has_vals) as i32);
+        self.at += 1;
+        Some(key)
+    }
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = &'a Value;
+    fn next(&mut self) -> Option<&'a Value> {
+        self.next_entry().map(|key| {
+            let x = key.line().star() as *const Value;
+            unsafe { &* x }
+        })
+    }
+}
+
+/// Entries of a map, as key value pairs.
+pub struct IterKv<'a> {
+    keys: Iter<'a>,
+}
+
+impl<'a> IterKv<'a> {
+    pub fn new(prism: AnchoredLine) -> IterKv<'a> { IterKv { keys: Iter::new(prism, 1) } }
+}
+
+impl<'a> Iterator for IterKv<'a> {
+    type Item = (&'a Value, &'a Value);
+    fn next(&mut self) -> Option<(&'a Value, &'a Value)> {
+        self.keys.next_entry().map(|key| {
+            let x = key.line().star() as *const Value;
+            let w = key.offset(1).line().star() as *const Value;
+            unsafe { (&* x, &* w) }
+        })
+    }
+}
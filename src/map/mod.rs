@@ -22,6 +22,7 @@ pub mod assoc;
 pub mod eq;
 pub mod get;
 pub mod reduce;
+pub mod iter;
 pub mod tear_down;
 pub mod dissoc;
 
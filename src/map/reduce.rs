@@ -39,6 +39,43 @@ pub fn reduce(prism: AnchoredLine, process_stack: &mut [Box<dyn Process>], has_v
     last_call(process_stack)
 }
 
+// A fold splits the trie into the root's own entries, then each child of the root.
+pub fn chunk_count(prism: AnchoredLine) -> u32 {
+    let guide = Guide::hydrate(prism);
+    Pop::from(guide.root[-1]).child_count() + 1
+}
+
+// Feeds the entries of one chunk into the process stack, without the last call.
+pub fn reduce_chunk(prism: AnchoredLine, chunk: u32, process_stack: &mut [Box<dyn Process>],
+                    has_vals: u32) -> Option<Value> {
+    let guide = Guide::hydrate(prism);
+    let (child_count, key_count) = {
+        let p = Pop::from(guide.root[-1]);
+        (p.child_count(), p.key_count())
+    };
+    if chunk == 0 {
+        let first_key = guide.root.offset((child_count << 1) as i32);
+        return ingest_keys(first_key, key_count, process_stack, has_vals)
+    }
+    assert!(chunk <= child_count);
+    let stack_space = [BLANK; 8];
+    let mut stack = NodeRecordStack::new(stack_space.as_ptr());
+    // A record whose next child is chunk - 1, and which ends after it
+    stack.push(NodeRecord {
+        first_child: guide.root,
+        child_count: chunk,
+        height: 0,
+        on_boundary: false,
+        current_child: if chunk == 1 { None } else { Some(chunk - 2) },
+    });
+    while !stack.is_empty() {
+        if let Some(ret) = step(&mut stack, process_stack, has_vals) {
+            return Some(ret);
+        }
+    }
+    None
+}
+
 pub fn ingest_keys(first_key: AnchoredLine, key_count: u32, process_stack: &mut [Box<dyn Process>],
                    has_vals: u32) -> Option<Value> {
     for i in 0..key_count {
@@ -6,7 +6,9 @@
 // You must not remove this notice, or any other, from this software.
 
 use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use memory::unit::Unit;
+use memory::line::Line;
 
 pub const ALIAS_BITS: u32 = if cfg!(target_pointer_width = "32") { 20 } else { 30 };
 pub const ALIAS_MASK: u32 = (1 << ALIAS_BITS) - 1;
@@ -66,6 +68,27 @@ impl Anchor {
     pub fn unit(&self) -> Unit { self.unit }
 }
 
+/// Shared access to an anchor in place, for threads holding aliases to its segment.
+/// Without `anchor_non_atomic`, all alias count changes go through these.
+impl Anchor {
+    fn atomic<'a>(anchor_line: Line) -> &'a AtomicUsize {
+        unsafe { &*(anchor_line.star() as *const usize as *const AtomicUsize) }
+    }
+    pub fn load(anchor_line: Line) -> Anchor {
+        Unit::from(Anchor::atomic(anchor_line).load(Ordering::SeqCst)).into()
+    }
+    /// Adds an alias, returning the new anchor.
+    pub fn fetch_aliased(anchor_line: Line) -> Anchor {
+        let prev = Anchor::atomic(anchor_line).fetch_add(1, Ordering::SeqCst);
+        Anchor::from(Unit::from(prev)).aliased() // Alerts on overflow
+    }
+    /// Removes an alias, returning the new anchor.
+    pub fn fetch_unaliased(anchor_line: Line) -> Anchor {
+        let prev = Anchor::atomic(anchor_line).fetch_sub(1, Ordering::SeqCst);
+        Anchor::from(Unit::from(prev)).unaliased() // Alerts on underflow
+    }
+}
+
 impl From<Unit> for Anchor { fn from(u: Unit) -> Self { Anchor { unit: u } } }
 impl Into<Unit> for Anchor { fn into(self) -> Unit { self.unit } }
 
@@ -49,7 +49,7 @@ impl Segment {
 
     pub fn free(s: Segment) {
         //println!("Free segment({:?}), capacity {}", s.line().unit(), s.capacity());
-        let a = Anchor::from(s.anchor_line[0]);
+        let a = s.anchor();
         #[cfg(any(test, feature = "segment_free"))]
             assert_eq!(a.aliases(), 0,
                        "segment_free: freeing segment with aliases = {}", a.aliases());
@@ -60,23 +60,11 @@ impl Segment {
         }
     }
 
-    pub fn capacity(&self) -> u32 { self.anchor_line[0].anchor().capacity() }
+    pub fn capacity(&self) -> u32 { self.anchor().capacity() }
 
     pub fn is_aliased(&self) -> bool {
-        if cfg!(feature = "anchor_non_atomic") {
-            let real_ret = self.anchor_line[0].anchor().is_aliased();
-            real_ret
-        } else {
-            use std::sync::atomic::{AtomicUsize, Ordering};
-            let ptr = self.anchor_line.star() as *const usize as *const AtomicUsize;
-            let curr = unsafe { (&*ptr).load(Ordering::SeqCst) };
-            let real_ret = Unit::from(curr).anchor().is_aliased();
-            if real_ret {
-                //use memory::schedule;
-                //schedule::step();
-            }
-            real_ret
-        }
+        preempt();
+        let real_ret = self.anchor().is_aliased();
         /*if cfg!(feature = "fuzz_segment_spurious_aliased") {
             use random::fuzz;
             let (seed, _log_tail) = fuzz::next_random();
@@ -85,6 +73,7 @@ impl Segment {
         } else {
             real_ret
         }*/
+        real_ret
     }
     pub fn alias(&self) {
         if cfg!(feature = "anchor_non_atomic") {
@@ -93,10 +82,8 @@ impl Segment {
             let mut x = *self;
             x.anchor_line[0] = new_a.into();
         } else {
-            use std::sync::atomic::{AtomicUsize, Ordering};
-            let ptr = self.anchor_line.star() as *const usize as *const AtomicUsize;
-            let prev = unsafe { (&*ptr).fetch_add(1, Ordering::SeqCst) };
-            let _ = Unit::from(prev).anchor().aliased(); // Alerts on overflow
+            preempt();
+            let _ = Anchor::fetch_aliased(self.anchor_line);
         }
     }
     pub fn unalias(&self) -> u32 {
@@ -107,11 +94,8 @@ impl Segment {
             x.anchor_line[0] = new_a.into();
             new_a.aliases()
         } else {
-            use std::sync::atomic::{AtomicUsize, Ordering};
-            let ptr = self.anchor_line.star() as *const usize as *const AtomicUsize;
-            let prev = unsafe { (&*ptr).fetch_sub(1, Ordering::SeqCst) };
-            let new_anchor = Unit::from(prev).anchor().unaliased();
-            new_anchor.aliases()
+            preempt();
+            Anchor::fetch_unaliased(self.anchor_line).aliases()
         }
     }
     pub fn unalias_expect_nonzero(&self) {
@@ -127,7 +111,13 @@ impl Segment {
         m[index] = x;
     }
     pub fn has_index(&self, index: u32) -> bool { index < self.capacity() }
-    pub fn anchor(&self) -> Anchor { self.anchor_line[0].anchor() }
+    pub fn anchor(&self) -> Anchor {
+        if cfg!(feature = "anchor_non_atomic") {
+            self.anchor_line[0].anchor()
+        } else {
+            Anchor::load(self.anchor_line)
+        }
+    }
     pub fn unit(&self) -> Unit { self.anchor_line.unit() }
     pub fn line_at(&self, base: u32) -> AnchoredLine { AnchoredLine::new(*self, base) }
     pub fn at(&self, range: Range<u32>) -> AnchoredRange { AnchoredRange::new(*self, range) }
@@ -181,6 +171,16 @@ impl Segment {
 impl From<Unit> for Segment {
     fn from(unit: Unit) -> Self { Segment::from(unit.line()) }
 }
+/// A point where another thread may take over, under the schedule harness.
+/// Only the atomic anchor build shares segments between threads.
+fn preempt() {
+    #[cfg(any(test, feature = "fuzz_schedule"))]
+    {
+        if !cfg!(feature = "anchor_non_atomic") {
+            schedule::step();
+        }
+    }
+}
 fn is_aligned(line: Line) -> bool {
     let mask = Unit::bytes() as usize - 1;
     line.unit().u() & mask == 0
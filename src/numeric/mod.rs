@@ -0,0 +1,339 @@
+// Copyright (c) Cole Frederick. All rights reserved.
+// The use and distribution terms for this software are covered by the
+// Eclipse Public License 1.0 (https://opensource.org/licenses/eclipse-1.0.php)
+// which can be found in the file epl-v10.html at the root of this distribution.
+// By using this software in any fashion, you are agreeing to be bound by the terms of this license.
+// You must not remove this notice, or any other, from this software.
+
+//! The numeric tower, for arithmetic across number types.
+//!
+//! Operands are lifted to the wider of their types, in the order integral,
+//! rational, decimal, float point, as in Clojure: 1 + 1/2 is 3/2, 1/2 + 1M
+//! is 1.5M and anything with a float is a float. Operands of the same type
+//! are left to that type's Numeral dispatch.
+
+use std::cmp::Ordering;
+use handle::Handle;
+use value::Value;
+use integral::{self, big::BigIntegral};
+use decimal::{self, BigDecimal, Context};
+use {rational, float_point};
+
+#[derive(Clone, Debug)]
+pub enum Num {
+    Int(BigIntegral),
+    Ratio(BigIntegral, BigIntegral),
+    Decimal(BigDecimal),
+    Float(f64),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Op { Add, Sub, Mul, Div, Quot, Rem, Mod }
+
+pub fn is_number(h: Handle) -> bool {
+    integral::is_integral(h) || rational::is_rational(h) ||
+        decimal::is_decimal(h) || float_point::is_float(h)
+}
+
+fn one() -> BigIntegral { BigIntegral::from_i64(1) }
+
+impl Num {
+    pub fn of(h: Handle) -> Option<Num> {
+        if let Some(prism) = integral::find_prism(h) {
+            return Some(Num::Int(integral::to_big(prism)))
+        }
+        if let Some(prism) = float_point::find_prism(h) {
+            return Some(Num::Float(float_point::as_f64(prism)))
+        }
+        if let Some(prism) = decimal::find_prism(h) {
+            return Some(Num::Decimal(decimal::decimal(prism)))
+        }
+        rational::parts(h).map(|(n, d)| Num::Ratio(n, d))
+    }
+    fn rank(&self) -> u8 {
+        match *self {
+            Num::Int(_) => 0,
+            Num::Ratio(_, _) => 1,
+            Num::Decimal(_) => 2,
+            Num::Float(_) => 3,
+        }
+    }
+    pub fn to_f64(&self) -> f64 {
+        match *self {
+            Num::Int(ref a) => a.to_f64(),
+            Num::Ratio(ref a, ref b) => {
+                let h = rational::ratio(a, b);
+                let x = rational::as_f64(rational::find_prism(h).unwrap());
+                h.retire();
+                x
+            },
+            Num::Decimal(ref d) => format!("{}", d).parse().unwrap(),
+            Num::Float(x) => x,
+        }
+    }
+    fn lift(self, rank: u8) -> Num {
+        if self.rank() == rank {
+            return self
+        }
+        match (self, rank) {
+            (x, 3) => Num::Float(x.to_f64()),
+            (Num::Int(a), 1) => Num::Ratio(a, one()),
+            (Num::Int(a), 2) => Num::Decimal(BigDecimal::new(a, 0)),
+            (Num::Ratio(a, b), 2) => {
+                let (x, y) = (BigDecimal::new(a, 0), BigDecimal::new(b, 0));
+                match x.div(&y, &Context::UNLIMITED) {
+                    Ok(d) => Num::Decimal(d),
+                    Err(msg) => panic!("{}", msg),
+                }
+            },
+            (x, _) => x,
+        }
+    }
+    pub fn into_handle(self) -> Handle {
+        match self {
+            Num::Int(a) => integral::demoted(&a).handle(),
+            Num::Ratio(a, b) => rational::ratio(&a, &b),
+            Num::Decimal(d) => decimal::new(&d),
+            Num::Float(x) => float_point::new(x).handle(),
+        }
+    }
+    pub fn signum(&self) -> Option<Ordering> {
+        match *self {
+            Num::Int(ref a) | Num::Ratio(ref a, _) => Some(a.cmp(&BigIntegral::zero())),
+            Num::Decimal(ref d) => Some(d.unscaled.cmp(&BigIntegral::zero())),
+            Num::Float(x) => x.partial_cmp(&0.0),
+        }
+    }
+    pub fn neg(self) -> Num {
+        match self {
+            Num::Int(a) => Num::Int(a.neg()),
+            Num::Ratio(a, b) => Num::Ratio(a.neg(), b),
+            Num::Decimal(d) => Num::Decimal(d.neg()),
+            Num::Float(x) => Num::Float(-x),
+        }
+    }
+}
+
+fn lifted(x: Num, y: Num) -> (Num, Num) {
+    let rank = x.rank().max(y.rank());
+    (x.lift(rank), y.lift(rank))
+}
+
+// Decimals at a common scale, as integrals.
+fn aligned(x: &BigDecimal, y: &BigDecimal) -> (BigIntegral, BigIntegral) {
+    let scale = x.scale.max(y.scale);
+    (x.rescaled(scale), y.rescaled(scale))
+}
+
+/// Applies op, after lifting both operands to the wider type.
+/// Quotients truncate toward zero, remainders take the sign of the dividend,
+/// and moduli the sign of the divisor.
+pub fn compute(op: Op, x: Num, y: Num) -> Num {
+    let (x, y) = lifted(x, y);
+    match (x, y) {
+        (Num::Int(a), Num::Int(b)) => match op {
+            Op::Add => Num::Int(a.add(&b)),
+            Op::Sub => Num::Int(a.sub(&b)),
+            Op::Mul => Num::Int(a.mul(&b)),
+            Op::Div => Num::Ratio(a, b),
+            Op::Quot => Num::Int(a.divmod(&b).0),
+            Op::Rem => Num::Int(a.divmod(&b).1),
+            Op::Mod => Num::Int(a.modulo(&b)),
+        },
+        (Num::Ratio(a, b), Num::Ratio(c, d)) => match op {
+            Op::Add => Num::Ratio(a.mul(&d).add(&c.mul(&b)), b.mul(&d)),
+            Op::Sub => Num::Ratio(a.mul(&d).sub(&c.mul(&b)), b.mul(&d)),
+            Op::Mul => Num::Ratio(a.mul(&c), b.mul(&d)),
+            Op::Div => Num::Ratio(a.mul(&d), b.mul(&c)),
+            Op::Quot => Num::Int(a.mul(&d).divmod(&b.mul(&c)).0),
+            Op::Rem | Op::Mod => {
+                let (p, q) = (a.mul(&d), b.mul(&c));
+                let r = if op == Op::Rem { p.divmod(&q).1 } else { p.modulo(&q) };
+                // r / (b d), in units of the common denominator.
+                Num::Ratio(r, b.mul(&d))
+            },
+        },
+        (Num::Decimal(x), Num::Decimal(y)) => match op {
+            Op::Add => Num::Decimal(x.add(&y)),
+            Op::Sub => Num::Decimal(x.sub(&y)),
+            Op::Mul => Num::Decimal(x.mul(&y)),
+            Op::Div => match x.div(&y, &Context::UNLIMITED) {
+                Ok(d) => Num::Decimal(d),
+                Err(msg) => panic!("{}", msg),
+            },
+            Op::Quot | Op::Rem | Op::Mod => {
+                let (a, b) = aligned(&x, &y);
+                let q = BigDecimal::new(a.divmod(&b).0, 0);
+                if op == Op::Quot {
+                    return Num::Decimal(q)
+                }
+                let r = x.sub(&q.mul(&y));
+                let flip = op == Op::Mod && !r.unscaled.is_zero() && r.unscaled.negative != y.unscaled.negative;
+                Num::Decimal(if flip { r.add(&y) } else { r })
+            },
+        },
+        (Num::Float(a), Num::Float(b)) => Num::Float(match op {
+            Op::Add => a + b,
+            Op::Sub => a - b,
+            Op::Mul => a * b,
+            Op::Div => a / b,
+            Op::Quot => (a / b).trunc(),
+            Op::Rem => a % b,
+            Op::Mod => {
+                let r = a % b;
+                if r != 0.0 && (r < 0.0) != (b < 0.0) { r + b } else { r }
+            },
+        }),
+        _ => unreachable!(),
+    }
+}
+
+// A decimal as an exact ratio, since a ratio may not be an exact decimal.
+fn as_ratio(x: Num) -> Num {
+    match x {
+        Num::Decimal(d) if d.scale < 0 => Num::Ratio(d.unscaled.mul(&BigIntegral::pow10(-d.scale as u32)), one()),
+        Num::Decimal(d) => Num::Ratio(d.unscaled, BigIntegral::pow10(d.scale as u32)),
+        x => x,
+    }
+}
+
+pub fn cmp(x: Num, y: Num) -> Option<Ordering> {
+    let (x, y) = match (x.rank(), y.rank()) {
+        (1, 2) | (2, 1) => (as_ratio(x), as_ratio(y)),
+        _ => (x, y),
+    };
+    match lifted(x, y) {
+        (Num::Int(a), Num::Int(b)) => Some(a.cmp(&b)),
+        (Num::Ratio(a, b), Num::Ratio(c, d)) => Some(a.mul(&d).cmp(&c.mul(&b))),
+        (Num::Decimal(a), Num::Decimal(b)) => a.partial_cmp(&b),
+        (Num::Float(a), Num::Float(b)) => a.partial_cmp(&b),
+        _ => unreachable!(),
+    }
+}
+
+// Numbers of different types, else None. Same types and non-numbers
+// are turned away before any conversion.
+fn mixed_nums(x: Handle, y: Handle) -> Option<(Num, Num)> {
+    if !x.is_ref() || !y.is_ref() || x.prism()[0] == y.prism()[0] {
+        return None
+    }
+    if !is_number(x) || !is_number(y) {
+        return None
+    }
+    match (Num::of(x), Num::of(y)) {
+        (Some(a), Some(b)) => Some((a, b)),
+        _ => None,
+    }
+}
+
+/// Applies op to numbers of different types, consuming both.
+/// None when they aren't, for the dispatch of the left operand.
+pub fn mixed(x: Handle, y: Handle, op: Op) -> Option<Handle> {
+    let (a, b) = mixed_nums(x, y)?;
+    x.retire();
+    y.retire();
+    Some(compute(op, a, b).into_handle())
+}
+/// Applies op to any two numbers, consuming both.
+pub fn any(x: Handle, y: Handle, op: Op) -> Option<Handle> {
+    let (a, b) = match (Num::of(x), Num::of(y)) {
+        (Some(a), Some(b)) => (a, b),
+        _ => return None,
+    };
+    x.retire();
+    y.retire();
+    Some(compute(op, a, b).into_handle())
+}
+pub fn mixed_cmp(x: Handle, y: Handle) -> Option<Option<Ordering>> {
+    mixed_nums(x, y).map(|(a, b)| cmp(a, b))
+}
+
+fn num_of(v: &Value) -> Num {
+    match Num::of(v._handle()) {
+        Some(n) => n,
+        None => panic!("Not a number: {}", v),
+    }
+}
+fn is_nan(v: &Value) -> bool {
+    match num_of(v) {
+        Num::Float(x) => x.is_nan(),
+        _ => false,
+    }
+}
+
+/// The greater of two numbers, as given. NaN wins.
+pub fn max(x: Value, y: Value) -> Value {
+    if is_nan(&x) { return x }
+    if is_nan(&y) { return y }
+    if cmp(num_of(&x), num_of(&y)) == Some(Ordering::Less) { y } else { x }
+}
+/// The lesser of two numbers, as given. NaN wins.
+pub fn min(x: Value, y: Value) -> Value {
+    if is_nan(&x) { return x }
+    if is_nan(&y) { return y }
+    if cmp(num_of(&x), num_of(&y)) == Some(Ordering::Greater) { y } else { x }
+}
+pub fn abs(x: Value) -> Value { if is_neg(&x) { -x } else { x } }
+pub fn quot(x: &Value, y: &Value) -> Value {
+    compute(Op::Quot, num_of(x), num_of(y)).into_handle().value()
+}
+pub fn is_zero(x: &Value) -> bool { num_of(x).signum() == Some(Ordering::Equal) }
+pub fn is_pos(x: &Value) -> bool { num_of(x).signum() == Some(Ordering::Greater) }
+pub fn is_neg(x: &Value) -> bool { num_of(x).signum() == Some(Ordering::Less) }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn n(s: &str) -> Value { s.parse().unwrap() }
+    fn p(v: Value) -> String { format!("{}", v) }
+
+    #[test]
+    fn contagion() {
+        assert_eq!(p(n("1") + n("1/2")), "3/2");
+        assert_eq!(p(n("1/2") + n("1")), "3/2");
+        assert_eq!(p(n("1/2") * n("2")), "1");
+        assert_eq!(p(n("1") + n("2.5")), "3.5");
+        assert_eq!(p(n("2.5") - n("1")), "1.5");
+        assert_eq!(p(n("1/4") + n("0.5")), "0.75");
+        assert_eq!(p(n("1/2") + n("1.25M")), "1.75M");
+        assert_eq!(p(n("3") * n("1.5M")), "4.5M");
+        assert_eq!(p(n("1.5M") + n("0.25")), "1.75");
+        assert_eq!(p(n("100000000000000000000") + n("0.5")), "100000000000000000000.0");
+        assert_eq!(p(n("7") / n("2")), "7/2");
+        assert_eq!(p(n("7.0") / n("2")), "3.5");
+        assert_eq!(p(n("7") % n("-2")), "1");
+        assert_eq!(p(n("7").modulus(n("-2"))), "-1");
+        assert_eq!(p(n("-7/2") % n("2")), "-3/2");
+        assert_eq!(p(n("-7/2").modulus(n("2"))), "1/2");
+        assert_eq!(p(n("-7.5M").modulus(n("2"))), "0.5M");
+        assert_eq!(p(n("-7.5") % n("2")), "-1.5");
+        assert_eq!(p(n("1.5") * n("2.0")), "3.0");
+        assert_eq!(p(-n("1.5")), "-1.5");
+        assert!(n("1") < n("1.5") && n("3/2") > n("1.25M") && n("0.5M") < n("2/3"));
+    }
+
+    #[test]
+    fn library() {
+        assert_eq!(p(max(n("1"), n("2.5"))), "2.5");
+        assert_eq!(p(max(n("3"), n("2.5"))), "3");
+        assert_eq!(p(min(n("1/3"), n("0.3M"))), "0.3M");
+        assert!(max(n("1"), n("##NaN")) != max(n("1"), n("##NaN")));
+        assert_eq!(p(abs(n("-5/3"))), "5/3");
+        assert_eq!(p(abs(n("-2.5M"))), "2.5M");
+        assert_eq!(p(abs(n("-9223372036854775808"))), "9223372036854775808N");
+        assert_eq!(p(quot(&n("-7"), &n("2"))), "-3");
+        assert_eq!(p(quot(&n("7/2"), &n("1/3"))), "10");
+        assert_eq!(p(quot(&n("7.5M"), &n("2"))), "3M");
+        assert_eq!(p(quot(&n("-7.5"), &n("2"))), "-3.0");
+        assert!(is_zero(&n("0")) && is_zero(&n("0.00M")) && is_zero(&n("0.0")) && !is_zero(&n("1/9")));
+        assert!(is_pos(&n("1/9")) && is_neg(&n("-0.1M")) && is_neg(&n("-1N")) && !is_pos(&n("##NaN")));
+        assert!(is_number(n("1/2")._handle()) && !is_number(n(":a")._handle()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Can't do arithmetic on Rational and Keyword.")]
+    fn not_a_number() {
+        let _ = n("1/2") + n(":a");
+    }
+}
@@ -5,6 +5,259 @@
 // By using this software in any fashion, you are agreeing to be bound by the terms of this license.
 // You must not remove this notice, or any other, from this software.
 
+//! Persistent FIFO queue.
+//!
+//! A queue holds a front list and a rear vector. Values are conj'd onto the rear,
+//! and peeked or popped from the front. When the front runs dry, the rear is
+//! poured (in order) into a fresh front list.
 
-// FIFO queue
-// priority queue
+use std::fmt;
+use std::cmp::Ordering;
+use memory::*;
+use dispatch::*;
+use value::*;
+use handle::Handle;
+use transduce::{inges, last_call, Process};
+use list;
+use vector;
+
+pub mod priority;
+
+// Layout: [prism front rear]
+pub struct Queue_ { }
+pub fn prism_unit() -> Unit { mechanism::prism::<Queue_>() }
+pub fn is_prism(prism: AnchoredLine) -> bool { prism[0] == prism_unit() }
+pub fn find_prism(h: Handle) -> Option<AnchoredLine> { h.find_prism(prism_unit()) }
+pub fn is_queue(h: Handle) -> bool { find_prism(h).is_some() }
+
+pub fn new() -> Unit {
+    let s = Segment::new(3 /*prism front rear*/);
+    s.set(0, prism_unit());
+    s.set(1, list::new());
+    s.set(2, vector::new());
+    s.unit()
+}
+pub fn new_value() -> Value { new().handle().value() }
+
+pub fn unaliased(prism: AnchoredLine) -> AnchoredLine {
+    let seg = prism.segment();
+    if seg.is_aliased() {
+        if prism.index() == 0 {
+            alias_components(prism);
+        } else {
+            seg.unit().handle()._alias_components();
+        }
+        let s = seg.carbon_copy();
+        let p = prism.with_seg(s);
+        seg.unit().handle().retire();
+        p
+    } else {
+        prism
+    }
+}
+pub fn alias_components(prism: AnchoredLine) {
+    prism[1].handle().split();
+    prism[2].handle().split();
+}
+
+pub fn front(prism: AnchoredLine) -> Handle { prism[1].handle() }
+pub fn rear(prism: AnchoredLine) -> Handle { prism[2].handle() }
+pub fn count(prism: AnchoredLine) -> u32 { front(prism).count() + rear(prism).count() }
+
+/// Element at index `idx`, counting from the front of the queue.
+pub fn nth(prism: AnchoredLine, idx: u32) -> *const Unit {
+    let front_count = front(prism).count();
+    if idx < front_count {
+        front(prism).nth(idx) as *const Unit
+    } else {
+        let rear_count = rear(prism).count();
+        if idx - front_count >= rear_count {
+            panic!("Index out of bounds: {} in queue of count {}", idx, front_count + rear_count);
+        }
+        rear(prism).nth(idx - front_count) as *const Unit
+    }
+}
+
+/// Moves the contents of the rear vector onto an empty front list.
+pub fn pour_rear(p: AnchoredLine) {
+    let r = rear(p);
+    let mut f = front(p);
+    let ct = r.count();
+    for i in (0..ct).rev() {
+        let x = unsafe { *r.nth(i) };
+        f = f.conj(x.split());
+    }
+    p.set(1, f.unit());
+    r.retire();
+    p.set(2, vector::new());
+}
+
+pub fn reduce(prism: AnchoredLine, process_stack: &mut [Box<dyn Process>]) -> Value {
+    for side in 1..3 {
+        let c = prism[side].handle();
+        let ct = c.count();
+        for i in 0..ct {
+            let x = c.nth(i) as *const Value;
+            let y = unsafe { &* x };
+            if let Some(ret) = inges(process_stack, y) {
+                return ret;
+            }
+        }
+    }
+    last_call(process_stack)
+}
+
+impl Dispatch for Queue_ {
+    fn tear_down(&self, prism: AnchoredLine) {
+        let seg = prism.segment();
+        assert_eq!(0, seg.anchor().aliases());
+        prism[1].handle().retire();
+        prism[2].handle().retire();
+        Segment::free(seg)
+    }
+    fn alias_components(&self, prism: AnchoredLine) { alias_components(prism); }
+}
+impl Identification for Queue_ {
+    fn type_name(&self) -> &'static str { "Queue" }
+}
+impl Distinguish for Queue_ {
+    fn hash(&self, prism: AnchoredLine) -> u32 {
+        // Same mixing as vector and list, so equal sequences hash alike.
+        use random::{PI, cycle_abc};
+        let ct = count(prism);
+        let mut y = cycle_abc(7, PI[321].wrapping_add(ct as u64));
+        for i in 0..ct {
+            let h = unsafe { (*nth(prism, i)).handle().hash() } as u64;
+            y = cycle_abc(34, y.wrapping_add(h));
+        }
+        cycle_abc(210, y) as u32
+    }
+    fn eq(&self, prism: AnchoredLine, other: Unit) -> bool {
+        let o = other.handle();
+        let (ct, other_ct) = if let Some(q_prism) = find_prism(o) {
+            (count(prism), count(q_prism))
+        } else if list::is_list(o) || vector::is_vector(o) {
+            (count(prism), o.count())
+        } else {
+            return false
+        };
+        if ct != other_ct { return false }
+        for i in 0..ct {
+            let x = unsafe { *nth(prism, i) };
+            let y = unsafe { *o.nth(i) };
+            if x.handle() != y { return false }
+        }
+        true
+    }
+    fn cmp(&self, prism: AnchoredLine, other: Unit) -> Option<Ordering> {
+        let o = other.handle();
+        if let Some(q_prism) = find_prism(o) {
+            let ct = count(prism);
+            let q_ct = count(q_prism);
+            for i in 0..ct.min(q_ct) {
+                let x = unsafe { *nth(prism, i) };
+                let y = unsafe { *nth(q_prism, i) };
+                let res = x.handle().cmp(y.handle());
+                match res {
+                    Some(Ordering::Equal) => { },
+                    _ => { return res },
+                }
+            }
+            return Some(ct.cmp(&q_ct))
+        }
+        if o.is_ref() {
+            let o_prism_unit = o.logical_value()[0];
+            Some(prism_unit().cmp(&o_prism_unit))
+        } else {
+            Some(Ordering::Greater)
+        }
+    }
+}
+impl Aggregate for Queue_ {
+    fn is_aggregate(&self, prism: AnchoredLine) -> bool { true }
+    fn count(&self, prism: AnchoredLine) -> u32 { count(prism) }
+    fn empty(&self, prism: AnchoredLine) -> Unit { new() }
+    fn conj(&self, prism: AnchoredLine, x: Unit) -> Unit {
+        let p = unaliased(prism);
+        if front(p).count() == 0 {
+            let f = front(p).conj(x.handle());
+            p.set(1, f.unit());
+        } else {
+            let r = rear(p).conj(x.handle());
+            p.set(2, r.unit());
+        }
+        p.segment().unit()
+    }
+    fn peek(&self, prism: AnchoredLine) -> *const Unit {
+        if front(prism).count() == 0 {
+            (& ::handle::STATIC_NIL) as *const Unit
+        } else {
+            front(prism).peek() as *const Unit
+        }
+    }
+    fn pop(&self, prism: AnchoredLine) -> (Unit, Unit) {
+        if front(prism).count() == 0 {
+            return (prism.segment().unit(), Handle::nil().unit())
+        }
+        let p = unaliased(prism);
+        let (f, popped) = front(p).pop();
+        p.set(1, f.unit());
+        if f.count() == 0 {
+            pour_rear(p);
+        }
+        (p.segment().unit(), popped.unit())
+    }
+    fn reduce(&self, prism: AnchoredLine, process: &mut [Box<dyn Process>]) -> Value {
+        reduce(prism, process)
+    }
+}
+impl Sequential for Queue_ {
+    fn is_sequential(&self, prism: AnchoredLine) -> bool { true }
+    fn nth(&self, prism: AnchoredLine, idx: u32) -> *const Unit { nth(prism, idx) }
+}
+impl Associative for Queue_ { }
+impl Reversible for Queue_ { }
+impl Sorted for Queue_ { }
+impl Notation for Queue_ {
+    fn edn(&self, prism: AnchoredLine, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#queue [")?;
+        let ct = count(prism);
+        for i in 0..ct {
+            let x = unsafe { *nth(prism, i) };
+            write!(f, "{}{}", if i == 0 { "" } else { " " }, x.handle())?;
+        }
+        write!(f, "]")
+    }
+}
+impl Numeral for Queue_ { }
+impl Callable for Queue_ { }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn fifo() {
+        let mut q = new_value();
+        for i in 0..40 {
+            q = q.conj(i.into());
+        }
+        assert_eq!(q.count(), 40);
+        assert_eq!(*q.peek(), 0.into());
+        let (q, x) = q.pop();
+        assert_eq!(x, 0.into());
+        let (q, x) = q.pop();
+        assert_eq!(x, 1.into());
+        assert_eq!(*q.peek(), 2.into());
+        assert_eq!(q.count(), 38);
+    }
+    #[test]
+    fn persistent() {
+        let q = new_value().conj(1.into()).conj(2.into()).conj(3.into());
+        let r = q.split_out().pop().0.conj(4.into());
+        assert_eq!(format!("{}", q), "#queue [1 2 3]");
+        assert_eq!(format!("{}", r), "#queue [2 3 4]");
+        let v: Value = "[2 3 4]".parse().unwrap();
+        assert_eq!(r, v);
+        assert_eq!(r.hash(), v.hash());
+    }
+}
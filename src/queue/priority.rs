@@ -0,0 +1,350 @@
+// Copyright (c) Cole Frederick. All rights reserved.
+// The use and distribution terms for this software are covered by the
+// Eclipse Public License 1.0 (https://opensource.org/licenses/eclipse-1.0.php)
+// which can be found in the file epl-v10.html at the root of this distribution.
+// By using this software in any fashion, you are agreeing to be bound by the terms of this license.
+// You must not remove this notice, or any other, from this software.
+
+//! Persistent priority queue.
+//!
+//! A binary min-heap laid out in a persistent vector. Elements are ordered by
+//! `partial_cmp`, or by comparing the results of a key function. Incomparable
+//! elements are treated as equal. Peek and pop yield the least element;
+//! reducing and printing visit elements in priority order.
+
+use std::fmt;
+use std::cmp::Ordering;
+use std::sync::Arc;
+use memory::*;
+use dispatch::*;
+use value::*;
+use handle::Handle;
+use transduce::{inges, last_call, Fun, Process, Shared};
+use vector;
+
+pub type KeyFn = Fun;
+
+// Layout: [prism heap key hash], key is a boxed KeyFn or 0,
+// hash is the cached hash, or 0 when not yet computed.
+pub struct Priority_ { }
+pub fn prism_unit() -> Unit { mechanism::prism::<Priority_>() }
+pub fn is_prism(prism: AnchoredLine) -> bool { prism[0] == prism_unit() }
+pub fn find_prism(h: Handle) -> Option<AnchoredLine> { h.find_prism(prism_unit()) }
+pub fn is_priority(h: Handle) -> bool { find_prism(h).is_some() }
+
+fn prism_of(h: Handle) -> AnchoredLine {
+    match find_prism(h) {
+        Some(prism) => prism,
+        None => panic!("Not a priority queue: {}", h),
+    }
+}
+
+pub fn new_keyed(key: Option<KeyFn>) -> Unit {
+    let s = Segment::new(4 /*prism heap key hash*/);
+    s.set(0, prism_unit());
+    s.set(1, vector::new());
+    s.set(2, boxed_key(key));
+    s.set(3, Unit::zero());
+    s.unit()
+}
+pub fn new() -> Unit { new_keyed(None) }
+pub fn new_value() -> Value { new().handle().value() }
+pub fn new_value_by(key: impl Fn(&Value) -> Value + Shared + 'static) -> Value {
+    let k: KeyFn = Arc::new(key);
+    new_keyed(Some(k)).handle().value()
+}
+
+fn boxed_key(key: Option<KeyFn>) -> Unit {
+    Unit::from(key.map_or(0, |k| Box::into_raw(Box::new(k)) as usize))
+}
+
+pub fn unaliased(prism: AnchoredLine) -> AnchoredLine {
+    let seg = prism.segment();
+    if seg.is_aliased() {
+        if prism.index() == 0 {
+            alias_components(prism);
+        } else {
+            seg.unit().handle()._alias_components();
+        }
+        let s = seg.carbon_copy();
+        let p = prism.with_seg(s);
+        // Each copy owns its boxed key.
+        p.set(2, boxed_key(key(prism).cloned()));
+        seg.unit().handle().retire();
+        p
+    } else {
+        prism
+    }
+}
+pub fn alias_components(prism: AnchoredLine) { prism[1].handle().split(); }
+
+pub fn heap(prism: AnchoredLine) -> Handle { prism[1].handle() }
+pub fn key<'a>(prism: AnchoredLine) -> Option<&'a KeyFn> {
+    let k = prism[2].u();
+    if k == 0 { None } else { Some(unsafe { &*(k as *const KeyFn) }) }
+}
+fn same_key(a: Option<&KeyFn>, b: Option<&KeyFn>) -> bool {
+    match (a, b) {
+        (None, None) => true,
+        (Some(x), Some(y)) => Arc::ptr_eq(x, y),
+        _ => false,
+    }
+}
+pub fn count(prism: AnchoredLine) -> u32 { heap(prism).count() }
+
+fn cached_hash(prism: AnchoredLine) -> Option<u32> {
+    let h = prism[3].u32();
+    if h == 0 { None } else { Some(h) }
+}
+
+fn elem<'a>(h: Handle, idx: u32) -> &'a Value { unsafe { &*(h.nth(idx) as *const Value) } }
+
+/// Does the element at index `i` sort strictly before the one at index `j`?
+pub fn less(key: Option<&KeyFn>, h: Handle, i: u32, j: u32) -> bool {
+    let (a, b) = (elem(h, i), elem(h, j));
+    let res = match key {
+        None => a.partial_cmp(b),
+        Some(k) => k(a).partial_cmp(&k(b)),
+    };
+    res == Some(Ordering::Less)
+}
+
+pub fn sift_up(key: Option<&KeyFn>, mut h: Handle, mut i: u32) -> Handle {
+    while i > 0 {
+        let parent = (i - 1) >> 1;
+        if !less(key, h, i, parent) { break }
+        h = h.swap_idx(i, parent);
+        i = parent;
+    }
+    h
+}
+
+pub fn sift_down(key: Option<&KeyFn>, mut h: Handle, mut i: u32) -> Handle {
+    let ct = h.count();
+    loop {
+        let left = (i << 1) + 1;
+        if left >= ct { break }
+        let right = left + 1;
+        let child = if right < ct && less(key, h, right, left) { right } else { left };
+        if !less(key, h, child, i) { break }
+        h = h.swap_idx(i, child);
+        i = child;
+    }
+    h
+}
+
+pub fn conj(prism: AnchoredLine, x: Unit) -> Unit {
+    let p = unaliased(prism);
+    let h = heap(p);
+    let ct = h.count();
+    let h = sift_up(key(p), h.conj(x.handle()), ct);
+    p.set(1, h.unit());
+    p.set(3, Unit::zero());
+    p.segment().unit()
+}
+
+pub fn pop(prism: AnchoredLine) -> (Unit, Unit) {
+    if count(prism) == 0 {
+        return (prism.segment().unit(), Handle::nil().unit())
+    }
+    let p = unaliased(prism);
+    let h = heap(p);
+    let last = h.count() - 1;
+    let (h, least) = h.swap_idx(0, last).pop();
+    let h = sift_down(key(p), h, 0);
+    p.set(1, h.unit());
+    p.set(3, Unit::zero());
+    (p.segment().unit(), least.unit())
+}
+
+/// Merges two priority queues, ordered as the first.
+pub fn merge(a: Handle, b: Handle) -> Handle {
+    let (a_prism, b_prism) = (prism_of(a), prism_of(b));
+    let (big, small) = if count(a_prism) < count(b_prism) && same_key(key(a_prism), key(b_prism)) {
+        (b, a)
+    } else {
+        (a, b)
+    };
+    let s = heap(prism_of(small));
+    let ct = s.count();
+    let mut res = big;
+    for i in 0..ct {
+        let x = unsafe { *s.nth(i) };
+        res = res.conj(x.split());
+    }
+    small.retire();
+    res
+}
+
+/// Elements in priority order, as a vector.
+pub fn in_order(prism: AnchoredLine) -> Handle {
+    let k = key(prism);
+    let mut h = heap(prism).split();
+    let mut v = vector::new().handle();
+    while h.count() != 0 {
+        let last = h.count() - 1;
+        let (g, least) = h.swap_idx(0, last).pop();
+        h = sift_down(k, g, 0);
+        v = v.conj(least);
+    }
+    h.retire();
+    v
+}
+
+pub fn reduce(prism: AnchoredLine, process_stack: &mut [Box<dyn Process>]) -> Value {
+    let v = in_order(prism);
+    let ct = v.count();
+    for i in 0..ct {
+        if let Some(ret) = inges(process_stack, elem(v, i)) {
+            v.retire();
+            return ret;
+        }
+    }
+    v.retire();
+    last_call(process_stack)
+}
+
+impl Dispatch for Priority_ {
+    fn tear_down(&self, prism: AnchoredLine) {
+        let seg = prism.segment();
+        assert_eq!(0, seg.anchor().aliases());
+        prism[1].handle().retire();
+        let k = prism[2].u();
+        if k != 0 {
+            drop(unsafe { Box::from_raw(k as *mut KeyFn) });
+        }
+        Segment::free(seg)
+    }
+    fn alias_components(&self, prism: AnchoredLine) { alias_components(prism); }
+}
+impl Identification for Priority_ {
+    fn type_name(&self) -> &'static str { "PriorityQueue" }
+}
+impl Distinguish for Priority_ {
+    fn hash(&self, prism: AnchoredLine) -> u32 {
+        if let Some(h) = cached_hash(prism) {
+            return h
+        }
+        let v = in_order(prism);
+        let h = v.hash();
+        v.retire();
+        prism.set(3, Unit::from(h));
+        h
+    }
+    fn eq(&self, prism: AnchoredLine, other: Unit) -> bool {
+        let o = other.handle();
+        if let Some(o_prism) = find_prism(o) {
+            if count(prism) != count(o_prism) { return false }
+            if let (Some(x), Some(y)) = (cached_hash(prism), cached_hash(o_prism)) {
+                if x != y { return false }
+            }
+            let (v, w) = (in_order(prism), in_order(o_prism));
+            let res = v.eq(w);
+            v.retire();
+            w.retire();
+            res
+        } else {
+            false
+        }
+    }
+    fn cmp(&self, prism: AnchoredLine, other: Unit) -> Option<Ordering> {
+        let o = other.handle();
+        if let Some(o_prism) = find_prism(o) {
+            let (v, w) = (in_order(prism), in_order(o_prism));
+            let res = v.cmp(w);
+            v.retire();
+            w.retire();
+            return res
+        }
+        if o.is_ref() {
+            let o_prism_unit = o.logical_value()[0];
+            Some(prism_unit().cmp(&o_prism_unit))
+        } else {
+            Some(Ordering::Greater)
+        }
+    }
+}
+impl Aggregate for Priority_ {
+    fn is_aggregate(&self, prism: AnchoredLine) -> bool { true }
+    fn count(&self, prism: AnchoredLine) -> u32 { count(prism) }
+    fn empty(&self, prism: AnchoredLine) -> Unit { new_keyed(key(prism).cloned()) }
+    fn conj(&self, prism: AnchoredLine, x: Unit) -> Unit { conj(prism, x) }
+    fn peek(&self, prism: AnchoredLine) -> *const Unit {
+        if count(prism) == 0 {
+            (& ::handle::STATIC_NIL) as *const Unit
+        } else {
+            heap(prism).nth(0) as *const Unit
+        }
+    }
+    fn pop(&self, prism: AnchoredLine) -> (Unit, Unit) { pop(prism) }
+    fn reduce(&self, prism: AnchoredLine, process: &mut [Box<dyn Process>]) -> Value {
+        reduce(prism, process)
+    }
+}
+impl Sequential for Priority_ { }
+impl Associative for Priority_ { }
+impl Reversible for Priority_ { }
+impl Sorted for Priority_ { }
+impl Notation for Priority_ {
+    fn edn(&self, prism: AnchoredLine, f: &mut fmt::Formatter) -> fmt::Result {
+        let v = in_order(prism);
+        let res = write!(f, "#priority {}", v);
+        v.retire();
+        res
+    }
+}
+impl Numeral for Priority_ { }
+impl Callable for Priority_ { }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn least_first() {
+        let mut q = new_value();
+        for i in [5, 3, 8, 1, 9, 2, 7].iter() {
+            q = q.conj((*i).into());
+        }
+        let snapshot = q.split_out();
+        let mut popped = vec![];
+        while q.count() != 0 {
+            let (r, x) = q.pop();
+            popped.push(x.as_i64());
+            q = r;
+        }
+        assert_eq!(popped, vec![1, 2, 3, 5, 7, 8, 9]);
+        assert_eq!(format!("{}", snapshot), "#priority [1 2 3 5 7 8 9]");
+    }
+    #[test]
+    fn keyed_merge() {
+        fn negate(v: &Value) -> Value { Value::from(-v.as_i64()) }
+        let a = new_value_by(negate).conj(1.into()).conj(4.into());
+        let b = new_value_by(negate).conj(3.into()).conj(2.into()).conj(5.into());
+        let c = merge(a._consume(), b._consume()).value();
+        assert_eq!(c.count(), 5);
+        assert_eq!(*c.peek(), 5.into());
+        assert_eq!(format!("{}", c), "#priority [5 4 3 2 1]");
+    }
+    #[test]
+    fn closure_key() {
+        let m = 10;
+        let q = new_value_by(move |v: &Value| Value::from(v.as_i64() % m));
+        let q = q.conj(12.into()).conj(25.into()).conj(31.into());
+        let snapshot = q.split_out();
+        let (q, x) = q.pop();
+        assert_eq!(x, 31.into());
+        assert_eq!(format!("{}", q.conj(40.into())), "#priority [40 12 25]");
+        assert_eq!(format!("{}", snapshot), "#priority [31 12 25]");
+        let h = snapshot.hash();
+        assert_eq!(snapshot.hash(), h);
+        let o = new_value().conj(25.into()).conj(31.into()).conj(12.into());
+        assert!(snapshot != o && snapshot.hash() != o.hash());
+        assert_eq!(prism_of(snapshot._handle())[3].u(), h as usize);
+    }
+    #[test]
+    #[should_panic(expected = "Not a priority queue: [1]")]
+    fn merge_others() {
+        let v = vector::new_value().conj(1.into());
+        merge(new_value()._consume(), v._consume());
+    }
+}
@@ -0,0 +1,131 @@
+// Copyright (c) Cole Frederick. All rights reserved.
+// The use and distribution terms for this software are covered by the
+// Eclipse Public License 1.0 (https://opensource.org/licenses/eclipse-1.0.php)
+// which can be found in the file epl-v10.html at the root of this distribution.
+// By using this software in any fashion, you are agreeing to be bound by the terms of this license.
+// You must not remove this notice, or any other, from this software.
+
+//! The elements of a collection, repeated in order without end.
+
+use std::fmt;
+use std::cmp::Ordering;
+use memory::*;
+use dispatch::*;
+use value::*;
+use handle::Handle;
+use transduce::{inges, last_call, Process, Transducers};
+use list;
+use vector;
+
+// Layout: [prism elements(Vector)]
+pub struct Cycle_ { }
+pub fn prism_unit() -> Unit { mechanism::prism::<Cycle_>() }
+pub fn is_prism(prism: AnchoredLine) -> bool { prism[0] == prism_unit() }
+pub fn find_prism(h: Handle) -> Option<AnchoredLine> { h.find_prism(prism_unit()) }
+pub fn is_cycle(h: Handle) -> bool { find_prism(h).is_some() }
+
+pub fn new(coll: Handle) -> Unit {
+    let elements = if vector::is_vector(coll) { coll } else {
+        let v = coll.pour(Transducers::new(), vector::new().handle());
+        coll.retire();
+        v
+    };
+    let s = Segment::new(2 /*prism elements*/);
+    s.set(0, prism_unit());
+    s.set(1, elements.unit());
+    s.unit()
+}
+
+pub fn reduce(prism: AnchoredLine, process_stack: &mut [Box<dyn Process>]) -> Value {
+    let v = prism[1].handle();
+    let ct = v.count();
+    if ct != 0 {
+        loop {
+            for i in 0..ct {
+                let x = unsafe { &*(v.nth(i) as *const Value) };
+                if let Some(ret) = inges(process_stack, x) {
+                    return ret;
+                }
+            }
+        }
+    }
+    last_call(process_stack)
+}
+
+impl Dispatch for Cycle_ {
+    fn tear_down(&self, prism: AnchoredLine) {
+        let seg = prism.segment();
+        assert_eq!(0, seg.anchor().aliases());
+        prism[1].handle().retire();
+        Segment::free(seg)
+    }
+    fn alias_components(&self, prism: AnchoredLine) { prism[1].handle().split(); }
+}
+impl Identification for Cycle_ {
+    fn type_name(&self) -> &'static str { "Cycle" }
+}
+impl Distinguish for Cycle_ {
+    fn hash(&self, prism: AnchoredLine) -> u32 {
+        // Equal only to cycles of the same elements.
+        use random::{PI, cycle_abc};
+        cycle_abc(210, PI[325].wrapping_add(prism[1].handle().hash() as u64)) as u32
+    }
+    fn eq(&self, prism: AnchoredLine, other: Unit) -> bool {
+        let o = other.handle();
+        if let Some(o_prism) = find_prism(o) {
+            prism[1].handle().eq(o_prism[1].handle())
+        } else {
+            false
+        }
+    }
+    fn cmp(&self, prism: AnchoredLine, other: Unit) -> Option<Ordering> {
+        let o = other.handle();
+        if let Some(o_prism) = find_prism(o) {
+            return prism[1].handle().cmp(o_prism[1].handle())
+        }
+        if o.is_ref() {
+            let o_prism_unit = o.logical_value()[0];
+            Some(prism_unit().cmp(&o_prism_unit))
+        } else {
+            Some(Ordering::Greater)
+        }
+    }
+}
+impl Aggregate for Cycle_ {
+    fn is_aggregate(&self, prism: AnchoredLine) -> bool { true }
+    fn count(&self, prism: AnchoredLine) -> u32 {
+        if prism[1].handle().count() == 0 { 0 } else { panic!("Can't count an infinite cycle.") }
+    }
+    fn empty(&self, prism: AnchoredLine) -> Unit { list::new() }
+    fn reduce(&self, prism: AnchoredLine, process: &mut [Box<dyn Process>]) -> Value {
+        reduce(prism, process)
+    }
+}
+impl Sequential for Cycle_ {
+    fn is_sequential(&self, prism: AnchoredLine) -> bool { true }
+    fn nth(&self, prism: AnchoredLine, idx: u32) -> *const Unit {
+        let v = prism[1].handle();
+        let ct = v.count();
+        if ct == 0 {
+            panic!("Index out of bounds: {} in empty cycle", idx);
+        }
+        v.nth(idx % ct) as *const Unit
+    }
+}
+impl Associative for Cycle_ { }
+impl Reversible for Cycle_ { }
+impl Sorted for Cycle_ { }
+impl Notation for Cycle_ {
+    fn edn(&self, prism: AnchoredLine, f: &mut fmt::Formatter) -> fmt::Result {
+        let v = prism[1].handle();
+        let ct = v.count();
+        write!(f, "(")?;
+        for i in 0..ct {
+            let x = unsafe { *v.nth(i) };
+            write!(f, "{}{}", if i == 0 { "" } else { " " }, x)?;
+        }
+        write!(f, "{})", if ct == 0 { "" } else { " ..." })
+    }
+}
+impl Numeral for Cycle_ { }
+impl Callable for Cycle_ { }
@@ -0,0 +1,224 @@
+// Copyright (c) Cole Frederick. All rights reserved.
+// The use and distribution terms for this software are covered by the
+// Eclipse Public License 1.0 (https://opensource.org/licenses/eclipse-1.0.php)
+// which can be found in the file epl-v10.html at the root of this distribution.
+// By using this software in any fashion, you are agreeing to be bound by the terms of this license.
+// You must not remove this notice, or any other, from this software.
+
+//! Infinite sequences computed by a function.
+//!
+//! Iterate yields x, f(x), f(f(x)), and so on. Repeatedly yields the results
+//! of calling f again and again, for functions with side effects.
+
+use std::fmt;
+use std::cmp::Ordering;
+use memory::*;
+use dispatch::*;
+use value::*;
+use handle::Handle;
+use transduce::{inges, ingest, Process, Shared};
+use list;
+
+pub trait Step: Fn(&Value) -> Value + Shared { }
+impl<F: Fn(&Value) -> Value + Shared> Step for F { }
+pub trait Make: Fn() -> Value + Shared { }
+impl<F: Fn() -> Value + Shared> Make for F { }
+
+// Layout: [prism f x], f is a boxed Box<dyn Step>
+pub struct Iterate_ { }
+pub fn prism_unit() -> Unit { mechanism::prism::<Iterate_>() }
+pub fn is_prism(prism: AnchoredLine) -> bool { prism[0] == prism_unit() }
+pub fn find_prism(h: Handle) -> Option<AnchoredLine> { h.find_prism(prism_unit()) }
+pub fn is_iterate(h: Handle) -> bool { find_prism(h).is_some() }
+
+pub fn new(f: impl Step + 'static, x: Handle) -> Unit {
+    let f: Box<dyn Step> = Box::new(f);
+    let s = Segment::new(3 /*prism f x*/);
+    s.set(0, prism_unit());
+    s.set(1, Unit::from(Box::into_raw(Box::new(f)) as usize));
+    s.set(2, x.unit());
+    s.unit()
+}
+pub fn step<'a>(prism: AnchoredLine) -> &'a dyn Step {
+    unsafe { &**(prism[1].u() as *const Box<dyn Step>) }
+}
+
+pub fn reduce(prism: AnchoredLine, process_stack: &mut [Box<dyn Process>]) -> Value {
+    let f = step(prism);
+    let mut x = prism[2].handle().split().value();
+    loop {
+        if let Some(ret) = inges(process_stack, &x) {
+            return ret;
+        }
+        x = f(&x);
+    }
+}
+
+impl Dispatch for Iterate_ {
+    fn tear_down(&self, prism: AnchoredLine) {
+        let seg = prism.segment();
+        assert_eq!(0, seg.anchor().aliases());
+        prism[2].handle().retire();
+        drop(unsafe { Box::from_raw(prism[1].u() as *mut Box<dyn Step>) });
+        Segment::free(seg)
+    }
+    fn alias_components(&self, prism: AnchoredLine) { prism[2].handle().split(); }
+}
+impl Identification for Iterate_ {
+    fn type_name(&self) -> &'static str { "Iterate" }
+}
+impl Distinguish for Iterate_ {
+    fn hash(&self, prism: AnchoredLine) -> u32 {
+        // Equal only to iterates of the same f and seed.
+        use random::{PI, cycle_abc};
+        let y = cycle_abc(7, PI[323].wrapping_add(prism[1].u64()));
+        let y = cycle_abc(34, y.wrapping_add(prism[2].handle().hash() as u64));
+        cycle_abc(210, y) as u32
+    }
+    fn eq(&self, prism: AnchoredLine, other: Unit) -> bool {
+        let o = other.handle();
+        if let Some(o_prism) = find_prism(o) {
+            prism[1] == o_prism[1] && prism[2].handle().eq(o_prism[2].handle())
+        } else {
+            false
+        }
+    }
+    fn cmp(&self, prism: AnchoredLine, other: Unit) -> Option<Ordering> {
+        let o = other.handle();
+        if let Some(o_prism) = find_prism(o) {
+            // By seed, then by f, in no meaningful order.
+            return match prism[2].handle().cmp(o_prism[2].handle()) {
+                Some(Ordering::Equal) => Some(prism[1].cmp(&o_prism[1])),
+                res => res,
+            }
+        }
+        if o.is_ref() {
+            let o_prism_unit = o.logical_value()[0];
+            Some(prism_unit().cmp(&o_prism_unit))
+        } else {
+            Some(Ordering::Greater)
+        }
+    }
+}
+impl Aggregate for Iterate_ {
+    fn is_aggregate(&self, prism: AnchoredLine) -> bool { true }
+    fn count(&self, prism: AnchoredLine) -> u32 { panic!("Can't count an infinite iterate.") }
+    fn empty(&self, prism: AnchoredLine) -> Unit { list::new() }
+    fn reduce(&self, prism: AnchoredLine, process: &mut [Box<dyn Process>]) -> Value {
+        reduce(prism, process)
+    }
+}
+impl Sequential for Iterate_ {
+    fn is_sequential(&self, prism: AnchoredLine) -> bool { true }
+    fn nth_out(&self, prism: AnchoredLine, idx: u32) -> Unit {
+        let f = step(prism);
+        let mut x = prism[2].handle().split().value();
+        for _ in 0..idx {
+            x = f(&x);
+        }
+        x._consume().unit()
+    }
+}
+impl Associative for Iterate_ { }
+impl Reversible for Iterate_ { }
+impl Sorted for Iterate_ { }
+impl Notation for Iterate_ {
+    fn edn(&self, prism: AnchoredLine, f: &mut fmt::Formatter) -> fmt::Result {
+        let g = step(prism);
+        let mut x = prism[2].handle().split().value();
+        write!(f, "(")?;
+        for i in 0..3 {
+            if i != 0 {
+                x = g(&x);
+            }
+            write!(f, "{} ", x)?;
+        }
+        write!(f, "...)")
+    }
+}
+impl Numeral for Iterate_ { }
+impl Callable for Iterate_ { }
+
+// Layout: [prism f], f is a boxed Box<dyn Make>
+pub struct Repeatedly_ { }
+pub fn repeatedly_prism_unit() -> Unit { mechanism::prism::<Repeatedly_>() }
+pub fn is_repeatedly(h: Handle) -> bool { h.find_prism(repeatedly_prism_unit()).is_some() }
+
+pub fn new_repeatedly(f: impl Make + 'static) -> Unit {
+    let f: Box<dyn Make> = Box::new(f);
+    let s = Segment::new(2 /*prism f*/);
+    s.set(0, repeatedly_prism_unit());
+    s.set(1, Unit::from(Box::into_raw(Box::new(f)) as usize));
+    s.unit()
+}
+pub fn make<'a>(prism: AnchoredLine) -> &'a dyn Make {
+    unsafe { &**(prism[1].u() as *const Box<dyn Make>) }
+}
+
+impl Dispatch for Repeatedly_ {
+    fn tear_down(&self, prism: AnchoredLine) {
+        let seg = prism.segment();
+        assert_eq!(0, seg.anchor().aliases());
+        drop(unsafe { Box::from_raw(prism[1].u() as *mut Box<dyn Make>) });
+        Segment::free(seg)
+    }
+    fn alias_components(&self, prism: AnchoredLine) { }
+}
+impl Identification for Repeatedly_ {
+    fn type_name(&self) -> &'static str { "Repeatedly" }
+}
+impl Distinguish for Repeatedly_ {
+    fn hash(&self, prism: AnchoredLine) -> u32 {
+        // Equal only to itself.
+        use random::{PI, cycle_abc};
+        cycle_abc(210, PI[324].wrapping_add(prism[1].u64())) as u32
+    }
+    fn eq(&self, prism: AnchoredLine, other: Unit) -> bool {
+        let o = other.handle();
+        if let Some(o_prism) = o.find_prism(repeatedly_prism_unit()) {
+            prism[1] == o_prism[1]
+        } else {
+            false
+        }
+    }
+    fn cmp(&self, prism: AnchoredLine, other: Unit) -> Option<Ordering> {
+        let o = other.handle();
+        if let Some(o_prism) = o.find_prism(repeatedly_prism_unit()) {
+            // In no meaningful order.
+            return Some(prism[1].cmp(&o_prism[1]))
+        }
+        if o.is_ref() {
+            let o_prism_unit = o.logical_value()[0];
+            Some(repeatedly_prism_unit().cmp(&o_prism_unit))
+        } else {
+            Some(Ordering::Greater)
+        }
+    }
+}
+impl Aggregate for Repeatedly_ {
+    fn is_aggregate(&self, prism: AnchoredLine) -> bool { true }
+    fn count(&self, prism: AnchoredLine) -> u32 { panic!("Can't count an infinite repeatedly.") }
+    fn empty(&self, prism: AnchoredLine) -> Unit { list::new() }
+    fn reduce(&self, prism: AnchoredLine, process_stack: &mut [Box<dyn Process>]) -> Value {
+        let f = make(prism);
+        loop {
+            if let Some(ret) = ingest(process_stack, f()) {
+                return ret;
+            }
+        }
+    }
+}
+impl Sequential for Repeatedly_ {
+    fn is_sequential(&self, prism: AnchoredLine) -> bool { true }
+}
+impl Associative for Repeatedly_ { }
+impl Reversible for Repeatedly_ { }
+impl Sorted for Repeatedly_ { }
+impl Notation for Repeatedly_ {
+    fn edn(&self, prism: AnchoredLine, f: &mut fmt::Formatter) -> fmt::Result {
+        // Elements can't be shown without calling f, and its side effects.
+        write!(f, "(...)")
+    }
+}
+impl Numeral for Repeatedly_ { }
+impl Callable for Repeatedly_ { }
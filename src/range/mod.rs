@@ -5,10 +5,339 @@
 // By using this software in any fashion, you are agreeing to be bound by the terms of this license.
 // You must not remove this notice, or any other, from this software.
 
-// A reducible object with start and end points
+//! A reducible object with start and end points.
+//!
+//! A range of integers from start (inclusive) toward end (exclusive), by step.
+//! Elements are computed on demand, so count and nth_out are constant time and
+//! reducing doesn't allocate a backing collection. A range without an end
+//! point is infinite; reduce it with a transducer that stops early, like take.
+
+use std::fmt;
+use std::cmp::Ordering;
+use memory::*;
+use dispatch::*;
+use value::*;
+use handle::Handle;
+use transduce::{ingest, last_call, Process};
+use integral;
+use list;
+use vector;
+
+pub mod repeat;
+pub mod cycle;
+pub mod iterate;
+
+// Layout: [prism start step end bounded], each number in `width()` units
+pub struct Range_ { }
+pub fn prism_unit() -> Unit { mechanism::prism::<Range_>() }
+pub fn is_prism(prism: AnchoredLine) -> bool { prism[0] == prism_unit() }
+pub fn find_prism(h: Handle) -> Option<AnchoredLine> { h.find_prism(prism_unit()) }
+pub fn is_range(h: Handle) -> bool { find_prism(h).is_some() }
+
+pub fn width() -> u32 { if cfg!(target_pointer_width = "32") { 2 } else { 1 } }
+
+fn new_(start: i64, step: i64, end: Option<i64>) -> Unit {
+    assert_ne!(step, 0, "A range can't have a step of zero.");
+    let w = width() as i32;
+    let s = Segment::new(1 /*prism*/ + 3 * width() /*start step end*/ + 1 /*bounded*/);
+    let prism = s.line_at(0);
+    prism.set(0, prism_unit());
+    integral::store(prism.offset(1), start);
+    integral::store(prism.offset(1 + w), step);
+    integral::store(prism.offset(1 + 2 * w), end.unwrap_or(0));
+    prism.set(1 + 3 * w, Unit::from(end.is_some() as u32));
+    s.unit()
+}
+pub fn new(start: i64, end: i64, step: i64) -> Unit { new_(start, step, Some(end)) }
+pub fn new_infinite(start: i64, step: i64) -> Unit { new_(start, step, None) }
+pub fn new_value(start: i64, end: i64, step: i64) -> Value { new(start, end, step).handle().value() }
+pub fn new_infinite_value(start: i64, step: i64) -> Value { new_infinite(start, step).handle().value() }
+
+pub fn start(prism: AnchoredLine) -> i64 { integral::hydrate(prism.offset(1)) }
+pub fn step(prism: AnchoredLine) -> i64 { integral::hydrate(prism.offset(1 + width() as i32)) }
+pub fn end(prism: AnchoredLine) -> Option<i64> {
+    let w = width() as i32;
+    if prism[1 + 3 * w].u() == 0 { None } else { Some(integral::hydrate(prism.offset(1 + 2 * w))) }
+}
+
+/// Element count, or None when the range is infinite.
+pub fn size(prism: AnchoredLine) -> Option<u32> {
+    end(prism).map(|e| {
+        let (s, st) = (start(prism) as i128, step(prism) as i128);
+        let span = e as i128 - s;
+        let ct = if st > 0 {
+            if span <= 0 { 0 } else { (span + st - 1) / st }
+        } else {
+            if span >= 0 { 0 } else { (span + st + 1) / st }
+        };
+        assert!(ct <= u32::MAX as i128, "Range too large to count: {} elements", ct);
+        ct as u32
+    })
+}
+pub fn count(prism: AnchoredLine) -> u32 {
+    size(prism).expect("Can't count an infinite range.")
+}
+
+/// Is h a sequence without end: an infinite range, an iterate, a repeatedly or a nonempty cycle?
+pub fn is_infinite(h: Handle) -> bool {
+    if let Some(prism) = find_prism(h) {
+        return size(prism).is_none()
+    }
+    if let Some(prism) = cycle::find_prism(h) {
+        return prism[1].handle().count() != 0
+    }
+    iterate::is_iterate(h) || iterate::is_repeatedly(h)
+}
+
+pub fn nth(prism: AnchoredLine, idx: u32) -> i64 {
+    if let Some(ct) = size(prism) {
+        if idx >= ct {
+            panic!("Index out of bounds: {} in range of count {}", idx, ct);
+        }
+    }
+    // Elements of a bounded range lie within it, an infinite one may run past i64.
+    let x = start(prism) as i128 + (idx as i128) * step(prism) as i128;
+    if x < i64::MIN as i128 || x > i64::MAX as i128 {
+        panic!("Range element {} is out of 64 bit bounds.", idx);
+    }
+    x as i64
+}
+
+pub fn reduce(prism: AnchoredLine, process_stack: &mut [Box<dyn Process>]) -> Value {
+    match size(prism) {
+        Some(ct) => {
+            for i in 0..ct {
+                if let Some(ret) = ingest(process_stack, Value::from(nth(prism, i))) {
+                    return ret;
+                }
+            }
+        },
+        None => {
+            let st = step(prism);
+            let mut x = start(prism);
+            loop {
+                if let Some(ret) = ingest(process_stack, Value::from(x)) {
+                    return ret;
+                }
+                x = match x.checked_add(st) {
+                    Some(y) => y,
+                    None => panic!("Infinite range ran past 64 bit bounds after {}.", x),
+                };
+            }
+        },
+    }
+    last_call(process_stack)
+}
+
+impl Dispatch for Range_ { /*default tear_down, alias_components*/ }
+impl Identification for Range_ {
+    fn type_name(&self) -> &'static str { "Range" }
+}
+impl Distinguish for Range_ {
+    fn hash(&self, prism: AnchoredLine) -> u32 {
+        // Same mixing as vector and list, so equal sequences hash alike.
+        use random::{PI, cycle_abc};
+        let ct = match size(prism) {
+            Some(ct) => ct,
+            None => {
+                // Equal only to infinite ranges of the same start and step.
+                let y = cycle_abc(7, PI[322].wrapping_add(Value::from(start(prism)).hash() as u64));
+                let y = cycle_abc(34, y.wrapping_add(Value::from(step(prism)).hash() as u64));
+                return cycle_abc(210, y) as u32
+            },
+        };
+        let mut y = cycle_abc(7, PI[321].wrapping_add(ct as u64));
+        for i in 0..ct {
+            let h = Value::from(nth(prism, i)).hash() as u64;
+            y = cycle_abc(34, y.wrapping_add(h));
+        }
+        cycle_abc(210, y) as u32
+    }
+    fn eq(&self, prism: AnchoredLine, other: Unit) -> bool {
+        let o = other.handle();
+        if let Some(o_prism) = find_prism(o) {
+            return match (size(prism), size(o_prism)) {
+                (None, None) => start(prism) == start(o_prism) && step(prism) == step(o_prism),
+                (Some(a), Some(b)) => a == b && (a == 0 || (start(prism) == start(o_prism) &&
+                    (a == 1 || step(prism) == step(o_prism)))),
+                _ => false,
+            }
+        }
+        if !(list::is_list(o) || vector::is_vector(o)) { return false }
+        let ct = match size(prism) { Some(ct) => ct, None => { return false } };
+        if ct != o.count() { return false }
+        for i in 0..ct {
+            let y = unsafe { *o.nth(i) };
+            if !Value::from(nth(prism, i))._handle().eq(y) { return false }
+        }
+        true
+    }
+    fn cmp(&self, prism: AnchoredLine, other: Unit) -> Option<Ordering> {
+        let o = other.handle();
+        if let Some(o_prism) = find_prism(o) {
+            // None counts as infinite, greater than any count.
+            let (ct, o_ct) = (size(prism), size(o_prism));
+            let prefix = match (ct, o_ct) {
+                (Some(a), Some(b)) => a.min(b),
+                (Some(a), None) | (None, Some(a)) => a,
+                // Elements differ by the first or second, if at all.
+                (None, None) => 2,
+            };
+            for i in 0..prefix {
+                let res = nth(prism, i).cmp(&nth(o_prism, i));
+                if res != Ordering::Equal { return Some(res) }
+            }
+            return Some(match (ct, o_ct) {
+                (Some(a), Some(b)) => a.cmp(&b),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            })
+        }
+        if o.is_ref() {
+            let o_prism_unit = o.logical_value()[0];
+            Some(prism_unit().cmp(&o_prism_unit))
+        } else {
+            Some(Ordering::Greater)
+        }
+    }
+}
+impl Aggregate for Range_ {
+    fn is_aggregate(&self, prism: AnchoredLine) -> bool { true }
+    fn count(&self, prism: AnchoredLine) -> u32 { count(prism) }
+    fn empty(&self, prism: AnchoredLine) -> Unit { list::new() }
+    fn reduce(&self, prism: AnchoredLine, process: &mut [Box<dyn Process>]) -> Value {
+        reduce(prism, process)
+    }
+}
+impl Sequential for Range_ {
+    fn is_sequential(&self, prism: AnchoredLine) -> bool { true }
+    fn nth(&self, prism: AnchoredLine, idx: u32) -> *const Unit {
+        // Elements are computed, there's none to lend.
+        panic!("Range elements can't be borrowed, use nth_out.")
+    }
+    fn nth_out(&self, prism: AnchoredLine, idx: u32) -> Unit { integral::new(nth(prism, idx)) }
+}
+impl Associative for Range_ { }
+impl Reversible for Range_ { }
+impl Sorted for Range_ { }
+impl Notation for Range_ {
+    fn edn(&self, prism: AnchoredLine, f: &mut fmt::Formatter) -> fmt::Result {
+        // An infinite range prints its first few elements, then an ellipsis.
+        let (ct, more) = match size(prism) {
+            Some(ct) => (ct, ""),
+            None => (3, " ..."),
+        };
+        write!(f, "(")?;
+        for i in 0..ct {
+            write!(f, "{}{}", if i == 0 { "" } else { " " }, nth(prism, i))?;
+        }
+        write!(f, "{})", more)
+    }
+}
+impl Numeral for Range_ { }
+impl Callable for Range_ { }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-
+    #[test]
+    fn counts() {
+        assert_eq!(new_value(0, 10, 1).count(), 10);
+        assert_eq!(new_value(0, 10, 3).count(), 4);
+        assert_eq!(new_value(10, 0, -3).count(), 4);
+        assert_eq!(new_value(5, 5, 1).count(), 0);
+        assert_eq!(new_value(5, 0, 1).count(), 0);
+        let r = new_value(10, 0, -3);
+        assert_eq!(r.nth_out(3), 1.into());
+        assert_eq!(format!("{}", r), "(10 7 4 1)");
+        let v: Value = "[10 7 4 1]".parse().unwrap();
+        assert_eq!(r, v);
+        assert_eq!(r.hash(), v.hash());
+    }
+    #[test]
+    fn infinite() {
+        struct Upto { sum: i64, limit: i64 }
+        impl Process for Upto {
+            fn ingest(&mut self, stack: &mut [Box<dyn Process>], v: Value) -> Option<Value> {
+                let x = v.as_i64();
+                if x >= self.limit { return Some(Value::from(self.sum)) }
+                self.sum += x;
+                None
+            }
+            fn last_call(&mut self, stack: &mut [Box<dyn Process>]) -> Value { Value::from(self.sum) }
+        }
+        let r = new_infinite_value(1, 1);
+        assert_eq!(format!("{}", r), "(1 2 3 ...)");
+        let mut stack: Vec<Box<dyn Process>> = vec![Box::new(Upto { sum: 0, limit: 101 })];
+        assert_eq!(r.split_out().reduce(&mut stack), 5050.into());
+        assert_eq!(r, new_infinite_value(1, 1));
+        assert_eq!(r.hash(), new_infinite_value(1, 1).hash());
+        assert!(r < new_infinite_value(1, 2) && new_infinite_value(0, 5) < r);
+        assert!(new_value(1, 50, 1) < r && r > new_value(1, 2, 1));
+    }
+    #[test]
+    fn nth_and_bounds() {
+        let r = new_value(0, 10, 1);
+        assert_eq!(::nth_out(&r, 3), 3.into());
+        assert_eq!((r.nth_out(9), r.nth_out(3)), (Value::from(9), Value::from(3)));
+        let top = new_value(i64::max_value() - 4, i64::max_value(), 3);
+        let v = ::into(::vector(), ::map(|x: &Value| x.split_out()), top.split_out());
+        assert_eq!(format!("{}", v), "[9223372036854775803 9223372036854775806]");
+        assert_eq!(top.nth_out(1), Value::from(i64::max_value() - 1));
+    }
+    #[test]
+    #[should_panic(expected = "Range elements can't be borrowed, use nth_out.")]
+    fn lends_nothing() {
+        new_value(0, 10, 1).nth(3);
+    }
+    #[test]
+    fn generators() {
+        struct Take { n: u32, c: Value }
+        impl Process for Take {
+            fn ingest(&mut self, stack: &mut [Box<dyn Process>], v: Value) -> Option<Value> {
+                self.c = self.c.split_out().conj(v);
+                self.n -= 1;
+                if self.n == 0 { Some(self.c.split_out()) } else { None }
+            }
+            fn last_call(&mut self, stack: &mut [Box<dyn Process>]) -> Value { self.c.split_out() }
+        }
+        fn take(n: u32, coll: Value) -> String {
+            let mut stack: Vec<Box<dyn Process>> = vec![Box::new(Take { n, c: vector::new_value() })];
+            format!("{}", coll.reduce(&mut stack))
+        }
+        fn double(x: &Value) -> Value { Value::from(x.as_i64() * 2) }
+        fn seven() -> Value { Value::from(7) }
+        let iterate = iterate::new(double, Value::from(1)._consume()).handle().value();
+        assert_eq!(format!("{}", iterate), "(1 2 4 ...)");
+        assert_eq!(take(5, iterate), "[1 2 4 8 16]");
+        let k = 3;
+        let triple = iterate::new(move |x: &Value| Value::from(x.as_i64() * k), Value::from(1)._consume());
+        let triple = triple.handle().value();
+        assert_eq!(take(4, triple.split_out()), "[1 3 9 27]");
+        assert_eq!(triple.hash(), triple.split_out().hash());
+        assert_eq!(triple.partial_cmp(&triple.split_out()), Some(Ordering::Equal));
+        let other = iterate::new(double, Value::from(1)._consume()).handle().value();
+        assert!(triple != other && triple.partial_cmp(&other).is_some());
+        let cycle = cycle::new("[1 2 3]".parse::<Value>().unwrap()._consume()).handle().value();
+        assert_eq!(format!("{}", cycle), "(1 2 3 ...)");
+        assert_eq!(take(5, cycle.split_out()), "[1 2 3 1 2]");
+        let same = cycle::new("[1 2 3]".parse::<Value>().unwrap()._consume()).handle().value();
+        let other = cycle::new("[1 2 4]".parse::<Value>().unwrap()._consume()).handle().value();
+        assert!(cycle == same && cycle.hash() == same.hash() && cycle.hash() != other.hash());
+        assert!(cycle < other);
+        let repeat = repeat::new(Value::from("x")._consume()).handle().value();
+        assert_eq!(take(3, repeat), "[\"x\" \"x\" \"x\"]");
+        let repeat = repeat::new_n(2, Value::from(0)._consume()).handle().value();
+        assert_eq!(repeat, "[0 0]".parse().unwrap());
+        assert_eq!(take(4, repeat), "[0 0]");
+        let repeatedly = iterate::new_repeatedly(seven).handle().value();
+        assert_eq!(take(2, repeatedly.split_out()), "[7 7]");
+        assert_eq!(repeatedly.hash(), repeatedly.split_out().hash());
+        use std::sync::atomic::{AtomicI64, Ordering::SeqCst};
+        let counter = AtomicI64::new(0);
+        let counting = iterate::new_repeatedly(move || Value::from(counter.fetch_add(1, SeqCst) + 1));
+        let counting = counting.handle().value();
+        assert_eq!(take(3, counting), "[1 2 3]");
+    }
 }
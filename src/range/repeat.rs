@@ -0,0 +1,145 @@
+// Copyright (c) Cole Frederick. All rights reserved.
+// The use and distribution terms for this software are covered by the
+// Eclipse Public License 1.0 (https://opensource.org/licenses/eclipse-1.0.php)
+// which can be found in the file epl-v10.html at the root of this distribution.
+// By using this software in any fashion, you are agreeing to be bound by the terms of this license.
+// You must not remove this notice, or any other, from this software.
+
+//! A value repeated, either some number of times or without end.
+
+use std::fmt;
+use std::cmp::Ordering;
+use memory::*;
+use dispatch::*;
+use value::*;
+use handle::Handle;
+use transduce::{inges, last_call, Process};
+use list;
+use vector;
+
+// Layout: [prism x count bounded]
+pub struct Repeat_ { }
+pub fn prism_unit() -> Unit { mechanism::prism::<Repeat_>() }
+pub fn is_prism(prism: AnchoredLine) -> bool { prism[0] == prism_unit() }
+pub fn find_prism(h: Handle) -> Option<AnchoredLine> { h.find_prism(prism_unit()) }
+pub fn is_repeat(h: Handle) -> bool { find_prism(h).is_some() }
+
+fn new_(x: Handle, count: Option<u32>) -> Unit {
+    let s = Segment::new(4 /*prism x count bounded*/);
+    s.set(0, prism_unit());
+    s.set(1, x.unit());
+    s.set(2, Unit::from(count.unwrap_or(0)));
+    s.set(3, Unit::from(count.is_some() as u32));
+    s.unit()
+}
+pub fn new(x: Handle) -> Unit { new_(x, None) }
+pub fn new_n(count: u32, x: Handle) -> Unit { new_(x, Some(count)) }
+
+pub fn size(prism: AnchoredLine) -> Option<u32> {
+    if prism[3].u() == 0 { None } else { Some(prism[2].u32()) }
+}
+pub fn count(prism: AnchoredLine) -> u32 {
+    size(prism).expect("Can't count an infinite repeat.")
+}
+
+pub fn reduce(prism: AnchoredLine, process_stack: &mut [Box<dyn Process>]) -> Value {
+    let x = unsafe { &*(prism.offset(1).line().star() as *const Value) };
+    match size(prism) {
+        Some(ct) => {
+            for _ in 0..ct {
+                if let Some(ret) = inges(process_stack, x) {
+                    return ret;
+                }
+            }
+        },
+        None => {
+            loop {
+                if let Some(ret) = inges(process_stack, x) {
+                    return ret;
+                }
+            }
+        },
+    }
+    last_call(process_stack)
+}
+
+impl Dispatch for Repeat_ {
+    fn tear_down(&self, prism: AnchoredLine) {
+        let seg = prism.segment();
+        assert_eq!(0, seg.anchor().aliases());
+        prism[1].handle().retire();
+        Segment::free(seg)
+    }
+    fn alias_components(&self, prism: AnchoredLine) { prism[1].handle().split(); }
+}
+impl Identification for Repeat_ {
+    fn type_name(&self) -> &'static str { "Repeat" }
+}
+impl Distinguish for Repeat_ {
+    fn hash(&self, prism: AnchoredLine) -> u32 {
+        // Same mixing as vector and list, so equal sequences hash alike.
+        use random::{PI, cycle_abc};
+        let ct = count(prism);
+        let h = prism[1].handle().hash() as u64;
+        let mut y = cycle_abc(7, PI[321].wrapping_add(ct as u64));
+        for _ in 0..ct {
+            y = cycle_abc(34, y.wrapping_add(h));
+        }
+        cycle_abc(210, y) as u32
+    }
+    fn eq(&self, prism: AnchoredLine, other: Unit) -> bool {
+        let o = other.handle();
+        let ct = size(prism);
+        if let Some(o_prism) = find_prism(o) {
+            return ct == size(o_prism) &&
+                (ct == Some(0) || prism[1].handle().eq(o_prism[1].handle()))
+        }
+        if !(list::is_list(o) || vector::is_vector(o)) { return false }
+        let ct = match ct { Some(ct) => ct, None => { return false } };
+        if ct != o.count() { return false }
+        let x = prism[1].handle();
+        for i in 0..ct {
+            let y = unsafe { *o.nth(i) };
+            if !x.eq(y) { return false }
+        }
+        true
+    }
+}
+impl Aggregate for Repeat_ {
+    fn is_aggregate(&self, prism: AnchoredLine) -> bool { true }
+    fn count(&self, prism: AnchoredLine) -> u32 { count(prism) }
+    fn empty(&self, prism: AnchoredLine) -> Unit { list::new() }
+    fn reduce(&self, prism: AnchoredLine, process: &mut [Box<dyn Process>]) -> Value {
+        reduce(prism, process)
+    }
+}
+impl Sequential for Repeat_ {
+    fn is_sequential(&self, prism: AnchoredLine) -> bool { true }
+    fn nth(&self, prism: AnchoredLine, idx: u32) -> *const Unit {
+        if let Some(ct) = size(prism) {
+            if idx >= ct {
+                panic!("Index out of bounds: {} in repeat of count {}", idx, ct);
+            }
+        }
+        prism.offset(1).line().star()
+    }
+}
+impl Associative for Repeat_ { }
+impl Reversible for Repeat_ { }
+impl Sorted for Repeat_ { }
+impl Notation for Repeat_ {
+    fn edn(&self, prism: AnchoredLine, f: &mut fmt::Formatter) -> fmt::Result {
+        let (ct, more) = match size(prism) {
+            Some(ct) => (ct, ""),
+            None => (3, " ..."),
+        };
+        let x = prism[1].handle();
+        write!(f, "(")?;
+        for i in 0..ct {
+            write!(f, "{}{}", if i == 0 { "" } else { " " }, x)?;
+        }
+        write!(f, "{})", more)
+    }
+}
+impl Numeral for Repeat_ { }
+impl Callable for Repeat_ { }
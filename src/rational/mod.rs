@@ -10,9 +10,11 @@ use std::cmp::Ordering;
 use memory::*;
 use dispatch::*;
 use handle::Handle;
-use integral;
+use integral::{self, big::BigIntegral};
 use std::fmt::Debug;
 
+// Layout: [prism numerator denominator], both integrals, kept in lowest
+// terms with a positive denominator other than one.
 pub struct Rational_ { }
 pub fn prism_unit() -> Unit { mechanism::prism::<Rational_>() }
 pub fn is_prism(prism: AnchoredLine) -> bool { prism[0] == prism_unit() }
@@ -29,28 +31,96 @@ pub fn new(top: Handle, bot: Handle) -> Handle {
     s.unit().handle()
 }
 pub fn new_from_i64(top: i64, bot: i64) -> Handle {
-    new(integral::new(top).handle(), integral::new(bot).handle())
+    ratio(&BigIntegral::from_i64(top), &BigIntegral::from_i64(bot))
 }
-pub fn parse(negate: bool, top: &[u8], bot: &[u8]) -> Handle {
-    let mut x = 0i64;
-    for b in top.iter() {
-        if *b == b'_' {
-            continue
-        }
-        x = x * 10 + (*b - b'0') as i64;
+
+/// top / bot in lowest terms, an integral when bot divides top.
+pub fn ratio(top: &BigIntegral, bot: &BigIntegral) -> Handle {
+    if bot.is_zero() {
+        panic!("Divide by zero, {} / {}.", top, bot);
     }
-    let mut y = 0i64;
-    for b in bot.iter() {
-        if *b == b'_' {
-            continue
-        }
-        y = y * 10 + (*b - b'0') as i64;
+    let g = top.gcd(bot);
+    let (mut n, mut d) = (top.divmod(&g).0, bot.divmod(&g).0);
+    if d.negative {
+        n = n.neg();
+        d = d.neg();
+    }
+    if d == BigIntegral::from_i64(1) {
+        return integral::demoted(&n).handle()
+    }
+    new(integral::demoted(&n).handle(), integral::demoted(&d).handle())
+}
+
+pub fn parse(negate: bool, top: &[u8], bot: &[u8]) -> Result<Handle, String> {
+    let n = BigIntegral::from_radix(negate, top, 10).unwrap();
+    let d = BigIntegral::from_radix(false, bot, 10).unwrap();
+    if d.is_zero() {
+        return Err(format!("Bad rational number, the denominator is zero."))
+    }
+    Ok(ratio(&n, &d))
+}
+
+/// Numerator and denominator of a rational or an integral.
+pub fn parts(h: Handle) -> Option<(BigIntegral, BigIntegral)> {
+    if let Some(prism) = find_prism(h) {
+        let top = integral::to_big(integral::find_prism(prism[1].handle()).unwrap());
+        let bot = integral::to_big(integral::find_prism(prism[2].handle()).unwrap());
+        return Some((top, bot))
     }
-    if negate { x = -x; }
-    new_from_i64(x, y)
+    integral::find_prism(h).map(|p| (integral::to_big(p), BigIntegral::from_i64(1)))
+}
+
+pub fn as_f64(prism: AnchoredLine) -> f64 {
+    let (top, bot) = parts(prism.segment().unit().handle()).unwrap();
+    // Scales the numerator up for 64 significant bits in the quotient.
+    let bits = |b: &BigIntegral| 64 * b.mag.len() as i64 - b.mag.last().map_or(64, |x| x.leading_zeros()) as i64;
+    let k = (64 + bits(&bot) - bits(&top)).max(0);
+    let q = top.mul(&BigIntegral::from_i64(2).pow(k as u32)).divmod(&bot).0;
+    scale_by_two(q.to_f64(), -k)
+}
+
+// x * 2^e, in steps that stay within f64 range, so tiny results don't
+// underflow on the way.
+fn scale_by_two(mut x: f64, mut e: i64) -> f64 {
+    while e < -1000 && x != 0.0 {
+        x *= 2f64.powi(-1000);
+        e += 1000;
+    }
+    while e > 1000 && x.is_finite() {
+        x *= 2f64.powi(1000);
+        e -= 1000;
+    }
+    x * 2f64.powi(e as i32)
+}
+
+// Applies op to the parts of two rationals (or integrals). Consumes both operands.
+fn binary(prism: AnchoredLine, other: Unit,
+          op: fn(&BigIntegral, &BigIntegral, &BigIntegral, &BigIntegral) -> (BigIntegral, BigIntegral)) -> Unit {
+    let o = other.handle();
+    let (a, b) = parts(prism.segment().unit().handle()).unwrap();
+    let (c, d) = match parts(o) {
+        Some(x) => x,
+        None => panic!("Can't do arithmetic on Rational and {}.", o.type_name()),
+    };
+    let (n, m) = op(&a, &b, &c, &d);
+    prism.segment().unit().handle().retire();
+    o.retire();
+    ratio(&n, &m).unit()
 }
 
-impl Dispatch for Rational_ { }
+impl Dispatch for Rational_ {
+    fn tear_down(&self, prism: AnchoredLine) {
+        let seg = prism.segment();
+        assert_eq!(0, seg.anchor().aliases());
+        prism[1].handle().retire();
+        prism[2].handle().retire();
+        Segment::free(seg)
+    }
+    fn alias_components(&self, prism: AnchoredLine) {
+        prism[1].handle().split();
+        prism[2].handle().split();
+    }
+}
 impl Identification for Rational_ {
     fn type_name(&self) -> &'static str { "Rational" }
 }
@@ -70,6 +140,19 @@ impl Distinguish for Rational_ {
             false
         }
     }
+    fn cmp(&self, prism: AnchoredLine, other: Unit) -> Option<Ordering> {
+        let o = other.handle();
+        if let Some((c, d)) = parts(o) {
+            let (a, b) = parts(prism.segment().unit().handle()).unwrap();
+            return Some(a.mul(&d).cmp(&c.mul(&b)))
+        }
+        if o.is_ref() {
+            let o_prism_unit = o.logical_value()[0];
+            Some(prism_unit().cmp(&o_prism_unit))
+        } else {
+            Some(Ordering::Greater)
+        }
+    }
 }
 
 impl Aggregate for Rational_ { }
@@ -79,17 +162,70 @@ impl Reversible for Rational_ {}
 impl Sorted for Rational_ {}
 impl Notation for Rational_ {
     fn edn(&self, prism: AnchoredLine, f: &mut fmt::Formatter) -> fmt::Result {
-        // write!(f, "{}/{}", prism[1].handle(), prism[2].handle()) // equivalent?
-        prism[1].handle().fmt(f)?;
-        write!(f, "/")?;
-        prism[2].handle().fmt(f)
+        // Parts print without an N, so big ones read back.
+        let (top, bot) = parts(prism.segment().unit().handle()).unwrap();
+        write!(f, "{}/{}", top, bot)
+    }
+}
+impl Numeral for Rational_ {
+    fn inc(&self, prism: AnchoredLine) -> Unit {
+        binary(prism, integral::new(1), |a, b, _, _| (a.add(b), b.clone()))
+    }
+    fn dec(&self, prism: AnchoredLine) -> Unit {
+        binary(prism, integral::new(1), |a, b, _, _| (a.sub(b), b.clone()))
+    }
+    fn add(&self, prism: AnchoredLine, other: Unit) -> Unit {
+        binary(prism, other, |a, b, c, d| (a.mul(d).add(&c.mul(b)), b.mul(d)))
+    }
+    fn subtract(&self, prism: AnchoredLine, other: Unit) -> Unit {
+        binary(prism, other, |a, b, c, d| (a.mul(d).sub(&c.mul(b)), b.mul(d)))
+    }
+    fn multiply(&self, prism: AnchoredLine, other: Unit) -> Unit {
+        binary(prism, other, |a, b, c, d| (a.mul(c), b.mul(d)))
+    }
+    fn divide(&self, prism: AnchoredLine, other: Unit) -> Unit {
+        binary(prism, other, |a, b, c, d| (a.mul(d), b.mul(c)))
+    }
+    fn neg(&self, prism: AnchoredLine) -> Unit {
+        let (a, b) = parts(prism.segment().unit().handle()).unwrap();
+        prism.segment().unit().handle().retire();
+        ratio(&a.neg(), &b).unit()
     }
 }
-impl Numeral for Rational_ { }
 impl Callable for Rational_ { }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use value::Value;
 
+    fn r(s: &str) -> Value { s.parse().unwrap() }
+
+    #[test]
+    fn arithmetic() {
+        assert_eq!(format!("{}", r("2/4")), "1/2");
+        assert_eq!(r("6/3"), 2.into());
+        assert!("1/0".parse::<Value>().is_err());
+        assert_eq!(format!("{}", r("1/3") + r("1/6")), "1/2");
+        assert_eq!(format!("{}", r("1/3") - r("1/2")), "-1/6");
+        assert_eq!(format!("{}", r("2/3") * r("3/4")), "1/2");
+        assert_eq!(r("2/3") * r("3/2"), 1.into());
+        assert_eq!(format!("{}", r("2/3") / r("-4/9")), "-3/2");
+        assert_eq!(format!("{}", -r("-5/7")), "5/7");
+        assert_eq!(format!("{}", r("1/2") + Value::from(1)), "3/2");
+        assert!(r("1/3") < r("1/2") && r("-1/2") < r("-1/3") && r("7/2") > Value::from(3));
+        assert_eq!(format!("{}", Value::from(6) / Value::from(4)), "3/2");
+        assert_eq!(Value::from(-6) / Value::from(3), (-2).into());
+        assert_eq!(format!("{}", Value::from(3) / Value::from(-9)), "-1/3");
+        let big = r("100000000000000000000000000000/300000000000000000000000000001");
+        assert_eq!(format!("{}", &big * &big), "10000000000000000000000000000000000000000000000000000000000/90000000000000000000000000000600000000000000000000000000001");
+        let f = |s: &str| as_f64(find_prism(r(s)._handle()).unwrap());
+        assert_eq!(f("1/4"), 0.25);
+        assert_eq!(f("-2/3"), -2.0 / 3.0);
+        assert!((f("100000000000000000000000000000/300000000000000000000000000001") - 1.0 / 3.0).abs() < 1e-15);
+        let tiny = |zeros: usize| format!("1/1{}", "0".repeat(zeros));
+        assert_eq!(f(&tiny(300)), 1e-300);
+        assert!((f(&tiny(320)) - 1e-320).abs() < 1e-322);
+        assert!(r(&tiny(300)) > Value::from(1e-301) && r(&tiny(300)) < Value::from(1e-299));
+    }
 }
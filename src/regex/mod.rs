@@ -5,3 +5,243 @@
 // By using this software in any fashion, you are agreeing to be bound by the terms of this license.
 // You must not remove this notice, or any other, from this software.
 
+
+//! Regular expressions, read and printed as `#"pattern"`.
+//!
+//! A Regex value holds its source string and a compiled program, see `vm`.
+//! Matching works over the characters of a string, and matched text comes
+//! back as substrings of the searched string.
+
+use std::fmt;
+use std::cmp::Ordering;
+use memory::*;
+use dispatch::*;
+use value::*;
+use handle::Handle;
+use {string, vector};
+
+pub mod parse;
+pub mod vm;
+use self::vm::{Program, Captures};
+
+// Layout: [prism source program], program is a boxed Program
+pub struct Regex_ { }
+pub fn prism_unit() -> Unit { mechanism::prism::<Regex_>() }
+pub fn is_prism(prism: AnchoredLine) -> bool { prism[0] == prism_unit() }
+pub fn find_prism(h: Handle) -> Option<AnchoredLine> { h.find_prism(prism_unit()) }
+pub fn is_regex(h: Handle) -> bool { find_prism(h).is_some() }
+
+pub fn new(source: &str) -> Result<Handle, String> {
+    let mut parser = parse::Parser::new(source);
+    let node = parser.parse()?;
+    let program = vm::compile(&node, parser.groups)?;
+    let s = Segment::new(3 /*prism source program*/);
+    s.set(0, prism_unit());
+    s.set(1, string::new_from_str(source).unit());
+    s.set(2, Unit::from(Box::into_raw(Box::new(program)) as usize));
+    Ok(s.unit().handle())
+}
+pub fn new_value(source: &str) -> Value {
+    match new(source) {
+        Ok(h) => h.value(),
+        Err(msg) => panic!("{}", msg),
+    }
+}
+
+pub fn source(prism: AnchoredLine) -> Handle { prism[1].handle() }
+pub fn program<'a>(prism: AnchoredLine) -> &'a Program {
+    unsafe { &*(prism[2].u() as *const Program) }
+}
+
+fn prism_of(re: &Value) -> AnchoredLine {
+    match find_prism(re._handle()) {
+        Some(prism) => prism,
+        None => panic!("Not a regex: {}", re),
+    }
+}
+fn chars_of(s: &Value) -> Vec<char> {
+    match string::find_prism(s._handle()) {
+        Some(prism) => string::chunks(prism).flat_map(|c| c.chars()).collect(),
+        None => panic!("Not a string: {}", s),
+    }
+}
+
+// The matched text when there are no groups, else a vector of the match
+// and each group, nil for groups that took no part.
+fn match_value(s: &Value, caps: &Captures) -> Value {
+    let piece = |i: usize| match (caps[2 * i], caps[2 * i + 1]) {
+        (Some(a), Some(b)) => string::substring(s, a as u32, b as u32),
+        _ => Value::nil(),
+    };
+    if caps.len() == 2 {
+        return piece(0)
+    }
+    (0..(caps.len() / 2)).fold(vector::new_value(), |v, i| v.conj(piece(i)))
+}
+
+// Every non-overlapping match, left to right.
+fn all_matches(re: &Value, text: &[char]) -> Vec<Captures> {
+    let prog = program(prism_of(re));
+    let mut found = vec![];
+    let mut from = 0;
+    while from <= text.len() {
+        match vm::exec(prog, text, from, false, false) {
+            Some(caps) => {
+                let (a, b) = (caps[0].unwrap(), caps[1].unwrap());
+                from = if b > a { b } else { b + 1 };
+                found.push(caps);
+            },
+            None => break,
+        }
+    }
+    found
+}
+
+/// The first match in s, or nil.
+pub fn re_find(re: &Value, s: &Value) -> Value {
+    let text = chars_of(s);
+    match vm::exec(program(prism_of(re)), &text, 0, false, false) {
+        Some(caps) => match_value(s, &caps),
+        None => Value::nil(),
+    }
+}
+
+/// The match of the whole of s, or nil.
+pub fn re_matches(re: &Value, s: &Value) -> Value {
+    let text = chars_of(s);
+    match vm::exec(program(prism_of(re)), &text, 0, true, true) {
+        Some(caps) => match_value(s, &caps),
+        None => Value::nil(),
+    }
+}
+
+/// A vector of every match in s.
+pub fn re_seq(re: &Value, s: &Value) -> Value {
+    let text = chars_of(s);
+    all_matches(re, &text).iter().fold(vector::new_value(), |v, caps| v.conj(match_value(s, caps)))
+}
+
+/// A vector of the pieces of s between matches, empty pieces included.
+pub fn re_split(re: &Value, s: &Value) -> Value {
+    let text = chars_of(s);
+    let mut pieces = vector::new_value();
+    let mut start = 0;
+    for caps in all_matches(re, &text) {
+        let (a, b) = (caps[0].unwrap(), caps[1].unwrap());
+        if b == 0 {
+            continue
+        }
+        pieces = pieces.conj(string::substring(s, start as u32, a as u32));
+        start = b;
+    }
+    pieces.conj(string::substring(s, start as u32, text.len() as u32))
+}
+
+impl Dispatch for Regex_ {
+    fn tear_down(&self, prism: AnchoredLine) {
+        let seg = prism.segment();
+        assert_eq!(0, seg.anchor().aliases());
+        prism[1].handle().retire();
+        let prog = unsafe { Box::from_raw(prism[2].u() as *mut Program) };
+        drop(prog);
+        Segment::free(seg)
+    }
+    fn alias_components(&self, prism: AnchoredLine) { prism[1].handle().split(); }
+}
+impl Identification for Regex_ {
+    fn type_name(&self) -> &'static str { "Regex" }
+}
+impl Distinguish for Regex_ {
+    fn hash(&self, prism: AnchoredLine) -> u32 {
+        use random::{PI, cycle_abc};
+        let y = cycle_abc(34, PI[91].wrapping_add(source(prism).hash() as u64));
+        cycle_abc(210, y) as u32
+    }
+    fn eq(&self, prism: AnchoredLine, other: Unit) -> bool {
+        match find_prism(other.handle()) {
+            Some(o_prism) => source(prism).eq(source(o_prism)),
+            None => false,
+        }
+    }
+    fn cmp(&self, prism: AnchoredLine, other: Unit) -> Option<Ordering> {
+        let o = other.handle();
+        if let Some(o_prism) = find_prism(o) {
+            return source(prism).cmp(source(o_prism))
+        }
+        if o.is_ref() {
+            let o_prism_unit = o.logical_value()[0];
+            Some(prism_unit().cmp(&o_prism_unit))
+        } else {
+            Some(Ordering::Greater)
+        }
+    }
+}
+impl Aggregate for Regex_ { }
+impl Sequential for Regex_ { }
+impl Associative for Regex_ { }
+impl Reversible for Regex_ { }
+impl Sorted for Regex_ { }
+impl Notation for Regex_ {
+    fn edn(&self, prism: AnchoredLine, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#\"")?;
+        for c in string::chunks(string::find_prism(source(prism)).unwrap()) {
+            write!(f, "{}", c)?;
+        }
+        write!(f, "\"")
+    }
+}
+impl Numeral for Regex_ { }
+impl Callable for Regex_ { }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn s(x: &str) -> Value { string::new_value_from_str(x) }
+
+    #[test]
+    fn finding() {
+        let re = new_value(r"(\d+)-(\d+)?");
+        assert_eq!(format!("{}", re_find(&re, &s("ab 12-34 5-"))), r#"["12-34" "12" "34"]"#);
+        assert_eq!(format!("{}", re_seq(&re, &s("1-2 3- 45-6"))),
+                   r#"[["1-2" "1" "2"] ["3-" "3" nil] ["45-6" "45" "6"]]"#);
+        assert_eq!(re_find(&new_value("x"), &s("abc")), Value::nil());
+        assert_eq!(re_find(&new_value(r"\bw\w*"), &s("a wide sword")), s("wide"));
+        assert_eq!(re_find(&new_value("a+?"), &s("aaa")), s("a"));
+        assert_eq!(re_find(&new_value("(a|ab)(c|bcd)"), &s("abcd")).nth_out(1), s("a"));
+    }
+
+    #[test]
+    fn matching_and_splitting() {
+        let re = new_value("[a-c]{2,3}|[^a-z]+");
+        assert_eq!(re_matches(&re, &s("abc")), s("abc"));
+        assert_eq!(re_matches(&re, &s("abcd")), Value::nil());
+        assert_eq!(re_matches(&re, &s("A1!")), s("A1!"));
+        assert_eq!(re_matches(&new_value("^(?:ab)*$"), &s("ababab")), s("ababab"));
+        assert_eq!(format!("{}", re_split(&new_value(r"\s*,\s*"), &s("a , b,c"))), r#"["a" "b" "c"]"#);
+        assert!(new("(ab").is_err() && new("*a").is_err() && new("[z-a]").is_err());
+    }
+
+    #[test]
+    fn limits_and_anchors() {
+        assert!(new("a{1000}").is_ok() && new("a{2,1000}").is_ok());
+        assert!(new("a{1001}").is_err() && new("a{1,1001}").is_err() && new("a{99999999999}").is_err());
+        assert!(new("(?:(?:a{1000}){1000}){1000}").unwrap_err().contains("too big"));
+        // A chain of 40000 optional atoms, followed without recursion.
+        assert_eq!(re_matches(&new_value("(?:(?:a?){1000}){40}"), &s("aaa")), s("aaa"));
+        assert_eq!(re_find(&new_value("a$"), &s("ba\n")), s("a"));
+        assert_eq!(re_find(&new_value(r"a\Z"), &s("ba\n")), s("a"));
+        assert_eq!(re_find(&new_value(r"a\z"), &s("ba\n")), Value::nil());
+        assert_eq!(re_matches(&new_value("a$"), &s("a\n")), Value::nil());
+        assert_eq!(re_find(&new_value(r"\bé"), &s("cé")), s("é"));
+        assert_eq!(re_find(&new_value(r"\w+\b"), &s("caé")), s("ca"));
+    }
+
+    #[test]
+    fn literals() {
+        let re: Value = r#"#"\d+\"x""#.parse().unwrap();
+        assert_eq!(format!("{}", re), r#"#"\d+\"x""#);
+        assert_eq!(re_find(&re, &s("a 42\"x")), s("42\"x"));
+        assert_eq!(re, new_value(r#"\d+\"x"#));
+    }
+}
@@ -0,0 +1,327 @@
+// Copyright (c) Cole Frederick. All rights reserved.
+// The use and distribution terms for this software are covered by the
+// Eclipse Public License 1.0 (https://opensource.org/licenses/eclipse-1.0.php)
+// which can be found in the file epl-v10.html at the root of this distribution.
+// By using this software in any fashion, you are agreeing to be bound by the terms of this license.
+// You must not remove this notice, or any other, from this software.
+
+//! Parses a pattern into a syntax tree.
+//!
+//! Supports literals, `.`, classes like `[a-z_]` and `[^\d]`, the escapes
+//! `\d \w \s \D \W \S`, anchors `^ $ \b \B \A \Z \z`, capturing `(...)`
+//! and non-capturing `(?:...)` groups, alternation, and the greedy and lazy
+//! quantifiers `* + ? {n} {n,} {n,m}`. As in RE2, counts are at most 1000.
+//! Words, for `\w` and `\b` alike, are ASCII letters, digits and underscore.
+
+use std::char;
+
+/// The largest count in a repetition like `{n,m}`.
+pub const MAX_REPEAT: u32 = 1000;
+
+/// Sorted, merged ranges of code points.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Class {
+    pub ranges: Vec<(u32, u32)>,
+}
+
+impl Class {
+    pub fn new(mut ranges: Vec<(u32, u32)>) -> Class {
+        ranges.sort();
+        let mut merged: Vec<(u32, u32)> = vec![];
+        for (lo, hi) in ranges {
+            match merged.last_mut() {
+                Some(last) if lo <= last.1.saturating_add(1) => { last.1 = last.1.max(hi); },
+                _ => merged.push((lo, hi)),
+            }
+        }
+        Class { ranges: merged }
+    }
+    pub fn negated(&self) -> Class {
+        let mut ranges = vec![];
+        let mut next = 0u32;
+        for &(lo, hi) in self.ranges.iter() {
+            if lo > next {
+                ranges.push((next, lo - 1));
+            }
+            next = hi + 1;
+        }
+        if next <= char::MAX as u32 {
+            ranges.push((next, char::MAX as u32));
+        }
+        Class { ranges }
+    }
+    pub fn contains(&self, c: char) -> bool {
+        let c = c as u32;
+        self.ranges.iter().any(|&(lo, hi)| lo <= c && c <= hi)
+    }
+    pub fn digit() -> Class { Class::new(vec![(0x30, 0x39)]) }
+    pub fn word() -> Class { Class::new(vec![(0x30, 0x39), (0x41, 0x5A), (0x5F, 0x5F), (0x61, 0x7A)]) }
+    pub fn space() -> Class { Class::new(vec![(0x09, 0x0D), (0x20, 0x20)]) }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Node {
+    Empty,
+    Char(char),
+    Any,
+    Class(Class),
+    Start,
+    /// Strict ends match only at the end of input, others also before a final newline.
+    End(bool),
+    WordBoundary(bool),
+    Group(Box<Node>, Option<usize>),
+    Concat(Vec<Node>),
+    Alternate(Vec<Node>),
+    Repeat { node: Box<Node>, min: u32, max: Option<u32>, greedy: bool },
+}
+
+pub struct Parser<'a> {
+    chars: Vec<char>,
+    pos: usize,
+    pub groups: usize,
+    source: &'a str,
+}
+
+impl<'a> Parser<'a> {
+    pub fn new(source: &'a str) -> Parser<'a> {
+        Parser { chars: source.chars().collect(), pos: 0, groups: 0, source }
+    }
+    fn err<T>(&self, msg: &str) -> Result<T, String> {
+        Err(format!("Bad regex #\"{}\" at character {}: {}", self.source, self.pos, msg))
+    }
+    fn peek(&self) -> Option<char> { self.chars.get(self.pos).cloned() }
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+    fn next(&mut self) -> Result<char, String> {
+        match self.peek() {
+            Some(c) => {
+                self.pos += 1;
+                Ok(c)
+            },
+            None => self.err("unexpected end of pattern"),
+        }
+    }
+
+    pub fn parse(&mut self) -> Result<Node, String> {
+        let node = self.alternation()?;
+        if self.pos < self.chars.len() {
+            return self.err("unmatched )")
+        }
+        Ok(node)
+    }
+
+    fn alternation(&mut self) -> Result<Node, String> {
+        let mut alts = vec![self.concatenation()?];
+        while self.eat('|') {
+            alts.push(self.concatenation()?);
+        }
+        Ok(if alts.len() == 1 { alts.pop().unwrap() } else { Node::Alternate(alts) })
+    }
+
+    fn concatenation(&mut self) -> Result<Node, String> {
+        let mut items = vec![];
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break
+            }
+            let atom = self.atom()?;
+            items.push(self.quantified(atom)?);
+        }
+        Ok(match items.len() {
+            0 => Node::Empty,
+            1 => items.pop().unwrap(),
+            _ => Node::Concat(items),
+        })
+    }
+
+    fn number(&mut self) -> Option<u32> {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if !c.is_ascii_digit() { break }
+            self.pos += 1;
+        }
+        if start == self.pos {
+            return None
+        }
+        // Only digits, so a failed parse overflowed.
+        Some(self.chars[start..self.pos].iter().collect::<String>().parse().unwrap_or(u32::MAX))
+    }
+
+    fn quantified(&mut self, atom: Node) -> Result<Node, String> {
+        let mut node = atom;
+        loop {
+            let (min, max) = match self.peek() {
+                Some('*') => { self.pos += 1; (0, None) },
+                Some('+') => { self.pos += 1; (1, None) },
+                Some('?') => { self.pos += 1; (0, Some(1)) },
+                Some('{') => {
+                    let mark = self.pos;
+                    self.pos += 1;
+                    match self.counted()? {
+                        Some(range) => range,
+                        None => {
+                            self.pos = mark;
+                            return Ok(node)
+                        },
+                    }
+                },
+                _ => return Ok(node),
+            };
+            match node {
+                Node::Start | Node::End(_) | Node::WordBoundary(_) | Node::Empty => {
+                    return self.err("nothing to repeat")
+                },
+                _ => { },
+            }
+            let greedy = !self.eat('?');
+            node = Node::Repeat { node: Box::new(node), min, max, greedy };
+        }
+    }
+
+    // After {, reads n} or n,} or n,m}. None if this isn't a count, so { is a literal.
+    fn counted(&mut self) -> Result<Option<(u32, Option<u32>)>, String> {
+        let min = match self.number() {
+            Some(n) => n,
+            None => return Ok(None),
+        };
+        let max = if self.eat(',') { self.number() } else { Some(min) };
+        if !self.eat('}') {
+            return Ok(None)
+        }
+        if let Some(m) = max {
+            if m < min {
+                return self.err("bad repetition range")
+            }
+        }
+        if max.unwrap_or(min) > MAX_REPEAT {
+            return self.err("repetition count too large")
+        }
+        Ok(Some((min, max)))
+    }
+
+    fn atom(&mut self) -> Result<Node, String> {
+        let c = self.next()?;
+        match c {
+            '.' => Ok(Node::Any),
+            '^' => Ok(Node::Start),
+            '$' => Ok(Node::End(false)),
+            '[' => Ok(Node::Class(self.class()?)),
+            '(' => {
+                let index = if self.eat('?') {
+                    if !self.eat(':') {
+                        return self.err("unsupported group construct")
+                    }
+                    None
+                } else {
+                    self.groups += 1;
+                    Some(self.groups)
+                };
+                let inner = self.alternation()?;
+                if !self.eat(')') {
+                    return self.err("unclosed group")
+                }
+                Ok(Node::Group(Box::new(inner), index))
+            },
+            '*' | '+' | '?' => self.err("nothing to repeat"),
+            '\\' => self.escape(),
+            _ => Ok(Node::Char(c)),
+        }
+    }
+
+    fn escape(&mut self) -> Result<Node, String> {
+        let c = self.next()?;
+        Ok(match c {
+            'd' => Node::Class(Class::digit()),
+            'D' => Node::Class(Class::digit().negated()),
+            'w' => Node::Class(Class::word()),
+            'W' => Node::Class(Class::word().negated()),
+            's' => Node::Class(Class::space()),
+            'S' => Node::Class(Class::space().negated()),
+            'b' => Node::WordBoundary(true),
+            'B' => Node::WordBoundary(false),
+            'A' => Node::Start,
+            'Z' => Node::End(false),
+            'z' => Node::End(true),
+            _ => Node::Char(self.escaped_char(c)?),
+        })
+    }
+
+    fn escaped_char(&mut self, c: char) -> Result<char, String> {
+        Ok(match c {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            'f' => '\x0C',
+            '0' => '\0',
+            'u' => {
+                let mut code = 0;
+                for _ in 0..4 {
+                    let d = self.next()?;
+                    match d.to_digit(16) {
+                        Some(x) => code = code * 16 + x,
+                        None => return self.err("a unicode escape should have four hex digits"),
+                    }
+                }
+                match char::from_u32(code) {
+                    Some(c) => c,
+                    None => return self.err("unicode escape is not a character"),
+                }
+            },
+            _ if c.is_ascii_alphanumeric() => return self.err("unknown escape"),
+            _ => c,
+        })
+    }
+
+    // After [, reads the members of a class through the closing ].
+    fn class(&mut self) -> Result<Class, String> {
+        let negated = self.eat('^');
+        let mut ranges = vec![];
+        let mut first = true;
+        loop {
+            let c = self.next()?;
+            if c == ']' && !first {
+                break
+            }
+            first = false;
+            let lo = if c == '\\' {
+                let e = self.next()?;
+                let builtin = match e {
+                    'd' => Some(Class::digit()),
+                    'D' => Some(Class::digit().negated()),
+                    'w' => Some(Class::word()),
+                    'W' => Some(Class::word().negated()),
+                    's' => Some(Class::space()),
+                    'S' => Some(Class::space().negated()),
+                    _ => None,
+                };
+                if let Some(b) = builtin {
+                    ranges.extend(b.ranges);
+                    continue
+                }
+                self.escaped_char(e)?
+            } else {
+                c
+            };
+            let is_range = self.peek() == Some('-') && self.chars.get(self.pos + 1).map_or(false, |&n| n != ']');
+            if is_range {
+                self.pos += 1;
+                let h = self.next()?;
+                let hi = if h == '\\' { let e = self.next()?; self.escaped_char(e)? } else { h };
+                if hi < lo {
+                    return self.err("bad class range")
+                }
+                ranges.push((lo as u32, hi as u32));
+            } else {
+                ranges.push((lo as u32, lo as u32));
+            }
+        }
+        let class = Class::new(ranges);
+        Ok(if negated { class.negated() } else { class })
+    }
+}
@@ -0,0 +1,239 @@
+// Copyright (c) Cole Frederick. All rights reserved.
+// The use and distribution terms for this software are covered by the
+// Eclipse Public License 1.0 (https://opensource.org/licenses/eclipse-1.0.php)
+// which can be found in the file epl-v10.html at the root of this distribution.
+// By using this software in any fashion, you are agreeing to be bound by the terms of this license.
+// You must not remove this notice, or any other, from this software.
+
+//! Compiles a syntax tree to a Thompson NFA program, and runs it as a Pike VM.
+//!
+//! The VM steps every live thread over each character in turn, so a search
+//! takes time proportional to the text times the program, never backtracking.
+//! Threads are kept in priority order, which gives the leftmost-first match
+//! (and captures) a backtracking engine would find.
+
+use std::mem;
+use super::parse::{Node, Class};
+
+/// The most instructions in a program, as nested counts multiply.
+pub const MAX_INSTS: usize = 100_000;
+
+#[derive(Clone, Debug)]
+pub enum Inst {
+    Char(char),
+    Any,
+    Class(Class),
+    Start,
+    End(bool),
+    WordBoundary(bool),
+    Split(usize, usize),
+    Jmp(usize),
+    Save(usize),
+    Match,
+}
+
+#[derive(Debug)]
+pub struct Program {
+    pub insts: Vec<Inst>,
+    pub slots: usize,
+}
+
+struct Compiler {
+    insts: Vec<Inst>,
+}
+
+impl Compiler {
+    fn push(&mut self, inst: Inst) -> usize {
+        self.insts.push(inst);
+        self.insts.len() - 1
+    }
+    fn split(&mut self, at: usize, body: usize, out: usize, greedy: bool) {
+        self.insts[at] = if greedy { Inst::Split(body, out) } else { Inst::Split(out, body) };
+    }
+    fn emit(&mut self, node: &Node) -> Result<(), String> {
+        if self.insts.len() > MAX_INSTS {
+            return Err(format!("Regex is too big, over {} instructions.", MAX_INSTS))
+        }
+        match *node {
+            Node::Empty => { },
+            Node::Char(c) => { self.push(Inst::Char(c)); },
+            Node::Any => { self.push(Inst::Any); },
+            Node::Class(ref k) => { self.push(Inst::Class(k.clone())); },
+            Node::Start => { self.push(Inst::Start); },
+            Node::End(strict) => { self.push(Inst::End(strict)); },
+            Node::WordBoundary(b) => { self.push(Inst::WordBoundary(b)); },
+            Node::Group(ref inner, index) => {
+                match index {
+                    Some(i) => {
+                        self.push(Inst::Save(2 * i));
+                        self.emit(inner)?;
+                        self.push(Inst::Save(2 * i + 1));
+                    },
+                    None => self.emit(inner)?,
+                }
+            },
+            Node::Concat(ref items) => {
+                for item in items.iter() {
+                    self.emit(item)?;
+                }
+            },
+            Node::Alternate(ref alts) => {
+                let mut jumps = vec![];
+                for (k, alt) in alts.iter().enumerate() {
+                    if k + 1 == alts.len() {
+                        self.emit(alt)?;
+                    } else {
+                        let split = self.push(Inst::Split(0, 0));
+                        self.emit(alt)?;
+                        jumps.push(self.push(Inst::Jmp(0)));
+                        let next = self.insts.len();
+                        self.insts[split] = Inst::Split(split + 1, next);
+                    }
+                }
+                let end = self.insts.len();
+                for j in jumps {
+                    self.insts[j] = Inst::Jmp(end);
+                }
+            },
+            Node::Repeat { ref node, min, max, greedy } => {
+                for _ in 0..min {
+                    self.emit(node)?;
+                }
+                match max {
+                    None => {
+                        let split = self.push(Inst::Split(0, 0));
+                        self.emit(node)?;
+                        self.push(Inst::Jmp(split));
+                        let out = self.insts.len();
+                        self.split(split, split + 1, out, greedy);
+                    },
+                    Some(max) => {
+                        let mut splits = vec![];
+                        for _ in min..max {
+                            splits.push(self.push(Inst::Split(0, 0)));
+                            self.emit(node)?;
+                        }
+                        let out = self.insts.len();
+                        for s in splits {
+                            self.split(s, s + 1, out, greedy);
+                        }
+                    },
+                }
+            },
+        }
+        Ok(())
+    }
+}
+
+pub fn compile(node: &Node, groups: usize) -> Result<Program, String> {
+    let mut c = Compiler { insts: vec![] };
+    c.push(Inst::Save(0));
+    c.emit(node)?;
+    c.push(Inst::Save(1));
+    c.push(Inst::Match);
+    Ok(Program { insts: c.insts, slots: 2 * (groups + 1) })
+}
+
+pub type Captures = Vec<Option<usize>>;
+
+struct Threads {
+    list: Vec<(usize, Captures)>,
+    seen: Vec<bool>,
+}
+
+impl Threads {
+    fn new(n: usize) -> Threads { Threads { list: vec![], seen: vec![false; n] } }
+    fn clear(&mut self) {
+        self.list.clear();
+        for s in self.seen.iter_mut() {
+            *s = false;
+        }
+    }
+}
+
+// As \w.
+fn is_word(c: char) -> bool { c.is_ascii_alphanumeric() || c == '_' }
+
+fn is_end(strict: bool, pos: usize, text: &[char]) -> bool {
+    pos == text.len() || (!strict && pos + 1 == text.len() && text[pos] == '\n')
+}
+
+// Follows jumps, splits, saves and assertions from pc, adding the
+// threads that wait on a character, in priority order. Depth first, with
+// an explicit stack, as programs can be long.
+fn add(prog: &Program, threads: &mut Threads, pc: usize, pos: usize, text: &[char], caps: Captures) {
+    let mut stack = vec![(pc, caps)];
+    while let Some((pc, mut caps)) = stack.pop() {
+        if threads.seen[pc] {
+            continue
+        }
+        threads.seen[pc] = true;
+        match prog.insts[pc] {
+            Inst::Jmp(to) => stack.push((to, caps)),
+            Inst::Split(a, b) => {
+                // b after all of a.
+                stack.push((b, caps.clone()));
+                stack.push((a, caps));
+            },
+            Inst::Save(slot) => {
+                caps[slot] = Some(pos);
+                stack.push((pc + 1, caps));
+            },
+            Inst::Start => if pos == 0 { stack.push((pc + 1, caps)) },
+            Inst::End(strict) => if is_end(strict, pos, text) { stack.push((pc + 1, caps)) },
+            Inst::WordBoundary(want) => {
+                let before = pos > 0 && is_word(text[pos - 1]);
+                let after = pos < text.len() && is_word(text[pos]);
+                if (before != after) == want {
+                    stack.push((pc + 1, caps));
+                }
+            },
+            _ => threads.list.push((pc, caps)),
+        }
+    }
+}
+
+/// Finds the leftmost-first match at or after from. Anchored searches only
+/// match starting at from, and full searches only match through the end.
+pub fn exec(prog: &Program, text: &[char], from: usize, anchored: bool, full: bool) -> Option<Captures> {
+    let n = prog.insts.len();
+    let mut current = Threads::new(n);
+    let mut next = Threads::new(n);
+    let mut matched = None;
+    let mut pos = from;
+    loop {
+        if matched.is_none() && (!anchored || pos == from) {
+            add(prog, &mut current, 0, pos, text, vec![None; prog.slots]);
+        }
+        if current.list.is_empty() {
+            break
+        }
+        let c = text.get(pos).cloned();
+        for (pc, caps) in current.list.drain(..) {
+            let step = match prog.insts[pc] {
+                Inst::Char(x) => c == Some(x),
+                Inst::Any => c.map_or(false, |c| c != '\n'),
+                Inst::Class(ref k) => c.map_or(false, |c| k.contains(c)),
+                Inst::Match => {
+                    if !full || pos == text.len() {
+                        // Lower priority threads are cut.
+                        matched = Some(caps);
+                        break
+                    }
+                    false
+                },
+                _ => false,
+            };
+            if step {
+                add(prog, &mut next, pc + 1, pos + 1, text, caps);
+            }
+        }
+        current.clear();
+        mem::swap(&mut current, &mut next);
+        if pos == text.len() {
+            break
+        }
+        pos += 1;
+    }
+    matched
+}
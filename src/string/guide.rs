@@ -8,15 +8,17 @@
 use memory::*;
 
 /// The Guide structure is hydrated from its in-memory representation, 64 bits in length.
-/// The top 32 bits contain the hash, the bottom 32 bits contain the byte count.
+/// The top 32 bits contain the hash, the bottom 32 bits contain the byte count,
+/// and a flag marking rope strings, whose counts are kept in the rope.
 
 /// `Top 32 bits  [ Hash  (32) ]`
-/// `Bottom bits  [ Count (16) ]`
+/// `Bottom bits  [ Rope (1) | Count (16) ]`
 ///
 
 #[derive(Copy, Clone, Debug)]
 pub struct Guide {
     pub hash: u32,
+    pub is_rope: bool,
     pub count: u32,
 
     pub prism: AnchoredLine,
@@ -51,23 +53,24 @@ impl Guide {
     }
     pub fn hydrate_top_bot(prism: AnchoredLine, top: u32, bot: u32) -> Guide {
         let hash = top;
+        let is_rope = (bot >> 31) == 1;
         let count = bot & 0xFFFF;
 
         let root_offset = 1 /*prism*/ + Guide::units();
         let root = prism.offset(root_offset as i32);
 
-        Guide { hash, count, prism, root }
+        Guide { hash, is_rope, count, prism, root }
     }
 
     pub fn new(prism: AnchoredLine) -> Guide {
         let root_offset = 1 /*prism*/ + Guide::units();
         let root = prism.offset(root_offset as i32);
-        Guide { hash: 0, count: 0, prism, root }
+        Guide { hash: 0, is_rope: false, count: 0, prism, root }
     }
 
     pub fn store_at(&self, mut prism: AnchoredLine) {
         let top: u32 = self.hash;
-        let bot: u32 = self.count;
+        let bot: u32 = ((self.is_rope as u32) << 31) | self.count;
         if cfg!(target_pointer_width = "32") {
             prism[1] = top.into();
             prism[2] = bot.into();
@@ -85,7 +88,7 @@ impl Guide {
     pub fn store_hash(self) -> Guide {
         let prism = self.prism;
         let top: u32 = self.hash;
-        let bot: u32 = self.count;
+        let bot: u32 = ((self.is_rope as u32) << 31) | self.count;
         if cfg!(target_pointer_width = "32") {
             prism.store_hash(1, top.into());
             prism.store_hash(2, bot.into());
@@ -104,6 +107,9 @@ impl Guide {
 
     pub fn byte_slice(&self, size: u32) -> &mut [u8] {
         use std::slice::from_raw_parts_mut;
+        if size == 0 {
+            return &mut []
+        }
         let p = self.root.line().star() as *mut u8;
         unsafe {
             from_raw_parts_mut(p, size as usize)
@@ -116,9 +122,16 @@ impl Guide {
 
     pub fn str(&self) -> &mut str {
         use std::str::from_utf8_mut;
+        assert!(!self.is_rope, "A rope has no flat str, go through its chunks.");
         from_utf8_mut(self.byte_slice(self.count)).unwrap()
     }
 
+    pub fn set_rope(mut self) -> Guide {
+        self.is_rope = true;
+        self.count = 0;
+        self.clear_hash()
+    }
+
     pub fn set_count(mut self, count: u32) -> Guide {
         self.count = count & 0xFFFF;
         self.clear_hash()
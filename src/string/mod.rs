@@ -12,13 +12,21 @@ use dispatch::*;
 use value::Value;
 use handle::Handle;
 use std::cmp::Ordering;
+use transduce::{ingest, last_call, Process};
+use character;
 
 pub mod guide;
 use self::guide::Guide;
+pub mod rope;
+use self::rope::Chunks;
+pub mod ops;
 
 // Str abstraction:
 // byte buffer (utf8 characters), fast append (tail like vector)
-// rope like tree, maybe rrb tree. buffer tree nodes labeled with character count, byte count.
+// rope like tree, see rope. buffer tree nodes labeled with character count, byte count.
+
+/// Strings up to this many bytes are flat, a single byte buffer. Longer strings are ropes.
+pub const FLAT_MAX: u32 = rope::LEAF_BYTES;
 
 pub struct String_ { }
 pub fn prism_unit() -> Unit { mechanism::prism::<String_>() }
@@ -39,6 +47,11 @@ pub fn blank(units: u32) -> Guide {
 }
 pub fn new_from_str(source: &str) -> Handle {
     let bytes = source.len() as u32;
+    if bytes > FLAT_MAX {
+        let guide = rope::new_rope();
+        rope::append(guide.root, source);
+        return guide.segment().unit().handle()
+    }
     let guide = blank(units_for(bytes)).set_count(bytes);
     guide.byte_slice(bytes).copy_from_slice(source.as_bytes());
     guide.store().segment().unit().handle()
@@ -99,6 +112,13 @@ pub fn new_escaping(source: &[u8]) -> Result<Handle, String> {
         fill += 1;
         i += 1;
     }
+    if fill as u32 > FLAT_MAX {
+        let h = new_from_str(from_utf8(&buf[..fill]).unwrap());
+        let seg = guide.segment();
+        seg.unalias();
+        Segment::free(seg);
+        return Ok(h)
+    }
     Ok(guide.set_count(fill as u32).store().segment().unit().handle())
 }
 pub fn new_value_from_str(source: &str) -> Value { new_from_str(source).value() }
@@ -108,16 +128,253 @@ pub fn units_for(byte_count: u32) -> u32 {
     (byte_count + b - 1) >> c
 }
 
+/// The bytes of a flat string. Ropes must go through chunks.
 pub fn byte_slice(prism: &AnchoredLine) -> &[u8] {
     use std::slice::from_raw_parts;
     let guide = Guide::hydrate(*prism);
+    assert!(!guide.is_rope, "A rope has no flat bytes, go through its chunks.");
     let b = guide.byte_slice(guide.count);
     unsafe {
         from_raw_parts(b.as_ptr(), b.len())
     }
 }
 
-impl Dispatch for String_ { /*default tear_down, alias_components*/ }
+pub fn byte_count(prism: AnchoredLine) -> u32 {
+    let guide = Guide::hydrate(prism);
+    if guide.is_rope { rope::byte_count(guide.root) } else { guide.count }
+}
+pub fn char_count(prism: AnchoredLine) -> u32 {
+    let guide = Guide::hydrate(prism);
+    if guide.is_rope { rope::char_count(guide.root) } else { guide.str().chars().count() as u32 }
+}
+pub fn chunks<'a>(prism: AnchoredLine) -> Chunks<'a> {
+    let guide = Guide::hydrate(prism);
+    if guide.is_rope {
+        Chunks::new(guide.root)
+    } else {
+        let s = guide.str();
+        Chunks::flat(unsafe { &*(s as *const str) })
+    }
+}
+
+pub fn unaliased(prism: AnchoredLine) -> AnchoredLine {
+    let seg = prism.segment();
+    if seg.is_aliased() {
+        if prism.index() == 0 {
+            alias_components(prism);
+        } else {
+            seg.unit().handle()._alias_components();
+        }
+        let s = seg.carbon_copy();
+        let p = prism.with_seg(s);
+        seg.unit().handle().retire();
+        p
+    } else {
+        prism
+    }
+}
+pub fn alias_components(prism: AnchoredLine) {
+    let guide = Guide::hydrate(prism);
+    if guide.is_rope {
+        rope::alias_components(guide.root);
+    }
+}
+
+fn prism_of(s: &Value) -> AnchoredLine {
+    match find_prism(s._handle()) {
+        Some(prism) => prism,
+        None => panic!("Not a string: {}", s),
+    }
+}
+
+// A flat string of the given chunks.
+fn flatten(chunks: Chunks, bytes: u32) -> Handle {
+    let guide = blank(units_for(bytes)).set_count(bytes);
+    let buf = guide.byte_slice(bytes);
+    let mut fill = 0;
+    for c in chunks {
+        buf[fill..(fill + c.len())].copy_from_slice(c.as_bytes());
+        fill += c.len();
+    }
+    guide.store().segment().unit().handle()
+}
+
+/// Appends text to a string. Appending is amortized constant time per byte.
+pub fn append(s: Value, text: &str) -> Value {
+    let prism = prism_of(&s);
+    let guide = Guide::hydrate(prism);
+    if guide.is_rope {
+        let g = Guide::hydrate(unaliased(prism));
+        s._consume();
+        rope::append(g.root, text);
+        return g.clear_hash().store().segment().unit().handle().value()
+    }
+    let needed = guide.count + text.len() as u32;
+    if needed > FLAT_MAX {
+        let g = rope::new_rope();
+        rope::append(g.root, guide.str());
+        rope::append(g.root, text);
+        return g.segment().unit().handle().value()
+    }
+    if prism.index() == 0 && !prism.segment().is_aliased() && guide.capacity_in_bytes() >= needed {
+        s._consume();
+        guide.byte_slice(needed)[(guide.count as usize)..].copy_from_slice(text.as_bytes());
+        return guide.set_count(needed).store().segment().unit().handle().value()
+    }
+    let cap = needed.max(guide.count * 2).min(FLAT_MAX);
+    let g = blank(units_for(cap)).set_count(needed);
+    let buf = g.byte_slice(needed);
+    buf[..(guide.count as usize)].copy_from_slice(guide.str().as_bytes());
+    buf[(guide.count as usize)..].copy_from_slice(text.as_bytes());
+    g.store().segment().unit().handle().value()
+}
+
+/// Appends one string to another. The leaves of a rope are shared, not copied.
+pub fn concat(s: Value, other: &Value) -> Value {
+    let o = Guide::hydrate(prism_of(other));
+    if !o.is_rope {
+        return append(s, o.str())
+    }
+    let prism = prism_of(&s);
+    let g = if Guide::hydrate(prism).is_rope {
+        let g = Guide::hydrate(unaliased(prism));
+        s._consume();
+        g
+    } else {
+        let g = rope::new_rope();
+        rope::append(g.root, Guide::hydrate(prism).str());
+        g
+    };
+    rope::append_rope(g.root, o.root);
+    g.clear_hash().store().segment().unit().handle().value()
+}
+
+/// The characters from start up to end, sharing the rope of a long string.
+pub fn substring(s: &Value, start: u32, end: u32) -> Value {
+    let guide = Guide::hydrate(prism_of(s));
+    let count = char_count(guide.prism);
+    if start > end || end > count {
+        panic!("Substring {}..{} out of bounds for string of {} characters.", start, end, count);
+    }
+    if !guide.is_rope {
+        return new_value_from_str(rope::char_range(guide.str(), start, end))
+    }
+    let g = rope::substring(guide.root, start, end);
+    let bytes = rope::byte_count(g.root);
+    if bytes > FLAT_MAX {
+        return g.segment().unit().handle().value()
+    }
+    let flat = flatten(Chunks::new(g.root), bytes);
+    g.segment().unit().handle().retire();
+    flat.value()
+}
+
+/// The character at an index, found in logarithmic time for a rope.
+pub fn nth_char(prism: AnchoredLine, idx: u32) -> char {
+    let guide = Guide::hydrate(prism);
+    let c = if guide.is_rope {
+        let (leaf, i) = rope::locate(guide.root, idx);
+        rope::leaf_str(leaf).chars().nth(i as usize)
+    } else {
+        guide.str().chars().nth(idx as usize)
+    };
+    match c {
+        Some(c) => c,
+        None => panic!("Index {} out of bounds for string of {} characters.", idx, char_count(prism)),
+    }
+}
+pub fn char_at(s: &Value, idx: u32) -> char { nth_char(prism_of(s), idx) }
+
+/// Reduces over the characters.
+pub fn reduce(prism: AnchoredLine, process_stack: &mut [Box<dyn Process>]) -> Value {
+    for c in chunks(prism).flat_map(|c| c.chars()) {
+        if let Some(ret) = ingest(process_stack, Value::from(c)) {
+            return ret;
+        }
+    }
+    last_call(process_stack)
+}
+
+/// Conjoins a character, or a whole string, onto the end.
+pub fn conj(prism: AnchoredLine, x: Unit) -> Unit {
+    let s = prism.segment().unit().handle().value();
+    let x = x.handle().value();
+    let res = if let Some(c) = character::find_prism(x._handle()) {
+        append(s, character::as_char(c).encode_utf8(&mut [0u8; 4]))
+    } else if is_string(x._handle()) {
+        concat(s, &x)
+    } else {
+        panic!("Can't conj a {} onto a string.", x.type_name())
+    };
+    res._consume().unit()
+}
+
+// Hashes like mix_range over the units of a flat string, zero padded,
+// but reads the bytes in chunks.
+fn hash_chunks(chunks: Chunks) -> u32 {
+    use random::PI;
+    use hash::{mix, end};
+    let mut a: (u64, u64, u64, u64) = (PI[22], PI[23], PI[24], PI[25]);
+    let mut block = [0u8; 32];
+    let mut fill = 0;
+    fn xor(a: &mut (u64, u64, u64, u64), block: &[u8; 32]) {
+        let word = |i: usize| {
+            let mut w = [0u8; 8];
+            w.copy_from_slice(&block[(i * 8)..(i * 8 + 8)]);
+            u64::from_ne_bytes(w)
+        };
+        a.0 ^= word(0);
+        a.1 ^= word(1);
+        a.2 ^= word(2);
+        a.3 ^= word(3);
+    }
+    for c in chunks {
+        for &b in c.as_bytes() {
+            if fill == 32 {
+                xor(&mut a, &block);
+                a = mix(a.0, a.1, a.2, a.3);
+                block = [0u8; 32];
+                fill = 0;
+            }
+            block[fill] = b;
+            fill += 1;
+        }
+    }
+    xor(&mut a, &block);
+    a = mix(a.0, a.1, a.2, a.3);
+    let (x, _y) = end(a.0, a.1, a.2, a.3);
+    x as u32
+}
+
+fn cmp_chunks(a: Chunks, b: Chunks) -> Ordering {
+    let mut xs = a.flat_map(|c| c.bytes());
+    let mut ys = b.flat_map(|c| c.bytes());
+    loop {
+        match (xs.next(), ys.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) => {
+                if x != y {
+                    return x.cmp(&y)
+                }
+            },
+        }
+    }
+}
+
+impl Dispatch for String_ {
+    fn tear_down(&self, prism: AnchoredLine) {
+        let seg = prism.segment();
+        assert_eq!(0, seg.anchor().aliases());
+        let guide = Guide::hydrate(prism);
+        if guide.is_rope {
+            rope::tear_down(guide.root);
+        }
+        Segment::free(seg);
+    }
+    fn alias_components(&self, prism: AnchoredLine) { alias_components(prism); }
+}
 impl Identification for String_ {
     fn type_name(&self) -> &'static str { "String" }
 }
@@ -127,7 +384,7 @@ impl Distinguish for String_ {
         let guide = Guide::hydrate(prism);
         if guide.has_hash() { return guide.hash; }
 
-        let h = {
+        let h = if guide.is_rope { hash_chunks(Chunks::new(guide.root)) } else {
             use random::PI;
             use hash::{mix_range, end};
             let iv: (u64, u64, u64, u64) = (PI[22], PI[23], PI[24], PI[25]);
@@ -144,7 +401,11 @@ impl Distinguish for String_ {
             //log!("String eq: {} {}", prism.segment().unit().handle(), o);
             let g = Guide::hydrate(prism);
             let h = Guide::hydrate(o_str);
-            return g.str() == h.str()
+            if !g.is_rope && !h.is_rope {
+                return g.str() == h.str()
+            }
+            byte_count(prism) == byte_count(o_str) &&
+                cmp_chunks(chunks(prism), chunks(o_str)) == Ordering::Equal
         } else {
             false
         }
@@ -153,9 +414,7 @@ impl Distinguish for String_ {
         let o = other.handle();
         if let Some(o_str) = find_prism(o) {
             log!("String cmp: {} {}", prism.segment().unit().handle(), o);
-            let g = Guide::hydrate(prism);
-            let h = Guide::hydrate(o_str);
-            Some(g.str().cmp(&h.str()))
+            Some(cmp_chunks(chunks(prism), chunks(o_str)))
         } else {
             if o.is_ref() {
                 let o_prism_unit = o.logical_value()[0];
@@ -166,8 +425,23 @@ impl Distinguish for String_ {
         }
     }
 }
-impl Aggregate for String_ { }
-impl Sequential for String_ { }
+impl Aggregate for String_ {
+    fn is_aggregate(&self, prism: AnchoredLine) -> bool { true }
+    fn count(&self, prism: AnchoredLine) -> u32 { char_count(prism) }
+    fn empty(&self, prism: AnchoredLine) -> Unit { new_from_str("").unit() }
+    fn conj(&self, prism: AnchoredLine, x: Unit) -> Unit { conj(prism, x) }
+    fn reduce(&self, prism: AnchoredLine, process: &mut [Box<dyn Process>]) -> Value {
+        reduce(prism, process)
+    }
+}
+impl Sequential for String_ {
+    fn is_sequential(&self, prism: AnchoredLine) -> bool { true }
+    fn nth(&self, prism: AnchoredLine, idx: u32) -> *const Unit {
+        // Characters are computed, there's none to lend.
+        panic!("String elements can't be borrowed, use nth_out.")
+    }
+    fn nth_out(&self, prism: AnchoredLine, idx: u32) -> Unit { character::new(nth_char(prism, idx)).unit() }
+}
 impl Associative for String_ { }
 impl Reversible for String_ { }
 impl Sorted for String_ { }
@@ -176,7 +450,15 @@ impl Notation for String_ {
         let guide = Guide::hydrate(prism);
         // TODO clojure doesn't support escaped single quotes like \'
         // https://doc.rust-lang.org/src/core/fmt/mod.rs.html#1956-1974
-        write!(f, "{:?}", guide.str())
+        if !guide.is_rope {
+            return write!(f, "{:?}", guide.str())
+        }
+        write!(f, "\"")?;
+        for c in Chunks::new(guide.root) {
+            let escaped = format!("{:?}", c);
+            write!(f, "{}", &escaped[1..(escaped.len() - 1)])?;
+        }
+        write!(f, "\"")
     }
 }
 impl Numeral for String_ {}
@@ -186,4 +468,85 @@ impl Callable for String_ {}
 mod tests {
     use super::*;
 
+    fn text(n: usize) -> String {
+        (0..n).map(|i| if i % 7 == 0 { 'λ' } else { (b'a' + (i % 26) as u8) as char }).collect()
+    }
+    fn as_string(s: &Value) -> String {
+        chunks(prism_of(s)).collect()
+    }
+
+    #[test]
+    fn append_past_flat() {
+        let t = text(100_000);
+        let mut s = new_value_from_str("");
+        for (i, c) in t.char_indices() {
+            s = append(s, &t[i..(i + c.len_utf8())]);
+        }
+        let prism = prism_of(&s);
+        assert!(Guide::hydrate(prism).is_rope);
+        assert_eq!(char_count(prism), 100_000);
+        assert_eq!(byte_count(prism), t.len() as u32);
+        assert_eq!(as_string(&s), t);
+        let whole = new_value_from_str(&t);
+        assert_eq!(s, whole);
+        assert_eq!(s.hash(), whole.hash());
+        assert_eq!(char_at(&s, 70_000), 'λ');
+        assert_eq!(char_at(&s, 99_999), t.chars().last().unwrap());
+    }
+
+    #[test]
+    #[should_panic(expected = "A rope has no flat bytes")]
+    fn rope_bytes() {
+        let s = new_value_from_str(&text(50_000));
+        byte_slice(&prism_of(&s));
+    }
+
+    #[test]
+    fn substring_shares() {
+        let t = text(50_000);
+        let s = new_value_from_str(&t);
+        let sub = substring(&s, 1234, 45_678);
+        let expected: String = t.chars().skip(1234).take(45_678 - 1234).collect();
+        assert_eq!(as_string(&sub), expected);
+        assert_eq!(sub, new_value_from_str(&expected));
+        let small = substring(&sub, 10, 20);
+        assert!(!Guide::hydrate(prism_of(&small)).is_rope);
+        assert_eq!(as_string(&small), expected.chars().skip(10).take(10).collect::<String>());
+        let grown = append(sub.split_out(), "tail");
+        assert_eq!(as_string(&grown), expected.clone() + "tail");
+        assert_eq!(as_string(&sub), expected);
+    }
+
+    #[test]
+    fn concat_and_order() {
+        let (a, b) = (text(3000), text(5000));
+        let ab = concat(new_value_from_str(&a), &new_value_from_str(&b));
+        assert_eq!(as_string(&ab), a.clone() + &b);
+        assert_eq!(ab, new_value_from_str(&(a.clone() + &b)));
+        let c = new_value_from_str(&(a.clone() + "λc"));
+        assert!(ab < c);
+        assert_eq!(format!("{}", ab), format!("{:?}", a + &b));
+    }
+
+    #[test]
+    fn characters() {
+        let s = new_value_from_str("héllo");
+        assert_eq!(s.count(), 5);
+        assert_eq!(s.nth_out(1), Value::from('é'));
+        assert_eq!((::nth_out(&s, 1), s.nth_out(4)), (Value::from('é'), Value::from('o')));
+        let s = s.conj('!'.into()).conj(new_value_from_str(" ok"));
+        assert_eq!(s, new_value_from_str("héllo! ok"));
+        let vowels = ::into(::vector(), ::filter(|c| "aeioué".contains(Into::<char>::into(c.split_out()))), s);
+        assert_eq!(format!("{}", vowels), "[\\é \\o \\o]");
+        let long = new_value_from_str(&"abc".repeat(1000));
+        assert_eq!(long.nth_out(2999), Value::from('c'));
+        assert_eq!(long.nth_out(2998), Value::from('b'));
+        assert_eq!(::into(::vector(), ::transduce::Transducers::new(), long).count(), 3000);
+    }
+
+    #[test]
+    #[should_panic(expected = "String elements can't be borrowed, use nth_out.")]
+    fn lends_nothing() {
+        new_value_from_str("abc").nth(1);
+    }
 }
@@ -0,0 +1,249 @@
+// Copyright (c) Cole Frederick. All rights reserved.
+// The use and distribution terms for this software are covered by the
+// Eclipse Public License 1.0 (https://opensource.org/licenses/eclipse-1.0.php)
+// which can be found in the file epl-v10.html at the root of this distribution.
+// By using this software in any fashion, you are agreeing to be bound by the terms of this license.
+// You must not remove this notice, or any other, from this software.
+
+//! String library.
+//!
+//! Indexes count characters (unicode scalar values), not bytes. Pieces of a
+//! string, as from split, trim or replace, are substrings, so they share the
+//! rope of a long string.
+
+use value::Value;
+use vector;
+use super::*;
+
+/// The text of a value, a string as is, nil as nothing, anything else in its printed form.
+pub fn str(s: Value, t: Value) -> Value {
+    let s = if is_string(s._handle()) { s } else if s.is_nil() { new_value_from_str("") } else {
+        new_value_from_str(&format!("{}", s))
+    };
+    if is_string(t._handle()) {
+        concat(s, &t)
+    } else if t.is_nil() {
+        s
+    } else {
+        append(s, &format!("{}", t))
+    }
+}
+
+// Char indexes of non-overlapping matches of pat, searching from a char
+// index, stopping after max matches. Chunks are searched through a window
+// that carries the end of each chunk over, to find matches across chunks.
+fn search(prism: AnchoredLine, pat: &str, from: u32, max: usize) -> Vec<u32> {
+    let mut found = vec![];
+    if pat.is_empty() {
+        let count = char_count(prism);
+        for i in from..(count + 1) {
+            if found.len() == max { break }
+            found.push(i);
+        }
+        return found
+    }
+    let mut window = String::new();
+    let mut window_start = 0u32;
+    let mut next = 0usize;
+    for c in chunks(prism) {
+        window.push_str(c);
+        if window_start < from {
+            let skip = (from - window_start) as usize;
+            let chars = window.chars().count();
+            if skip > chars {
+                window_start += chars as u32;
+                window.clear();
+                continue
+            }
+            next = window.char_indices().map(|(i, _)| i).chain(Some(window.len())).nth(skip).unwrap();
+        }
+        while let Some(b) = window[next..].find(pat) {
+            let at = next + b;
+            found.push(window_start + window[..at].chars().count() as u32);
+            if found.len() == max {
+                return found
+            }
+            next = at + pat.len();
+        }
+        let mut cut = window.len().saturating_sub(pat.len() - 1);
+        while !window.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        let cut = cut.max(next);
+        window_start += window[..cut].chars().count() as u32;
+        window.drain(..cut);
+        next = next.saturating_sub(cut);
+    }
+    found
+}
+
+fn text_of(v: &Value) -> String {
+    match find_prism(v._handle()) {
+        Some(prism) => chunks(prism).collect(),
+        None => panic!("Not a string: {}", v),
+    }
+}
+
+/// Char index of the first occurrence of t in s.
+pub fn index_of(s: &Value, t: &Value) -> Option<u32> { index_of_from(s, t, 0) }
+/// Char index of the first occurrence of t in s, at or after char index from.
+pub fn index_of_from(s: &Value, t: &Value, from: u32) -> Option<u32> {
+    search(prism_of(s), &text_of(t), from, 1).pop()
+}
+
+pub fn starts_with(s: &Value, t: &Value) -> bool {
+    let (p, q) = (prism_of(s), prism_of(t));
+    if byte_count(q) > byte_count(p) {
+        return false
+    }
+    let mut xs = chunks(p).flat_map(|c| c.bytes());
+    chunks(q).flat_map(|c| c.bytes()).all(|y| xs.next() == Some(y))
+}
+
+pub fn ends_with(s: &Value, t: &Value) -> bool {
+    let (p, q) = (prism_of(s), prism_of(t));
+    let (m, n) = (byte_count(p), byte_count(q));
+    if n > m {
+        return false
+    }
+    let mut xs = chunks(p).flat_map(|c| c.bytes()).skip((m - n) as usize);
+    chunks(q).flat_map(|c| c.bytes()).all(|y| xs.next() == Some(y))
+}
+
+/// A vector of the pieces of s between occurrences of sep, empty pieces included.
+/// An empty separator splits s into its characters.
+pub fn split(s: &Value, sep: &Value) -> Value {
+    let prism = prism_of(s);
+    let pat = text_of(sep);
+    let count = char_count(prism);
+    let mut pieces = vector::new_value();
+    if pat.is_empty() {
+        for i in 0..count {
+            pieces = pieces.conj(substring(s, i, i + 1));
+        }
+        return pieces
+    }
+    let sep_chars = pat.chars().count() as u32;
+    let mut start = 0;
+    for at in search(prism, &pat, 0, usize::MAX) {
+        pieces = pieces.conj(substring(s, start, at));
+        start = at + sep_chars;
+    }
+    pieces.conj(substring(s, start, count))
+}
+
+/// The items of a collection as one string, with sep between them.
+pub fn join(sep: &Value, coll: &Value) -> Value {
+    let mut s = new_value_from_str("");
+    for (i, x) in coll.split_out().into_iter().enumerate() {
+        if i != 0 {
+            s = concat(s, sep);
+        }
+        s = str(s, x);
+    }
+    s
+}
+
+/// Replaces every occurrence of pat in s with rep.
+pub fn replace(s: &Value, pat: &Value, rep: &Value) -> Value {
+    let prism = prism_of(s);
+    let p = text_of(pat);
+    if p.is_empty() {
+        panic!("Replace with an empty pattern.");
+    }
+    let pat_chars = p.chars().count() as u32;
+    let mut r = new_value_from_str("");
+    let mut start = 0;
+    for at in search(prism, &p, 0, usize::MAX) {
+        r = concat(r, &substring(s, start, at));
+        r = concat(r, rep);
+        start = at + pat_chars;
+    }
+    if start == 0 {
+        return s.split_out()
+    }
+    concat(r, &substring(s, start, char_count(prism)))
+}
+
+pub fn trim_start(s: &Value) -> Value {
+    let prism = prism_of(s);
+    let lead = chunks(prism).flat_map(|c| c.chars()).take_while(|c| c.is_whitespace()).count() as u32;
+    substring(s, lead, char_count(prism))
+}
+
+pub fn trim_end(s: &Value) -> Value {
+    let prism = prism_of(s);
+    let mut end = 0;
+    for (i, c) in chunks(prism).flat_map(|c| c.chars()).enumerate() {
+        if !c.is_whitespace() {
+            end = i as u32 + 1;
+        }
+    }
+    substring(s, 0, end)
+}
+
+pub fn trim(s: &Value) -> Value { trim_start(&trim_end(s)) }
+
+fn map_chunks(s: &Value, f: impl Fn(&str) -> String) -> Value {
+    chunks(prism_of(s)).fold(new_value_from_str(""), |r, c| append(r, &f(c)))
+}
+pub fn upper_case(s: &Value) -> Value { map_chunks(s, |c| c.to_uppercase()) }
+pub fn lower_case(s: &Value) -> Value { map_chunks(s, |c| c.to_lowercase()) }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn s(x: &str) -> Value { new_value_from_str(x) }
+
+    #[test]
+    fn pieces() {
+        let csv = s("a,bb,,λ,");
+        assert_eq!(format!("{}", split(&csv, &s(","))), r#"["a" "bb" "" "λ" ""]"#);
+        assert_eq!(format!("{}", split(&s("aλc"), &s(""))), r#"["a" "λ" "c"]"#);
+        let words = split(&s("one two three"), &s(" "));
+        assert_eq!(join(&s(", "), &words), s("one, two, three"));
+        assert_eq!(replace(&s("a-b-c"), &s("-"), &s("--")), s("a--b--c"));
+        assert_eq!(trim(&s(" \t hi there \n")), s("hi there"));
+        assert_eq!(str(s("n="), 5.into()), s("n=5"));
+        assert_eq!(str(s("n="), Value::nil()), s("n="));
+        assert_eq!(str(Value::nil(), s("x")), s("x"));
+    }
+
+    #[test]
+    fn queries() {
+        let t = s("héllo wörld");
+        assert_eq!(t.count(), 11);
+        assert_eq!(index_of(&t, &s("wö")), Some(6));
+        assert_eq!(index_of(&t, &s("x")), None);
+        assert_eq!(index_of_from(&t, &s("l"), 3), Some(3));
+        assert_eq!(index_of_from(&t, &s("l"), 4), Some(9));
+        assert_eq!(index_of_from(&t, &s("l"), 10), None);
+        assert_eq!(index_of_from(&t, &s("l"), 50), None);
+        assert_eq!(index_of_from(&t, &s(""), 11), Some(11));
+        assert!(starts_with(&t, &s("hé")) && !starts_with(&t, &s("wö")));
+        assert!(ends_with(&t, &s("rld")) && !ends_with(&t, &s("hé")));
+        assert_eq!(upper_case(&t), s("HÉLLO WÖRLD"));
+        assert_eq!(lower_case(&s("ÀB")), s("àb"));
+    }
+
+    #[test]
+    fn long_text() {
+        let line = "the quick brown fox; ";
+        let long: String = line.repeat(2000);
+        let t = s(&long);
+        let n = line.chars().count() as u32;
+        assert_eq!(index_of(&t, &s("fox; the")), Some(16));
+        assert_eq!(index_of_from(&t, &s("fox; the"), 17), Some(16 + n));
+        assert_eq!(index_of_from(&t, &s("fox; the"), 1500 * n + 17), Some(1501 * n + 16));
+        assert_eq!(index_of_from(&t, &s("the"), 1999 * n + 1), None);
+        let parts = split(&t, &s("; "));
+        assert_eq!(parts.count(), 2001);
+        assert_eq!(*parts.nth(1999), s("the quick brown fox"));
+        let r = replace(&t, &s("fox"), &s("cat"));
+        assert_eq!(r, s(&long.replace("fox", "cat")));
+        assert!(ends_with(&t, &s("fox; ")) && starts_with(&t, &s("the quick")));
+        assert_eq!(trim(&t).count(), 2000 * n - 1);
+        assert_eq!(join(&s("; "), &parts), t);
+    }
+}
@@ -0,0 +1,388 @@
+// Copyright (c) Cole Frederick. All rights reserved.
+// The use and distribution terms for this software are covered by the
+// Eclipse Public License 1.0 (https://opensource.org/licenses/eclipse-1.0.php)
+// which can be found in the file epl-v10.html at the root of this distribution.
+// By using this software in any fashion, you are agreeing to be bound by the terms of this license.
+// You must not remove this notice, or any other, from this software.
+
+//! Rope strings, for text longer than a flat string holds.
+//!
+//! A rope is a tree of leaves, each a byte buffer of whole utf8 characters.
+//! Every node records the byte and character count of each child, so finding
+//! a character by index walks one path from the root. All leaves sit at the
+//! same depth. Appends fill a tail leaf, which joins the tree when full,
+//! like the tail of a vector. Substrings share every subtree they cover whole.
+
+use std::str::from_utf8_unchecked;
+use std::slice;
+use memory::*;
+use super::guide::Guide;
+use super::{prism_unit, units_for};
+
+pub const BITS: u32 = 4;
+pub const ARITY: u32 = 1 << BITS;
+/// Byte capacity of a tail leaf.
+pub const LEAF_BYTES: u32 = 1 << 10;
+
+// Layout after the guide: [height tree_bytes tree_chars tree tail]
+// A height of zero means no tree, otherwise the tree is a node of that height.
+pub const ROPE_UNITS: u32 = 5;
+// Leaf layout: [bytes chars utf8...]
+// Node layout: [count child bytes chars child bytes chars ...]
+
+pub fn new_rope() -> Guide {
+    let s = Segment::new(1 /*prism*/ + Guide::units() + ROPE_UNITS);
+    let prism = s.line_at(0);
+    prism.set(0, prism_unit());
+    let guide = Guide::new(prism).set_rope().store();
+    let root = guide.root;
+    root.set(0, Unit::zero());
+    root.set(1, Unit::zero());
+    root.set(2, Unit::zero());
+    root.set(3, Unit::zero());
+    root.set(4, leaf_new(LEAF_BYTES).unit());
+    guide
+}
+
+pub fn height(root: AnchoredLine) -> u32 { root[0].u32() }
+pub fn tree_bytes(root: AnchoredLine) -> u32 { root[1].u32() }
+pub fn tree_chars(root: AnchoredLine) -> u32 { root[2].u32() }
+pub fn tree(root: AnchoredLine) -> Segment { root[3].segment() }
+pub fn tail(root: AnchoredLine) -> Segment { root[4].segment() }
+pub fn byte_count(root: AnchoredLine) -> u32 { tree_bytes(root) + leaf_bytes(tail(root)) }
+pub fn char_count(root: AnchoredLine) -> u32 { tree_chars(root) + leaf_chars(tail(root)) }
+
+pub fn leaf_new(byte_cap: u32) -> Segment {
+    let s = Segment::new(2 + units_for(byte_cap));
+    s.set(0, Unit::zero());
+    s.set(1, Unit::zero());
+    s
+}
+pub fn leaf_bytes(leaf: Segment) -> u32 { leaf[0].u32() }
+pub fn leaf_chars(leaf: Segment) -> u32 { leaf[1].u32() }
+pub fn leaf_room(leaf: Segment) -> u32 { (leaf.capacity() - 2) * Unit::bytes() - leaf_bytes(leaf) }
+pub fn leaf_str<'a>(leaf: Segment) -> &'a str {
+    let p = leaf.line_at(2).line().star() as *const u8;
+    unsafe { from_utf8_unchecked(slice::from_raw_parts(p, leaf_bytes(leaf) as usize)) }
+}
+/// Copies as much of s as fits into the leaf, in whole characters, returning the rest.
+pub fn leaf_fill<'a>(leaf: Segment, s: &'a str) -> &'a str {
+    let mut end = (leaf_room(leaf) as usize).min(s.len());
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    let (head, rest) = s.split_at(end);
+    let b = leaf_bytes(leaf);
+    unsafe {
+        let p = (leaf.line_at(2).line().star() as *mut u8).offset(b as isize);
+        slice::from_raw_parts_mut(p, end).copy_from_slice(head.as_bytes());
+    }
+    leaf.set(0, Unit::from(b + end as u32));
+    leaf.set(1, Unit::from(leaf_chars(leaf) + head.chars().count() as u32));
+    rest
+}
+pub fn leaf_from(s: &str) -> Segment {
+    let leaf = leaf_new(s.len() as u32);
+    leaf_fill(leaf, s);
+    leaf
+}
+
+pub fn node_new() -> Segment {
+    let s = Segment::new(1 + 3 * ARITY);
+    s.set(0, Unit::zero());
+    s
+}
+pub fn node_count(node: Segment) -> u32 { node[0].u32() }
+pub fn node_child(node: Segment, i: u32) -> Segment { node[1 + 3 * i].segment() }
+pub fn node_bytes(node: Segment, i: u32) -> u32 { node[2 + 3 * i].u32() }
+pub fn node_chars(node: Segment, i: u32) -> u32 { node[3 + 3 * i].u32() }
+pub fn node_set(node: Segment, i: u32, child: Segment, bytes: u32, chars: u32) {
+    node.set(1 + 3 * i, child.unit());
+    node.set(2 + 3 * i, Unit::from(bytes));
+    node.set(3 + 3 * i, Unit::from(chars));
+}
+pub fn node_add(node: Segment, child: Segment, bytes: u32, chars: u32) {
+    let c = node_count(node);
+    node_set(node, c, child, bytes, chars);
+    node.set(0, Unit::from(c + 1));
+}
+
+/// Drops one alias to a node (or leaf, at height zero), freeing it with the last alias.
+pub fn release(seg: Segment, height: u32) {
+    if seg.unalias() == 0 {
+        if height != 0 {
+            for i in 0..node_count(seg) {
+                release(node_child(seg, i), height - 1);
+            }
+        }
+        Segment::free(seg)
+    }
+}
+
+pub fn unaliased(seg: Segment, height: u32) -> Segment {
+    if !seg.is_aliased() {
+        return seg
+    }
+    let s = seg.carbon_copy();
+    if height != 0 {
+        for i in 0..node_count(s) {
+            node_child(s, i).alias();
+        }
+    }
+    release(seg, height);
+    s
+}
+
+pub fn alias_components(root: AnchoredLine) {
+    if height(root) != 0 {
+        tree(root).alias();
+    }
+    tail(root).alias();
+}
+
+pub fn tear_down(root: AnchoredLine) {
+    if height(root) != 0 {
+        release(tree(root), height(root));
+    }
+    release(tail(root), 0);
+}
+
+// Pushes a leaf onto the right edge of a node, handing back a new
+// sibling node when this one is full.
+fn push(node: Segment, height: u32, leaf: Segment, bytes: u32, chars: u32) -> (Segment, Option<Segment>) {
+    let node = unaliased(node, height);
+    let c = node_count(node);
+    let overflow = if height == 1 { Some(leaf) } else {
+        let last = c - 1;
+        let (child, sibling) = push(node_child(node, last), height - 1, leaf, bytes, chars);
+        if sibling.is_some() {
+            node_set(node, last, child, node_bytes(node, last), node_chars(node, last));
+        } else {
+            node_set(node, last, child, node_bytes(node, last) + bytes, node_chars(node, last) + chars);
+        }
+        sibling
+    };
+    match overflow {
+        None => (node, None),
+        Some(child) => {
+            if c < ARITY {
+                node_add(node, child, bytes, chars);
+                (node, None)
+            } else {
+                let sibling = node_new();
+                node_add(sibling, child, bytes, chars);
+                (node, Some(sibling))
+            }
+        },
+    }
+}
+
+/// Adds a leaf to the end of the tree. The rope must be unaliased.
+pub fn push_leaf(root: AnchoredLine, leaf: Segment) {
+    let (bytes, chars) = (leaf_bytes(leaf), leaf_chars(leaf));
+    let (h, tb, tc) = (height(root), tree_bytes(root), tree_chars(root));
+    if h == 0 {
+        let n = node_new();
+        node_add(n, leaf, bytes, chars);
+        root.set(0, Unit::from(1u32));
+        root.set(3, n.unit());
+    } else {
+        let (t, sibling) = push(tree(root), h, leaf, bytes, chars);
+        match sibling {
+            None => root.set(3, t.unit()),
+            Some(s) => {
+                let n = node_new();
+                node_add(n, t, tb, tc);
+                node_add(n, s, bytes, chars);
+                root.set(0, Unit::from(h + 1));
+                root.set(3, n.unit());
+            },
+        }
+    }
+    root.set(1, Unit::from(tb + bytes));
+    root.set(2, Unit::from(tc + chars));
+}
+
+/// Appends text through the tail. The rope must be unaliased.
+pub fn append(root: AnchoredLine, text: &str) {
+    let mut text = text;
+    loop {
+        let t = unaliased(tail(root), 0);
+        root.set(4, t.unit());
+        text = leaf_fill(t, text);
+        if text.is_empty() {
+            return
+        }
+        push_leaf(root, t);
+        root.set(4, leaf_new(LEAF_BYTES).unit());
+    }
+}
+
+/// Appends another rope, sharing its leaves. The rope must be unaliased.
+pub fn append_rope(root: AnchoredLine, other: AnchoredLine) {
+    let t = tail(root);
+    if leaf_bytes(t) != 0 {
+        push_leaf(root, t);
+        root.set(4, leaf_new(LEAF_BYTES).unit());
+    }
+    for leaf in Leaves::new(other) {
+        leaf.alias();
+        push_leaf(root, leaf);
+    }
+    append(root, leaf_str(tail(other)));
+}
+
+pub fn char_range(s: &str, from: u32, to: u32) -> &str {
+    let mut it = s.char_indices().map(|(i, _)| i).chain(Some(s.len()));
+    let start = it.nth(from as usize).unwrap();
+    let end = if to == from { start } else { it.nth((to - from - 1) as usize).unwrap() };
+    &s[start..end]
+}
+
+// The characters from..to of a node (or leaf, at height zero), as a new
+// node of the same height. Children covered whole are shared.
+fn slice(seg: Segment, height: u32, from: u32, to: u32) -> (Segment, u32, u32) {
+    if height == 0 {
+        if from == 0 && to == leaf_chars(seg) {
+            seg.alias();
+            return (seg, leaf_bytes(seg), leaf_chars(seg))
+        }
+        let leaf = leaf_from(char_range(leaf_str(seg), from, to));
+        return (leaf, leaf_bytes(leaf), leaf_chars(leaf))
+    }
+    let n = node_new();
+    let (mut bytes, mut chars) = (0, 0);
+    let mut at = 0;
+    for i in 0..node_count(seg) {
+        let (lo, hi) = (at, at + node_chars(seg, i));
+        at = hi;
+        if hi <= from || lo >= to {
+            continue
+        }
+        let child = node_child(seg, i);
+        let (c, b, ch) = if from <= lo && hi <= to {
+            child.alias();
+            (child, node_bytes(seg, i), node_chars(seg, i))
+        } else {
+            slice(child, height - 1, from.max(lo) - lo, to.min(hi) - lo)
+        };
+        node_add(n, c, b, ch);
+        bytes += b;
+        chars += ch;
+    }
+    (n, bytes, chars)
+}
+
+/// The characters from..to, as a new rope sharing the tree.
+pub fn substring(root: AnchoredLine, from: u32, to: u32) -> Guide {
+    let guide = new_rope();
+    let r = guide.root;
+    let tc = tree_chars(root);
+    if from < tc.min(to) {
+        let mut h = height(root);
+        let (mut t, b, c) = slice(tree(root), h, from, to.min(tc));
+        while h > 1 && node_count(t) == 1 {
+            let only = node_child(t, 0);
+            only.alias();
+            release(t, h);
+            t = only;
+            h -= 1;
+        }
+        r.set(0, Unit::from(h));
+        r.set(1, Unit::from(b));
+        r.set(2, Unit::from(c));
+        r.set(3, t.unit());
+    }
+    if to > tc {
+        leaf_fill(tail(r), char_range(leaf_str(tail(root)), from.max(tc) - tc, to - tc));
+    }
+    guide
+}
+
+/// The leaf holding a character, and the character's index in that leaf.
+pub fn locate(root: AnchoredLine, idx: u32) -> (Segment, u32) {
+    let tc = tree_chars(root);
+    if idx >= tc {
+        return (tail(root), idx - tc)
+    }
+    let mut idx = idx;
+    let mut node = tree(root);
+    let mut h = height(root);
+    loop {
+        let mut i = 0;
+        while idx >= node_chars(node, i) {
+            idx -= node_chars(node, i);
+            i += 1;
+        }
+        node = node_child(node, i);
+        if h == 1 {
+            return (node, idx)
+        }
+        h -= 1;
+    }
+}
+
+/// The leaves of the tree, in order, not counting the tail.
+pub struct Leaves {
+    pub stack: Vec<(Segment, u32)>,
+    pub height: u32,
+}
+impl Leaves {
+    pub fn new(root: AnchoredLine) -> Leaves {
+        let h = height(root);
+        let stack = if h == 0 { vec![] } else { vec![(tree(root), 0)] };
+        Leaves { stack, height: h }
+    }
+}
+impl Iterator for Leaves {
+    type Item = Segment;
+    fn next(&mut self) -> Option<Segment> {
+        loop {
+            let depth = self.stack.len() as u32;
+            let child = match self.stack.last_mut() {
+                None => return None,
+                Some(top) => {
+                    if top.1 == node_count(top.0) {
+                        None
+                    } else {
+                        top.1 += 1;
+                        Some(node_child(top.0, top.1 - 1))
+                    }
+                },
+            };
+            match child {
+                None => { self.stack.pop(); },
+                Some(c) => {
+                    if depth == self.height {
+                        return Some(c)
+                    }
+                    self.stack.push((c, 0));
+                },
+            }
+        }
+    }
+}
+
+/// The text of a string in pieces: the leaves of a rope then its tail,
+/// or the whole of a flat string.
+pub struct Chunks<'a> {
+    pub leaves: Leaves,
+    pub tail: Option<&'a str>,
+}
+impl<'a> Chunks<'a> {
+    pub fn new(root: AnchoredLine) -> Chunks<'a> {
+        Chunks { leaves: Leaves::new(root), tail: Some(leaf_str(tail(root))) }
+    }
+    pub fn flat(s: &'a str) -> Chunks<'a> {
+        Chunks { leaves: Leaves { stack: vec![], height: 0 }, tail: Some(s) }
+    }
+}
+impl<'a> Iterator for Chunks<'a> {
+    type Item = &'a str;
+    fn next(&mut self) -> Option<&'a str> {
+        match self.leaves.next() {
+            Some(leaf) => Some(leaf_str(leaf)),
+            None => self.tail.take(),
+        }
+    }
+}
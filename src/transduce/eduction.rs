@@ -0,0 +1,134 @@
+// Copyright (c) Cole Frederick. All rights reserved.
+// The use and distribution terms for this software are covered by the
+// Eclipse Public License 1.0 (https://opensource.org/licenses/eclipse-1.0.php)
+// which can be found in the file epl-v10.html at the root of this distribution.
+// By using this software in any fashion, you are agreeing to be bound by the terms of this license.
+// You must not remove this notice, or any other, from this software.
+
+//! A collection seen through transducers.
+//!
+//! An eduction holds a source collection and a transducer stack. Each reduce
+//! runs the source through fresh processes, so nothing is cached.
+
+use std::fmt;
+use memory::*;
+use dispatch::*;
+use value::*;
+use handle::Handle;
+use transduce::{ingest, ingest_kv, last_call, Process, Transducers};
+use list;
+use vector;
+
+// Layout: [prism coll xf], xf is a boxed Transducers
+pub struct Eduction_ { }
+pub fn prism_unit() -> Unit { mechanism::prism::<Eduction_>() }
+pub fn is_prism(prism: AnchoredLine) -> bool { prism[0] == prism_unit() }
+pub fn find_prism(h: Handle) -> Option<AnchoredLine> { h.find_prism(prism_unit()) }
+pub fn is_eduction(h: Handle) -> bool { find_prism(h).is_some() }
+
+pub fn new(xf: Transducers, coll: Handle) -> Unit {
+    let s = Segment::new(3 /*prism coll xf*/);
+    s.set(0, prism_unit());
+    s.set(1, coll.unit());
+    s.set(2, Unit::from(Box::into_raw(Box::new(xf)) as usize));
+    s.unit()
+}
+
+pub fn transducers<'a>(prism: AnchoredLine) -> &'a Transducers {
+    unsafe { &*(prism[2].u() as *const Transducers) }
+}
+
+pub fn reduce(prism: AnchoredLine, process_stack: &mut [Box<dyn Process>]) -> Value {
+    struct Outer { rest: *mut [Box<dyn Process>] }
+    impl Process for Outer {
+        fn ingest   (&mut self, _stack: &mut [Box<dyn Process>], v:  Value)            -> Option<Value> {
+            ingest(unsafe { &mut *self.rest }, v)
+        }
+        fn ingest_kv(&mut self, _stack: &mut [Box<dyn Process>], k:  Value, v:  Value) -> Option<Value> {
+            ingest_kv(unsafe { &mut *self.rest }, k, v)
+        }
+        fn last_call(&mut self, _stack: &mut [Box<dyn Process>]) -> Value {
+            last_call(unsafe { &mut *self.rest })
+        }
+    }
+    let outer: Box<dyn Process> = Box::new(Outer { rest: process_stack as *mut [Box<dyn Process>] });
+    let mut stack = transducers(prism).apply(vec![outer]);
+    prism[1].handle().reduce(&mut stack)
+}
+
+/// The elements of the eduction, in a vector.
+pub fn materialize(prism: AnchoredLine) -> Handle {
+    prism.segment().unit().handle().pour(Transducers::new(), vector::new().handle())
+}
+
+impl Dispatch for Eduction_ {
+    fn tear_down(&self, prism: AnchoredLine) {
+        let seg = prism.segment();
+        assert_eq!(0, seg.anchor().aliases());
+        prism[1].handle().retire();
+        let xf = unsafe { Box::from_raw(prism[2].u() as *mut Transducers) };
+        drop(xf);
+        Segment::free(seg)
+    }
+    fn alias_components(&self, prism: AnchoredLine) { prism[1].handle().split(); }
+}
+impl Identification for Eduction_ {
+    fn type_name(&self) -> &'static str { "Eduction" }
+}
+impl Distinguish for Eduction_ {
+    fn hash(&self, prism: AnchoredLine) -> u32 {
+        let v = materialize(prism);
+        let h = v.hash();
+        v.retire();
+        h
+    }
+    fn eq(&self, prism: AnchoredLine, other: Unit) -> bool {
+        let o = other.handle();
+        let w = if let Some(o_prism) = find_prism(o) {
+            materialize(o_prism)
+        } else if list::is_list(o) || vector::is_vector(o) {
+            o.split()
+        } else {
+            return false
+        };
+        let v = materialize(prism);
+        let res = v.eq(w);
+        v.retire();
+        w.retire();
+        res
+    }
+}
+impl Aggregate for Eduction_ {
+    fn is_aggregate(&self, prism: AnchoredLine) -> bool { true }
+    fn count(&self, prism: AnchoredLine) -> u32 {
+        let v = materialize(prism);
+        let ct = v.count();
+        v.retire();
+        ct
+    }
+    fn empty(&self, prism: AnchoredLine) -> Unit { list::new() }
+    fn reduce(&self, prism: AnchoredLine, process: &mut [Box<dyn Process>]) -> Value {
+        reduce(prism, process)
+    }
+}
+impl Sequential for Eduction_ {
+    fn is_sequential(&self, prism: AnchoredLine) -> bool { true }
+}
+impl Associative for Eduction_ { }
+impl Reversible for Eduction_ { }
+impl Sorted for Eduction_ { }
+impl Notation for Eduction_ {
+    fn edn(&self, prism: AnchoredLine, f: &mut fmt::Formatter) -> fmt::Result {
+        let v = materialize(prism);
+        let ct = v.count();
+        write!(f, "(")?;
+        for i in 0..ct {
+            let x = unsafe { *v.nth(i) };
+            write!(f, "{}{}", if i == 0 { "" } else { " " }, x)?;
+        }
+        v.retire();
+        write!(f, ")")
+    }
+}
+impl Numeral for Eduction_ { }
+impl Callable for Eduction_ { }
@@ -0,0 +1,199 @@
+// Copyright (c) Cole Frederick. All rights reserved.
+// The use and distribution terms for this software are covered by the
+// Eclipse Public License 1.0 (https://opensource.org/licenses/eclipse-1.0.php)
+// which can be found in the file epl-v10.html at the root of this distribution.
+// By using this software in any fashion, you are agreeing to be bound by the terms of this license.
+// You must not remove this notice, or any other, from this software.
+
+//! Parallel fold: reduce chunks, then combine.
+//!
+//! Vectors split into runs of about n elements, at leaf boundaries. Maps and
+//! sets split at the children of the root. Each chunk runs through its own
+//! copy of the transducer processes, reducing with combine from its first
+//! element. Chunk results are then combined, in order.
+//!
+//! Chunks are reduced on a pool of threads only in the atomic anchor build.
+//! With `anchor_non_atomic`, alias counts can't be shared across threads, so
+//! chunks are reduced one after another on the calling thread.
+
+use std::sync::Arc;
+use memory::*;
+use value::Value;
+use handle::Handle;
+use transduce::{entry, ingest, last_call, reduce_into, reduced, Process, Shared, Transducers};
+use vector;
+use map;
+use set;
+
+pub trait CombineFn: Fn(Value, Value) -> Value + Shared { }
+impl<F: Fn(Value, Value) -> Value + Shared> CombineFn for F { }
+pub type Combine = Arc<dyn CombineFn>;
+
+pub enum Chunk {
+    /// Vector elements [start, end)
+    Span(u32, u32),
+    /// Entries of the map or set trie, by root chunk
+    Trie(u32),
+    /// Any other collection, reduced whole
+    Whole,
+}
+
+pub fn chunks(coll: Handle, n: u32) -> Vec<Chunk> {
+    if let Some(prism) = vector::find_prism(coll) {
+        let ct = coll.count();
+        let step = ((n.max(1) + vector::MASK) & !vector::MASK) as usize;
+        (0..ct).step_by(step).map(|i| Chunk::Span(i, (i + step as u32).min(ct))).collect()
+    } else if let Some(prism) = map::find_prism(coll).or(set::find_prism(coll)) {
+        (0..map::reduce::chunk_count(prism)).map(|i| Chunk::Trie(i)).collect()
+    } else {
+        vec![Chunk::Whole]
+    }
+}
+
+/// Reduces one chunk, or None when no elements made it through xf.
+/// The chunk stops early when xf does, or when combine returns `reduced(x)`.
+pub fn reduce_chunk(coll: Handle, chunk: &Chunk, xf: &Transducers, combine: &Combine) -> Option<Value> {
+    // Keeps the accumulator outside the stack, current however the reduction ends.
+    struct Fold { combine: Combine, acc: *mut Option<Value> }
+    impl Process for Fold {
+        fn ingest   (&mut self, _stack: &mut [Box<dyn Process>], v:  Value)            -> Option<Value> {
+            let acc = unsafe { &mut *self.acc };
+            match acc.take() {
+                None => { *acc = Some(v); None },
+                Some(a) => match reduced::stop((self.combine)(a, v)) {
+                    Ok(x) => { *acc = x; Some(Value::nil()) },
+                    Err(a) => { *acc = Some(a); None },
+                },
+            }
+        }
+        fn ingest_kv(&mut self, stack: &mut [Box<dyn Process>], k:  Value, v:  Value) -> Option<Value> {
+            ingest(stack, entry(k, v))
+        }
+        fn last_call(&mut self, _stack: &mut [Box<dyn Process>]) -> Value { Value::nil() }
+    }
+    let mut acc: Option<Value> = None;
+    {
+        let base: Box<dyn Process> = Box::new(Fold { combine: combine.clone(), acc: &mut acc as *mut Option<Value> });
+        let mut stack = xf.apply(vec![base]);
+        let early = match *chunk {
+            Chunk::Span(start, end) => {
+                vector::reduce::reduce_span(vector::find_prism(coll).unwrap(), start, end, &mut stack)
+            },
+            Chunk::Trie(i) => {
+                let (prism, has_vals) = match map::find_prism(coll) {
+                    Some(prism) => (prism, 1),
+                    None => (set::find_prism(coll).unwrap(), 0),
+                };
+                map::reduce::reduce_chunk(prism, i, &mut stack, has_vals)
+            },
+            Chunk::Whole => reduce_into(&mut stack, coll.split().value()),
+        };
+        if early.is_none() {
+            let _ = last_call(&mut stack);
+        }
+    }
+    acc
+}
+
+#[cfg(not(feature = "anchor_non_atomic"))]
+fn run<F: Fn(usize) -> Option<Value> + Sync>(count: usize, work: F) -> Vec<Option<Value>> {
+    use std::thread;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    let workers = thread::available_parallelism().map_or(1, |n| n.get()).min(count);
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<Option<Value>> = (0..count).map(|_| None).collect();
+    thread::scope(|s| {
+        let handles: Vec<_> = (0..workers).map(|_| s.spawn(|| {
+            let mut done = vec![];
+            loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                if i >= count { break }
+                done.push((i, work(i)));
+            }
+            done
+        })).collect();
+        for h in handles {
+            for (i, r) in h.join().unwrap() {
+                results[i] = r;
+            }
+        }
+    });
+    results
+}
+
+#[cfg(feature = "anchor_non_atomic")]
+fn run<F: Fn(usize) -> Option<Value>>(count: usize, work: F) -> Vec<Option<Value>> {
+    (0..count).map(work).collect()
+}
+
+/// Folds coll in chunks of about n elements, reducing and combining with combine.
+/// Returns nil when nothing makes it through xf.
+pub fn fold<X: Into<Transducers>>(coll: Value, n: u32, combine: impl Fn(Value, Value) -> Value + Shared + 'static,
+                                  xf: X) -> Value {
+    let combine: Combine = Arc::new(combine);
+    let xf = xf.into();
+    let c = coll._consume();
+    let chunks = chunks(c, n);
+    let results = run(chunks.len(), |i| reduce_chunk(c, &chunks[i], &xf, &combine));
+    c.retire();
+    let mut acc: Option<Value> = None;
+    for r in results.into_iter().flatten() {
+        acc = match acc {
+            None => Some(r),
+            Some(a) => match reduced::stop(combine(a, r)) {
+                Ok(x) => return x.unwrap(),
+                Err(a) => Some(a),
+            },
+        };
+    }
+    acc.unwrap_or(Value::nil())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use transduce::{filter, map, take};
+
+    fn add(a: Value, b: Value) -> Value { Value::from(a.as_i64() + b.as_i64()) }
+
+    #[test]
+    fn sums() {
+        let mut v = vector::new_value();
+        let mut m = map::new_value();
+        for i in 0..5000 {
+            v = v.conj(i.into());
+            m = m.assoc(i.into(), (i * 2).into());
+        }
+        assert_eq!(fold(v.split_out(), 100, add, Transducers::new()), 12497500.into());
+        let evens = filter(|x: &Value| x.as_i64() % 2 == 0);
+        assert_eq!(fold(v.split_out(), 512, add, evens), 6247500.into());
+        let vals = map(|kv: &Value| kv.nth(1).split_out());
+        assert_eq!(fold(m, 64, add, vals), 24995000.into());
+        assert_eq!(fold(vector::new_value(), 64, add, Transducers::new()), Value::nil());
+        let l: Value = "(1 2 3)".parse().unwrap();
+        assert_eq!(fold(l, 64, add, Transducers::new()), 6.into());
+    }
+
+    #[test]
+    fn stops_early() {
+        let v: Value = (0..5000).collect();
+        // Each chunk takes its first ten.
+        assert_eq!(fold(v.split_out(), 5000, add, take(10)), 45.into());
+        assert_eq!(fold(v.split_out(), 64, add, take(10)), 1965394.into());
+        let capped = |a: Value, b: Value| {
+            let s = a.as_i64() + b.as_i64();
+            if s >= 100 { reduced::new_value(s.into()) } else { s.into() }
+        };
+        assert_eq!(fold(v.split_out(), 5000, capped, Transducers::new()), 105.into());
+    }
+
+    #[cfg(not(feature = "anchor_non_atomic"))]
+    #[test]
+    fn threaded() {
+        let v: Value = (0..100_000).collect();
+        for _ in 0..10 {
+            assert_eq!(fold(v.split_out(), 64, add, Transducers::new()), 4999950000i64.into());
+            assert_eq!(fold(v.split_out(), 64, add, take(10)), 781320255.into());
+        }
+    }
+}
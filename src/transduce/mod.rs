@@ -6,7 +6,15 @@
 // You must not remove this notice, or any other, from this software.
 
 use std::sync::Arc;
+use std::cmp::Ordering;
+use std::collections::VecDeque;
 use Value;
+use vector;
+use set;
+
+pub mod eduction;
+pub mod reduced;
+pub mod fold;
 
 // Model:
 // Transducer -> [Transducers] stack
@@ -47,6 +55,8 @@ pub fn last_call(stack: &mut [Box<dyn Process>]) -> Value {
     unsafe { (*top(stack)).last_call(stack) }
 }
 
+/// A step of a transduction. Returning Some from an ingest ends the reduction
+/// at once with that result; see `reduced` for the reducing function side.
 pub trait Process {
     fn ingest   (&mut self, stack: &mut [Box<dyn Process>], v:  Value)            -> Option<Value> {
         inges(stack, &v)
@@ -141,42 +151,549 @@ pub struct Xf<F> {
     pub new_process: F,
 }
 
-impl<F: 'static + Fn() -> Box<dyn Process>> Transduce for Xf<F> {
+impl<F: 'static + Fn() -> Box<dyn Process> + Shared> Transduce for Xf<F> {
     fn process(&self) -> Box<dyn Process> {
         (self.new_process)()
     }
 }
 
-impl<F: 'static + Fn() -> Box<dyn Process>> Xf<F> {
+impl<F: 'static + Fn() -> Box<dyn Process> + Shared> Xf<F> {
     pub fn new(f: F) -> Transducer {
         Transducer { t: Arc::new(Xf { new_process: f }) }
     }
 }
 
-pub fn filter(pred: fn(&Value) -> bool) -> Transducer {
-    struct Filter<G> { pred: G }
-    impl<G: Fn(&Value) -> bool> Process for Filter<G> {
+/// Functions for transducers, Shared as the transducers holding them.
+pub trait ValueFn: Fn(&Value) -> Value + Shared { }
+impl<F: Fn(&Value) -> Value + Shared> ValueFn for F { }
+pub trait PredFn: Fn(&Value) -> bool + Shared { }
+impl<F: Fn(&Value) -> bool + Shared> PredFn for F { }
+
+pub type Fun = Arc<dyn ValueFn>;
+pub type Pred = Arc<dyn PredFn>;
+
+/// Map entries reach transducers of single values as [k v] vectors.
+pub fn entry(k: Value, v: Value) -> Value { vector::new_value().conj(k).conj(v) }
+
+/// Feeds each element of coll to the stack, without making the last call.
+/// Returns Some when the stack stops early.
+pub fn reduce_into(stack: &mut [Box<dyn Process>], coll: Value) -> Option<Value> {
+    struct Forward {
+        rest: *mut [Box<dyn Process>],
+        done: *mut Option<Value>,
+    }
+    fn stop(done: *mut Option<Value>, res: Option<Value>) -> Option<Value> {
+        if res.is_some() {
+            unsafe { *done = res; }
+            Some(Value::nil())
+        } else {
+            None
+        }
+    }
+    impl Process for Forward {
+        fn ingest   (&mut self, _stack: &mut [Box<dyn Process>], v:  Value)            -> Option<Value> {
+            stop(self.done, ingest(unsafe { &mut *self.rest }, v))
+        }
+        fn ingest_kv(&mut self, _stack: &mut [Box<dyn Process>], k:  Value, v:  Value) -> Option<Value> {
+            stop(self.done, ingest_kv(unsafe { &mut *self.rest }, k, v))
+        }
+        fn last_call(&mut self, _stack: &mut [Box<dyn Process>]) -> Value { Value::nil() }
+    }
+    let mut done = None;
+    {
+        let forward = Forward { rest: stack as *mut [Box<dyn Process>], done: &mut done as *mut Option<Value> };
+        let mut procs: [Box<dyn Process>; 1] = [Box::new(forward)];
+        let _ = coll.reduce(&mut procs);
+    }
+    done
+}
+
+struct Filter { pred: Pred }
+impl Process for Filter {
+    fn ingest   (&mut self, stack: &mut [Box<dyn Process>], v:  Value)            -> Option<Value> {
+        if (self.pred)(&v) {
+            let (_, rest) = stack.split_last_mut().unwrap();
+            ingest(rest, v)
+        } else {
+            None
+        }
+    }
+    fn inges    (&mut self, stack: &mut [Box<dyn Process>], v: &Value)            -> Option<Value> {
+        if (self.pred)(v) {
+            let (_, rest) = stack.split_last_mut().unwrap();
+            inges(rest, v)
+        } else {
+            None
+        }
+    }
+    fn ingest_kv(&mut self, stack: &mut [Box<dyn Process>], k:  Value, v:  Value) -> Option<Value> {
+        ingest(stack, entry(k, v))
+    }
+}
+
+pub fn filter(pred: impl Fn(&Value) -> bool + Shared + 'static) -> Transducer {
+    let pred: Pred = Arc::new(pred);
+    Xf::new(move || Box::new(Filter { pred: pred.clone() }))
+}
+
+pub fn remove(pred: impl Fn(&Value) -> bool + Shared + 'static) -> Transducer {
+    filter(move |v: &Value| !pred(v))
+}
+
+pub fn map(f: impl Fn(&Value) -> Value + Shared + 'static) -> Transducer {
+    struct Map { f: Fun }
+    impl Process for Map {
+        fn inges    (&mut self, stack: &mut [Box<dyn Process>], v: &Value)            -> Option<Value> {
+            let (_, rest) = stack.split_last_mut().unwrap();
+            ingest(rest, (self.f)(v))
+        }
+        fn ingest_kv(&mut self, stack: &mut [Box<dyn Process>], k:  Value, v:  Value) -> Option<Value> {
+            ingest(stack, entry(k, v))
+        }
+    }
+    let f: Fun = Arc::new(f);
+    Xf::new(move || Box::new(Map { f: f.clone() }))
+}
+
+pub fn keys() -> Transducer {
+    struct Keys { }
+    impl Process for Keys {
+        fn inges    (&mut self, stack: &mut [Box<dyn Process>], v: &Value)            -> Option<Value> {
+            let (_, rest) = stack.split_last_mut().unwrap();
+            inges(rest, v.nth(0))
+        }
+        fn inges_kv (&mut self, stack: &mut [Box<dyn Process>], k: &Value, v: &Value) -> Option<Value> {
+            let (_, rest) = stack.split_last_mut().unwrap();
+            inges(rest, k)
+        }
+    }
+    Xf::new(|| Box::new(Keys { }))
+}
+
+pub fn vals() -> Transducer {
+    struct Vals { }
+    impl Process for Vals {
+        fn inges    (&mut self, stack: &mut [Box<dyn Process>], v: &Value)            -> Option<Value> {
+            let (_, rest) = stack.split_last_mut().unwrap();
+            inges(rest, v.nth(1))
+        }
+        fn inges_kv (&mut self, stack: &mut [Box<dyn Process>], k: &Value, v: &Value) -> Option<Value> {
+            let (_, rest) = stack.split_last_mut().unwrap();
+            inges(rest, v)
+        }
+    }
+    Xf::new(|| Box::new(Vals { }))
+}
+
+pub fn cat() -> Transducer {
+    struct Cat { }
+    impl Process for Cat {
+        fn ingest   (&mut self, stack: &mut [Box<dyn Process>], v:  Value)            -> Option<Value> {
+            let (_, rest) = stack.split_last_mut().unwrap();
+            reduce_into(rest, v)
+        }
+        fn ingest_kv(&mut self, stack: &mut [Box<dyn Process>], k:  Value, v:  Value) -> Option<Value> {
+            ingest(stack, entry(k, v))
+        }
+    }
+    Xf::new(|| Box::new(Cat { }))
+}
+
+pub fn mapcat(f: impl Fn(&Value) -> Value + Shared + 'static) -> Transducer {
+    struct Mapcat { f: Fun }
+    impl Process for Mapcat {
+        fn inges    (&mut self, stack: &mut [Box<dyn Process>], v: &Value)            -> Option<Value> {
+            let (_, rest) = stack.split_last_mut().unwrap();
+            reduce_into(rest, (self.f)(v))
+        }
+        fn ingest_kv(&mut self, stack: &mut [Box<dyn Process>], k:  Value, v:  Value) -> Option<Value> {
+            ingest(stack, entry(k, v))
+        }
+    }
+    let f: Fun = Arc::new(f);
+    Xf::new(move || Box::new(Mapcat { f: f.clone() }))
+}
+
+pub fn take(n: u32) -> Transducer {
+    struct Take { n: u32 }
+    impl Process for Take {
+        fn ingest   (&mut self, stack: &mut [Box<dyn Process>], v:  Value)            -> Option<Value> {
+            let (_, rest) = stack.split_last_mut().unwrap();
+            if self.n == 0 {
+                return Some(last_call(rest))
+            }
+            self.n -= 1;
+            let res = ingest(rest, v);
+            if res.is_some() || self.n != 0 { res } else { Some(last_call(rest)) }
+        }
+        fn ingest_kv(&mut self, stack: &mut [Box<dyn Process>], k:  Value, v:  Value) -> Option<Value> {
+            ingest(stack, entry(k, v))
+        }
+    }
+    Xf::new(move || Box::new(Take { n }))
+}
+
+pub fn drop(n: u32) -> Transducer {
+    struct Drop { n: u32 }
+    impl Process for Drop {
+        fn inges    (&mut self, stack: &mut [Box<dyn Process>], v: &Value)            -> Option<Value> {
+            if self.n != 0 {
+                self.n -= 1;
+                return None
+            }
+            let (_, rest) = stack.split_last_mut().unwrap();
+            inges(rest, v)
+        }
+        fn ingest_kv(&mut self, stack: &mut [Box<dyn Process>], k:  Value, v:  Value) -> Option<Value> {
+            ingest(stack, entry(k, v))
+        }
+    }
+    Xf::new(move || Box::new(Drop { n }))
+}
+
+/// Every nth element, starting with the first.
+pub fn take_nth(n: u32) -> Transducer {
+    assert_ne!(n, 0, "take_nth needs a positive n.");
+    struct TakeNth { n: u32, i: u32 }
+    impl Process for TakeNth {
+        fn inges    (&mut self, stack: &mut [Box<dyn Process>], v: &Value)            -> Option<Value> {
+            let i = self.i;
+            self.i = if i + 1 == self.n { 0 } else { i + 1 };
+            if i != 0 {
+                return None
+            }
+            let (_, rest) = stack.split_last_mut().unwrap();
+            inges(rest, v)
+        }
+        fn ingest_kv(&mut self, stack: &mut [Box<dyn Process>], k:  Value, v:  Value) -> Option<Value> {
+            ingest(stack, entry(k, v))
+        }
+    }
+    Xf::new(move || Box::new(TakeNth { n, i: 0 }))
+}
+
+pub fn take_while(pred: impl Fn(&Value) -> bool + Shared + 'static) -> Transducer {
+    struct TakeWhile { pred: Pred }
+    impl Process for TakeWhile {
+        fn inges    (&mut self, stack: &mut [Box<dyn Process>], v: &Value)            -> Option<Value> {
+            let (_, rest) = stack.split_last_mut().unwrap();
+            if (self.pred)(v) { inges(rest, v) } else { Some(last_call(rest)) }
+        }
+        fn ingest_kv(&mut self, stack: &mut [Box<dyn Process>], k:  Value, v:  Value) -> Option<Value> {
+            ingest(stack, entry(k, v))
+        }
+    }
+    let pred: Pred = Arc::new(pred);
+    Xf::new(move || Box::new(TakeWhile { pred: pred.clone() }))
+}
+
+pub fn drop_while(pred: impl Fn(&Value) -> bool + Shared + 'static) -> Transducer {
+    struct DropWhile { pred: Pred, dropping: bool }
+    impl Process for DropWhile {
+        fn inges    (&mut self, stack: &mut [Box<dyn Process>], v: &Value)            -> Option<Value> {
+            if self.dropping && (self.pred)(v) {
+                return None
+            }
+            self.dropping = false;
+            let (_, rest) = stack.split_last_mut().unwrap();
+            inges(rest, v)
+        }
+        fn ingest_kv(&mut self, stack: &mut [Box<dyn Process>], k:  Value, v:  Value) -> Option<Value> {
+            ingest(stack, entry(k, v))
+        }
+    }
+    let pred: Pred = Arc::new(pred);
+    Xf::new(move || Box::new(DropWhile { pred: pred.clone(), dropping: true }))
+}
+
+/// The last n elements, passed along at the last call.
+pub fn take_last(n: u32) -> Transducer {
+    struct TakeLast { n: usize, buf: VecDeque<Value> }
+    impl Process for TakeLast {
+        fn ingest   (&mut self, stack: &mut [Box<dyn Process>], v:  Value)            -> Option<Value> {
+            self.buf.push_back(v);
+            if self.buf.len() > self.n {
+                self.buf.pop_front();
+            }
+            None
+        }
+        fn ingest_kv(&mut self, stack: &mut [Box<dyn Process>], k:  Value, v:  Value) -> Option<Value> {
+            ingest(stack, entry(k, v))
+        }
+        fn last_call(&mut self, stack: &mut [Box<dyn Process>]) -> Value {
+            let (_, rest) = stack.split_last_mut().unwrap();
+            while let Some(v) = self.buf.pop_front() {
+                if let Some(ret) = ingest(rest, v) {
+                    return ret
+                }
+            }
+            last_call(rest)
+        }
+    }
+    Xf::new(move || Box::new(TakeLast { n: n as usize, buf: VecDeque::new() }))
+}
+
+/// All but the last n elements.
+pub fn drop_last(n: u32) -> Transducer {
+    struct DropLast { n: usize, buf: VecDeque<Value> }
+    impl Process for DropLast {
         fn ingest   (&mut self, stack: &mut [Box<dyn Process>], v:  Value)            -> Option<Value> {
-            if (self.pred)(&v) {
+            self.buf.push_back(v);
+            if self.buf.len() > self.n {
                 let (_, rest) = stack.split_last_mut().unwrap();
-                ingest(rest, v)
+                ingest(rest, self.buf.pop_front().unwrap())
             } else {
                 None
             }
         }
+        fn ingest_kv(&mut self, stack: &mut [Box<dyn Process>], k:  Value, v:  Value) -> Option<Value> {
+            ingest(stack, entry(k, v))
+        }
+    }
+    Xf::new(move || Box::new(DropLast { n: n as usize, buf: VecDeque::new() }))
+}
+
+/// Each element once, the first time it's seen.
+pub fn distinct() -> Transducer {
+    struct Distinct { seen: Value }
+    impl Process for Distinct {
         fn inges    (&mut self, stack: &mut [Box<dyn Process>], v: &Value)            -> Option<Value> {
-            if (self.pred)(v) {
-                let (_, rest) = stack.split_last_mut().unwrap();
+            if self.seen.contains(v) {
+                return None
+            }
+            let seen = std::mem::replace(&mut self.seen, Value::nil());
+            self.seen = seen.conj(v.split_out());
+            let (_, rest) = stack.split_last_mut().unwrap();
+            inges(rest, v)
+        }
+        fn ingest_kv(&mut self, stack: &mut [Box<dyn Process>], k:  Value, v:  Value) -> Option<Value> {
+            ingest(stack, entry(k, v))
+        }
+    }
+    Xf::new(|| Box::new(Distinct { seen: set::new_value() }))
+}
+
+/// Drops elements equal to the one just before.
+pub fn dedupe() -> Transducer {
+    struct Dedupe { prev: Option<Value> }
+    impl Process for Dedupe {
+        fn inges    (&mut self, stack: &mut [Box<dyn Process>], v: &Value)            -> Option<Value> {
+            if self.prev.as_ref() == Some(v) {
+                return None
+            }
+            self.prev = Some(v.split_out());
+            let (_, rest) = stack.split_last_mut().unwrap();
+            inges(rest, v)
+        }
+        fn ingest_kv(&mut self, stack: &mut [Box<dyn Process>], k:  Value, v:  Value) -> Option<Value> {
+            ingest(stack, entry(k, v))
+        }
+    }
+    Xf::new(|| Box::new(Dedupe { prev: None }))
+}
+
+/// Puts sep between elements.
+pub fn interpose(sep: Value) -> Transducer {
+    struct Interpose { sep: Value, started: bool }
+    impl Process for Interpose {
+        fn inges    (&mut self, stack: &mut [Box<dyn Process>], v: &Value)            -> Option<Value> {
+            let (_, rest) = stack.split_last_mut().unwrap();
+            if self.started {
+                if let Some(ret) = inges(rest, &self.sep) {
+                    return Some(ret)
+                }
+            }
+            self.started = true;
+            inges(rest, v)
+        }
+        fn ingest_kv(&mut self, stack: &mut [Box<dyn Process>], k:  Value, v:  Value) -> Option<Value> {
+            ingest(stack, entry(k, v))
+        }
+    }
+    Xf::new(move || Box::new(Interpose { sep: sep.split_out(), started: false }))
+}
+
+/// Swaps elements found as keys in smap for their values.
+pub fn replace(smap: Value) -> Transducer {
+    struct Replace { smap: Value }
+    impl Process for Replace {
+        fn inges    (&mut self, stack: &mut [Box<dyn Process>], v: &Value)            -> Option<Value> {
+            let (_, rest) = stack.split_last_mut().unwrap();
+            if self.smap.contains(v) {
+                inges(rest, self.smap.get(v))
+            } else {
                 inges(rest, v)
+            }
+        }
+        fn ingest_kv(&mut self, stack: &mut [Box<dyn Process>], k:  Value, v:  Value) -> Option<Value> {
+            ingest(stack, entry(k, v))
+        }
+    }
+    Xf::new(move || Box::new(Replace { smap: smap.split_out() }))
+}
+
+/// Vectors of n elements. A final, shorter partition is passed along at the last call.
+pub fn partition(n: u32) -> Transducer {
+    assert_ne!(n, 0, "partition needs a positive n.");
+    struct Partition { n: u32, buf: Value }
+    impl Process for Partition {
+        fn ingest   (&mut self, stack: &mut [Box<dyn Process>], v:  Value)            -> Option<Value> {
+            let buf = std::mem::replace(&mut self.buf, Value::nil()).conj(v);
+            if buf.count() == self.n {
+                self.buf = vector::new_value();
+                let (_, rest) = stack.split_last_mut().unwrap();
+                ingest(rest, buf)
+            } else {
+                self.buf = buf;
+                None
+            }
+        }
+        fn ingest_kv(&mut self, stack: &mut [Box<dyn Process>], k:  Value, v:  Value) -> Option<Value> {
+            ingest(stack, entry(k, v))
+        }
+        fn last_call(&mut self, stack: &mut [Box<dyn Process>]) -> Value {
+            let (_, rest) = stack.split_last_mut().unwrap();
+            let buf = std::mem::replace(&mut self.buf, vector::new_value());
+            if buf.count() != 0 {
+                if let Some(ret) = ingest(rest, buf) {
+                    return ret
+                }
+            }
+            last_call(rest)
+        }
+    }
+    Xf::new(move || Box::new(Partition { n, buf: vector::new_value() }))
+}
+
+/// Vectors of consecutive elements for which f returns the same value.
+pub fn partition_by(f: impl Fn(&Value) -> Value + Shared + 'static) -> Transducer {
+    struct PartitionBy { f: Fun, key: Value, buf: Value }
+    impl Process for PartitionBy {
+        fn ingest   (&mut self, stack: &mut [Box<dyn Process>], v:  Value)            -> Option<Value> {
+            let key = (self.f)(&v);
+            let res = if self.buf.count() != 0 && key != self.key {
+                let buf = std::mem::replace(&mut self.buf, vector::new_value());
+                let (_, rest) = stack.split_last_mut().unwrap();
+                ingest(rest, buf)
             } else {
                 None
+            };
+            self.key = key;
+            let buf = std::mem::replace(&mut self.buf, Value::nil());
+            self.buf = buf.conj(v);
+            res
+        }
+        fn ingest_kv(&mut self, stack: &mut [Box<dyn Process>], k:  Value, v:  Value) -> Option<Value> {
+            ingest(stack, entry(k, v))
+        }
+        fn last_call(&mut self, stack: &mut [Box<dyn Process>]) -> Value {
+            let (_, rest) = stack.split_last_mut().unwrap();
+            let buf = std::mem::replace(&mut self.buf, vector::new_value());
+            if buf.count() != 0 {
+                if let Some(ret) = ingest(rest, buf) {
+                    return ret
+                }
+            }
+            last_call(rest)
+        }
+    }
+    let f: Fun = Arc::new(f);
+    Xf::new(move || Box::new(PartitionBy { f: f.clone(), key: Value::nil(), buf: vector::new_value() }))
+}
+
+/// Folds f over coll, starting from init. f may return `reduced(x)` to stop early.
+pub fn reduce(f: impl Fn(Value, Value) -> Value + 'static, init: Value, coll: Value) -> Value {
+    transduce(Transducers::new(), f, init, coll)
+}
+
+/// Folds f over the elements of coll, as transformed by xf.
+pub fn transduce<X: Into<Transducers>>(xf: X, f: impl Fn(Value, Value) -> Value + 'static,
+                                       init: Value, coll: Value) -> Value {
+    struct Reduce<F> { f: F, acc: Option<Value> }
+    impl<F: Fn(Value, Value) -> Value> Process for Reduce<F> {
+        fn ingest   (&mut self, _stack: &mut [Box<dyn Process>], v:  Value)            -> Option<Value> {
+            let acc = self.acc.take().unwrap();
+            match reduced::stop((self.f)(acc, v)) {
+                Ok(done) => done,
+                Err(acc) => {
+                    self.acc = Some(acc);
+                    None
+                },
+            }
+        }
+        fn ingest_kv(&mut self, stack: &mut [Box<dyn Process>], k:  Value, v:  Value) -> Option<Value> {
+            ingest(stack, entry(k, v))
+        }
+        fn last_call(&mut self, _stack: &mut [Box<dyn Process>]) -> Value { self.acc.take().unwrap() }
+    }
+    let base: Box<dyn Process> = Box::new(Reduce { f, acc: Some(init) });
+    let mut stack = xf.into().apply(vec![base]);
+    coll.reduce(&mut stack)
+}
+
+/// A reducible view of coll through xf, recomputed on each reduce.
+pub fn educe<X: Into<Transducers>>(xf: X, coll: Value) -> Value {
+    eduction::new(xf.into(), coll._consume()).handle().value()
+}
+
+fn best_key(c: &Value, f: impl Fn(&Value) -> Value + 'static, want: Ordering) -> Value {
+    struct Best<F> { f: F, want: Ordering, best: Option<(Value, Value)> }
+    impl<F: Fn(&Value) -> Value> Process for Best<F> {
+        fn inges    (&mut self, _stack: &mut [Box<dyn Process>], v: &Value)            -> Option<Value> {
+            let k = (self.f)(v);
+            let better = match self.best {
+                None => true,
+                Some((ref bk, _)) => k.partial_cmp(bk) == Some(self.want),
+            };
+            if better {
+                self.best = Some((k, v.split_out()));
             }
+            None
+        }
+        fn ingest_kv(&mut self, stack: &mut [Box<dyn Process>], k:  Value, v:  Value) -> Option<Value> {
+            ingest(stack, entry(k, v))
+        }
+        fn last_call(&mut self, _stack: &mut [Box<dyn Process>]) -> Value {
+            self.best.take().map_or(Value::nil(), |(_, v)| v)
         }
     }
-    Xf::new(move || Box::new(Filter { pred }))
+    let mut stack: [Box<dyn Process>; 1] = [Box::new(Best { f, want, best: None })];
+    c.split_out().reduce(&mut stack)
+}
+
+/// The element of c whose key is greatest, the first one on ties.
+pub fn max_key(c: &Value, f: impl Fn(&Value) -> Value + 'static) -> Value {
+    best_key(c, f, Ordering::Greater)
+}
+
+/// The element of c whose key is least, the first one on ties.
+pub fn min_key(c: &Value, f: impl Fn(&Value) -> Value + 'static) -> Value {
+    best_key(c, f, Ordering::Less)
 }
 
-pub trait Transduce {
+/// A map from f of each element to a vector of the elements having that key.
+pub fn group_by(f: impl Fn(&Value) -> Value + 'static, coll: Value) -> Value {
+    reduce(move |m: Value, v: Value| {
+        let k = f(&v);
+        let group = m.get(&k).split_out();
+        let group = if group.is_nil() { vector::new_value() } else { group };
+        m.assoc(k, group.conj(v))
+    }, ::map::new_value(), coll)
+}
+
+/// Send and Sync in the atomic anchor build. With `anchor_non_atomic`, values
+/// stay on their thread, and so do transducers holding them.
+#[cfg(not(feature = "anchor_non_atomic"))]
+pub trait Shared: Send + Sync { }
+#[cfg(not(feature = "anchor_non_atomic"))]
+impl<T: Send + Sync> Shared for T { }
+#[cfg(feature = "anchor_non_atomic")]
+pub trait Shared { }
+#[cfg(feature = "anchor_non_atomic")]
+impl<T> Shared for T { }
+
+/// Transducers are shared across threads by fold, so they must be Shared.
+pub trait Transduce: Shared {
     fn process(&self) -> Box<dyn Process>;
     fn transduce(&self, mut process_stack: Vec<Box<dyn Process>>) -> Vec<Box<dyn Process>> {
         process_stack.push(self.process());
@@ -199,6 +716,7 @@ impl Transducer {
     }
 }
 
+#[derive(Clone)]
 pub struct Transducers {
     pub stack: Arc<Vec<Transducer>>,
 }
@@ -208,6 +726,11 @@ impl Transducers {
         Transducers { stack: Vec::new().into() }
     }
 
+    pub fn add(mut self, t: Transducer) -> Transducers {
+        self.add_transducer(t);
+        self
+    }
+
     pub fn add_transducer(&mut self, t: Transducer) {
         Arc::make_mut(&mut self.stack).push(t)
     }
@@ -221,13 +744,74 @@ impl Transducers {
 }
 
 
+impl From<Transducer> for Transducers {
+    fn from(t: Transducer) -> Transducers { Transducers::new().add(t) }
+}
+
+impl From<Vec<Transducer>> for Transducers {
+    fn from(ts: Vec<Transducer>) -> Transducers { Transducers { stack: Arc::new(ts) } }
+}
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use range;
+
+    fn into_vec<X: Into<Transducers>>(xf: X, source: &str) -> String {
+        let source: Value = source.parse().unwrap();
+        format!("{}", source.pour(xf.into(), vector::new_value()))
+    }
+    fn inc(v: &Value) -> Value { Value::from(v.as_i64() + 1) }
+    fn is_even(v: &Value) -> bool { v.as_i64() % 2 == 0 }
+    fn small(v: &Value) -> bool { v.as_i64() < 3 }
+    fn pair(v: &Value) -> Value { vector::new_value().conj(v.split_out()).conj(v.split_out()) }
+
     #[test]
     fn hello() {
-
+        assert_eq!(into_vec(map(inc), "[1 2 3]"), "[2 3 4]");
+        assert_eq!(into_vec(vec![filter(is_even), map(inc)], "[1 2 3 4]"), "[3 5]");
+        assert_eq!(into_vec(remove(is_even), "[1 2 3 4]"), "[1 3]");
+        assert_eq!(into_vec(mapcat(pair), "[1 2]"), "[1 1 2 2]");
+        assert_eq!(into_vec(vec![cat(), take(3)], "[[1 2] [3 4] [5]]"), "[1 2 3]");
+        assert_eq!(into_vec(keys(), "{:a 1}"), "[:a]");
+        assert_eq!(into_vec(vals(), "{:a 1}"), "[1]");
+        assert_eq!(into_vec(drop(2), "[1 2 3 4]"), "[3 4]");
+        assert_eq!(into_vec(take_nth(2), "[1 2 3 4 5]"), "[1 3 5]");
+        assert_eq!(into_vec(take_while(small), "[1 2 3 1]"), "[1 2]");
+        assert_eq!(into_vec(drop_while(small), "[1 2 3 1]"), "[3 1]");
+        assert_eq!(into_vec(take_last(2), "[1 2 3 4]"), "[3 4]");
+        assert_eq!(into_vec(drop_last(2), "[1 2 3 4]"), "[1 2]");
+        assert_eq!(into_vec(distinct(), "[1 2 1 3 2]"), "[1 2 3]");
+        assert_eq!(into_vec(dedupe(), "[1 1 2 1 1]"), "[1 2 1]");
+        assert_eq!(into_vec(interpose(Value::from(0)), "[1 2 3]"), "[1 0 2 0 3]");
+        assert_eq!(into_vec(replace("{1 :one}".parse().unwrap()), "[1 2]"), "[:one 2]");
+        assert_eq!(into_vec(partition(2), "[1 2 3 4 5]"), "[[1 2] [3 4] [5]]");
+        assert_eq!(into_vec(partition_by(|v| Value::from(is_even(v))), "[1 3 2 4 5]"),
+                   "[[1 3] [2 4] [5]]");
+        let at_least = 2;
+        assert_eq!(into_vec(filter(move |v| v.as_i64() >= at_least), "[1 2 3]"), "[2 3]");
+        let k = Value::from(2);
+        assert_eq!(into_vec(remove(move |v| *v == k), "[1 2 3]"), "[1 3]");
+        let (lo, hi) = (Value::from(1), Value::from(10));
+        assert_eq!(into_vec(map(move |v| if *v < lo { hi.split_out() } else { v.split_out() }), "[0 1]"), "[10 1]");
+        let naturals = range::new_infinite_value(0, 1);
+        let firsts = naturals.pour(vec![partition(2), take(2)].into(), vector::new_value());
+        assert_eq!(format!("{}", firsts), "[[0 1] [2 3]]");
+    }
+    #[test]
+    fn reducing() {
+        let v: Value = "[1 2 3 4]".parse().unwrap();
+        let sum = |a: Value, b: Value| Value::from(a.as_i64() + b.as_i64());
+        assert_eq!(reduce(sum, 0.into(), v.split_out()), 10.into());
+        let offset = 10;
+        let e = educe(map(move |x| Value::from(x.as_i64() + offset)), v.split_out());
+        assert_eq!(format!("{}", e), "(11 12 13 14)");
+        assert_eq!(transduce(filter(is_even), sum, 0.into(), e.split_out()), 26.into());
+        assert_eq!(e, "[11 12 13 14]".parse().unwrap());
+        assert_eq!(max_key(&v, |x| Value::from(-(x.as_i64() - 2) * (x.as_i64() - 2))), 2.into());
+        assert_eq!(min_key(&v, |x| x.split_out()), 1.into());
+        let g = group_by(|x| Value::from(is_even(x)), v);
+        assert_eq!(g, "{true [2 4], false [1 3]}".parse().unwrap());
     }
 }
 
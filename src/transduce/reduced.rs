@@ -0,0 +1,157 @@
+// Copyright (c) Cole Frederick. All rights reserved.
+// The use and distribution terms for this software are covered by the
+// Eclipse Public License 1.0 (https://opensource.org/licenses/eclipse-1.0.php)
+// which can be found in the file epl-v10.html at the root of this distribution.
+// By using this software in any fashion, you are agreeing to be bound by the terms of this license.
+// You must not remove this notice, or any other, from this software.
+
+//! A value marked as the final result of a reduction.
+//!
+//! A reducing function returns `reduced(x)` to stop early with result x.
+//! Processes turn it into `Some(x)`, which every collection's reduce
+//! returns immediately, visiting no further elements.
+
+use std::fmt;
+use std::cmp::Ordering;
+use memory::*;
+use dispatch::*;
+use value::*;
+use handle::Handle;
+
+// Layout: [prism v]
+pub struct Reduced_ { }
+pub fn prism_unit() -> Unit { mechanism::prism::<Reduced_>() }
+pub fn is_prism(prism: AnchoredLine) -> bool { prism[0] == prism_unit() }
+pub fn find_prism(h: Handle) -> Option<AnchoredLine> { h.find_prism(prism_unit()) }
+pub fn is_reduced(h: Handle) -> bool { find_prism(h).is_some() }
+
+pub fn new(v: Handle) -> Unit {
+    let s = Segment::new(2 /*prism v*/);
+    s.set(0, prism_unit());
+    s.set(1, v.unit());
+    s.unit()
+}
+pub fn new_value(v: Value) -> Value { new(v._consume()).handle().value() }
+
+/// The value inside, if v is reduced. Otherwise v itself.
+pub fn unreduced(v: Value) -> Value {
+    let h = v._consume();
+    if let Some(prism) = find_prism(h) {
+        let inner = prism[1].handle().split();
+        h.retire();
+        inner.value()
+    } else {
+        h.value()
+    }
+}
+
+/// Some result when v is reduced, the signal for a process to stop.
+pub fn stop(v: Value) -> Result<Option<Value>, Value> {
+    if is_reduced(v._handle()) { Ok(Some(unreduced(v))) } else { Err(v) }
+}
+
+impl Dispatch for Reduced_ {
+    fn tear_down(&self, prism: AnchoredLine) {
+        let seg = prism.segment();
+        assert_eq!(0, seg.anchor().aliases());
+        prism[1].handle().retire();
+        Segment::free(seg)
+    }
+    fn alias_components(&self, prism: AnchoredLine) { prism[1].handle().split(); }
+}
+impl Identification for Reduced_ {
+    fn type_name(&self) -> &'static str { "Reduced" }
+}
+impl Distinguish for Reduced_ {
+    fn hash(&self, prism: AnchoredLine) -> u32 { prism[1].handle().hash().rotate_left(7) }
+    fn eq(&self, prism: AnchoredLine, other: Unit) -> bool {
+        let o = other.handle();
+        if let Some(o_prism) = find_prism(o) {
+            prism[1].handle().eq(o_prism[1].handle())
+        } else {
+            false
+        }
+    }
+    fn cmp(&self, prism: AnchoredLine, other: Unit) -> Option<Ordering> {
+        let o = other.handle();
+        if let Some(o_prism) = find_prism(o) {
+            return prism[1].handle().cmp(o_prism[1].handle())
+        }
+        if o.is_ref() {
+            let o_prism_unit = o.logical_value()[0];
+            Some(prism_unit().cmp(&o_prism_unit))
+        } else {
+            Some(Ordering::Greater)
+        }
+    }
+}
+impl Aggregate for Reduced_ { }
+impl Sequential for Reduced_ { }
+impl Associative for Reduced_ { }
+impl Reversible for Reduced_ { }
+impl Sorted for Reduced_ { }
+impl Notation for Reduced_ {
+    fn edn(&self, prism: AnchoredLine, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#reduced {}", prism[1].handle())
+    }
+}
+impl Numeral for Reduced_ { }
+impl Callable for Reduced_ { }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use transduce::{self, Process};
+    use {vector, list, map, set, queue, range};
+
+    struct Visits { n: u32, stop_at: u32 }
+    impl Process for Visits {
+        fn inges(&mut self, _stack: &mut [Box<dyn Process>], v: &Value) -> Option<Value> {
+            self.n += 1;
+            if self.n == self.stop_at { Some(Value::from(self.n)) } else { None }
+        }
+        fn inges_kv(&mut self, stack: &mut [Box<dyn Process>], k: &Value, v: &Value) -> Option<Value> {
+            self.inges(stack, k)
+        }
+        fn last_call(&mut self, _stack: &mut [Box<dyn Process>]) -> Value { Value::from(self.n + 1000) }
+    }
+    fn visits(coll: &Value, stop_at: u32) -> Value {
+        let mut stack: [Box<dyn Process>; 1] = [Box::new(Visits { n: 0, stop_at })];
+        coll.split_out().reduce(&mut stack)
+    }
+
+    #[test]
+    fn stops_immediately() {
+        for &ct in [5u32, 40, 2000].iter() {
+            let (mut v, mut l, mut m, mut s, mut q) = (vector::new_value(), list::new_value(),
+                map::new_value(), set::new_value(), queue::new_value());
+            for i in 0..ct {
+                v = v.conj(i.into());
+                l = l.conj(i.into());
+                m = m.assoc(i.into(), i.into());
+                s = s.conj(i.into());
+                q = q.conj(i.into());
+            }
+            let r = range::new_value(0, ct as i64, 1);
+            for coll in [v, l, m, s, q, r].iter() {
+                for &stop_at in [1, 3, ct].iter() {
+                    assert_eq!(visits(coll, stop_at), stop_at.into(), "{} {}", coll.type_name(), ct);
+                }
+                assert_eq!(visits(coll, ct + 1), (ct + 1000).into());
+            }
+        }
+    }
+    #[test]
+    fn reducing_fn() {
+        let r = new_value(Value::from(3));
+        assert_eq!(format!("{}", r), "#reduced 3");
+        assert!(is_reduced(r._handle()));
+        assert_eq!(unreduced(r), 3.into());
+        let sum_upto_10 = |a: Value, b: Value| {
+            let x = a.as_i64() + b.as_i64();
+            if x >= 10 { new_value(Value::from(x)) } else { Value::from(x) }
+        };
+        let naturals = range::new_infinite_value(0, 1);
+        assert_eq!(transduce::reduce(sum_upto_10, 0.into(), naturals), 10.into());
+    }
+}
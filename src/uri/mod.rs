@@ -5,3 +5,517 @@
 // By using this software in any fashion, you are agreeing to be bound by the terms of this license.
 // You must not remove this notice, or any other, from this software.
 
+//! Uniform resource identifiers (RFC 3986), read and printed as `#uri "..."`.
+//!
+//! A Uri value holds its five components as strings, nil when absent, so a
+//! component is handed out by aliasing it. Resolution and normalization
+//! work on the text of the components and build a new Uri.
+
+use std::fmt;
+use std::io;
+use std::cmp::Ordering;
+use memory::*;
+use dispatch::*;
+use value::*;
+use handle::Handle;
+use {string, fressian};
+
+// Layout: [prism scheme authority path query fragment]
+pub struct Uri_ { }
+pub fn prism_unit() -> Unit { mechanism::prism::<Uri_>() }
+pub fn is_prism(prism: AnchoredLine) -> bool { prism[0] == prism_unit() }
+pub fn find_prism(h: Handle) -> Option<AnchoredLine> { h.find_prism(prism_unit()) }
+pub fn is_uri(h: Handle) -> bool { find_prism(h).is_some() }
+
+pub const SCHEME: i32 = 1;
+pub const AUTHORITY: i32 = 2;
+pub const PATH: i32 = 3;
+pub const QUERY: i32 = 4;
+pub const FRAGMENT: i32 = 5;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Parts {
+    pub scheme: Option<String>,
+    pub authority: Option<String>,
+    pub path: String,
+    pub query: Option<String>,
+    pub fragment: Option<String>,
+}
+
+impl Parts {
+    /// The text of the uri, as in section 5.3.
+    pub fn recompose(&self) -> String {
+        let mut s = String::new();
+        if let Some(ref x) = self.scheme {
+            s.push_str(x);
+            s.push(':');
+        }
+        if let Some(ref x) = self.authority {
+            s.push_str("//");
+            s.push_str(x);
+        }
+        s.push_str(&self.path);
+        if let Some(ref x) = self.query {
+            s.push('?');
+            s.push_str(x);
+        }
+        if let Some(ref x) = self.fragment {
+            s.push('#');
+            s.push_str(x);
+        }
+        s
+    }
+}
+
+fn is_unreserved(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'-' || b == b'.' || b == b'_' || b == b'~'
+}
+fn is_allowed(b: u8) -> bool {
+    is_unreserved(b) || b"%:/?#[]@!$&'()*+,;=".contains(&b)
+}
+fn hex_value(b: u8) -> Option<u8> { (b as char).to_digit(16).map(|d| d as u8) }
+
+/// Splits source into its components, checking that it has only characters
+/// a uri may have and well formed percent escapes.
+pub fn parse(source: &str) -> Result<Parts, String> {
+    let bytes = source.as_bytes();
+    for (i, &b) in bytes.iter().enumerate() {
+        if !is_allowed(b) {
+            return Err(format!("Bad uri #uri {:?}, the character at byte {} isn't allowed, \
+                                it should be percent-encoded.", source, i))
+        }
+        if b == b'%' {
+            let escape = bytes.get((i + 1)..(i + 3));
+            if !escape.map_or(false, |e| e.iter().all(|&h| hex_value(h).is_some())) {
+                return Err(format!("Bad uri #uri {:?}, the % at byte {} should begin \
+                                    an escape of two hex digits, like %2F.", source, i))
+            }
+        }
+    }
+    let mut rest = source;
+    let mut parts = Parts::default();
+    if let Some(i) = rest.find('#') {
+        parts.fragment = Some(rest[(i + 1)..].to_string());
+        rest = &rest[..i];
+    }
+    if let Some(i) = rest.find('?') {
+        parts.query = Some(rest[(i + 1)..].to_string());
+        rest = &rest[..i];
+    }
+    if let Some(i) = rest.find(':') {
+        if !rest[..i].contains('/') {
+            let scheme = &rest[..i];
+            let b = scheme.as_bytes();
+            let valid = !b.is_empty() && b[0].is_ascii_alphabetic() &&
+                b.iter().all(|&c| c.is_ascii_alphanumeric() || c == b'+' || c == b'-' || c == b'.');
+            if !valid {
+                return Err(format!("Bad uri #uri {:?}, the scheme {:?} should be a letter \
+                                    followed by letters, digits, + - or .", source, scheme))
+            }
+            parts.scheme = Some(scheme.to_string());
+            rest = &rest[(i + 1)..];
+        }
+    }
+    if rest.starts_with("//") {
+        let after = &rest[2..];
+        let end = after.find('/').unwrap_or(after.len());
+        parts.authority = Some(after[..end].to_string());
+        rest = &after[end..];
+    }
+    parts.path = rest.to_string();
+    Ok(parts)
+}
+
+pub fn new_parsed(source: &str) -> Result<Handle, String> {
+    parse(source).map(|p| from_parts(&p))
+}
+pub fn new_value(source: &str) -> Value {
+    match new_parsed(source) {
+        Ok(h) => h.value(),
+        Err(msg) => panic!("{}", msg),
+    }
+}
+
+fn component_handle(x: &Option<String>) -> Handle {
+    match *x {
+        Some(ref s) => string::new_from_str(s),
+        None => Handle::nil(),
+    }
+}
+pub fn from_parts(p: &Parts) -> Handle {
+    let s = Segment::new(6 /*prism scheme authority path query fragment*/);
+    let prism = s.line_at(0);
+    prism.set(0, prism_unit());
+    prism.set(SCHEME, component_handle(&p.scheme).unit());
+    prism.set(AUTHORITY, component_handle(&p.authority).unit());
+    prism.set(PATH, string::new_from_str(&p.path).unit());
+    prism.set(QUERY, component_handle(&p.query).unit());
+    prism.set(FRAGMENT, component_handle(&p.fragment).unit());
+    s.unit().handle()
+}
+
+fn text(h: Handle) -> Option<String> {
+    string::find_prism(h).map(|prism| string::chunks(prism).collect())
+}
+pub fn parts(prism: AnchoredLine) -> Parts {
+    Parts {
+        scheme: text(prism[SCHEME].handle()),
+        authority: text(prism[AUTHORITY].handle()),
+        path: text(prism[PATH].handle()).unwrap(),
+        query: text(prism[QUERY].handle()),
+        fragment: text(prism[FRAGMENT].handle()),
+    }
+}
+
+fn prism_of(u: &Value) -> AnchoredLine {
+    match find_prism(u._handle()) {
+        Some(prism) => prism,
+        None => panic!("Not a uri: {}", u),
+    }
+}
+
+/// A component of a uri, a string or nil, by its index in the layout.
+pub fn component(u: &Value, idx: i32) -> Value {
+    let h = prism_of(u)[idx].handle();
+    h.split();
+    h.value()
+}
+pub fn scheme(u: &Value) -> Value { component(u, SCHEME) }
+pub fn authority(u: &Value) -> Value { component(u, AUTHORITY) }
+pub fn path(u: &Value) -> Value { component(u, PATH) }
+pub fn query(u: &Value) -> Value { component(u, QUERY) }
+pub fn fragment(u: &Value) -> Value { component(u, FRAGMENT) }
+
+// Authority is [user-info@]host[:port], where host may be a bracketed ip literal.
+fn split_authority(a: &str) -> (Option<&str>, &str, Option<&str>) {
+    let (user_info, host_port) = match a.rfind('@') {
+        Some(i) => (Some(&a[..i]), &a[(i + 1)..]),
+        None => (None, a),
+    };
+    let bracket = host_port.rfind(']').unwrap_or(0);
+    match host_port.rfind(':') {
+        Some(i) if i >= bracket => (user_info, &host_port[..i], Some(&host_port[(i + 1)..])),
+        _ => (user_info, host_port, None),
+    }
+}
+fn from_authority(u: &Value, f: impl Fn(&str) -> Option<Value>) -> Value {
+    text(prism_of(u)[AUTHORITY].handle()).and_then(|a| f(&a)).unwrap_or_else(Value::nil)
+}
+pub fn user_info(u: &Value) -> Value {
+    from_authority(u, |a| split_authority(a).0.map(string::new_value_from_str))
+}
+pub fn host(u: &Value) -> Value {
+    from_authority(u, |a| Some(string::new_value_from_str(split_authority(a).1)))
+}
+/// The port as an integer, nil when absent or empty.
+pub fn port(u: &Value) -> Value {
+    from_authority(u, |a| split_authority(a).2.and_then(|p| p.parse::<i64>().ok()).map(Value::from))
+}
+
+/// Removes . and .. segments from a path, as in section 5.2.4.
+pub fn remove_dot_segments(path: &str) -> String {
+    let mut input = path;
+    let mut output = String::new();
+    while !input.is_empty() {
+        if input.starts_with("../") {
+            input = &input[3..];
+        } else if input.starts_with("./") || input.starts_with("/./") {
+            input = &input[2..];
+        } else if input == "/." {
+            input = "/";
+        } else if input.starts_with("/../") || input == "/.." {
+            input = if input == "/.." { "/" } else { &input[3..] };
+            let cut = output.rfind('/').unwrap_or(0);
+            output.truncate(cut);
+        } else if input == "." || input == ".." {
+            input = "";
+        } else {
+            let start = if input.starts_with('/') { 1 } else { 0 };
+            let end = input[start..].find('/').map_or(input.len(), |i| i + start);
+            output.push_str(&input[..end]);
+            input = &input[end..];
+        }
+    }
+    output
+}
+
+fn merge(base: &Parts, path: &str) -> String {
+    if base.authority.is_some() && base.path.is_empty() {
+        return format!("/{}", path)
+    }
+    match base.path.rfind('/') {
+        Some(i) => format!("{}{}", &base.path[..(i + 1)], path),
+        None => path.to_string(),
+    }
+}
+
+/// Resolves a reference against a base, as in section 5.2.2.
+pub fn resolve_parts(base: &Parts, r: &Parts) -> Parts {
+    let mut t = Parts { fragment: r.fragment.clone(), ..Parts::default() };
+    if r.scheme.is_some() {
+        t.scheme = r.scheme.clone();
+        t.authority = r.authority.clone();
+        t.path = remove_dot_segments(&r.path);
+        t.query = r.query.clone();
+        return t
+    }
+    t.scheme = base.scheme.clone();
+    if r.authority.is_some() {
+        t.authority = r.authority.clone();
+        t.path = remove_dot_segments(&r.path);
+        t.query = r.query.clone();
+        return t
+    }
+    t.authority = base.authority.clone();
+    if r.path.is_empty() {
+        t.path = base.path.clone();
+        t.query = if r.query.is_some() { r.query.clone() } else { base.query.clone() };
+    } else {
+        t.path = if r.path.starts_with('/') {
+            remove_dot_segments(&r.path)
+        } else {
+            remove_dot_segments(&merge(base, &r.path))
+        };
+        t.query = r.query.clone();
+    }
+    t
+}
+
+pub fn resolve(base: &Value, reference: &Value) -> Value {
+    let b = parts(prism_of(base));
+    let r = parts(prism_of(reference));
+    from_parts(&resolve_parts(&b, &r)).value()
+}
+
+// Uppercases the hex digits of escapes, and decodes escaped unreserved characters.
+fn normalize_escapes(s: &str) -> String {
+    let b = s.as_bytes();
+    let mut out = String::new();
+    let mut i = 0;
+    while i < b.len() {
+        if b[i] == b'%' {
+            let x = hex_value(b[i + 1]).unwrap() * 16 + hex_value(b[i + 2]).unwrap();
+            if is_unreserved(x) {
+                out.push(x as char);
+            } else {
+                out.push_str(&format!("%{:02X}", x));
+            }
+            i += 3;
+        } else {
+            out.push(b[i] as char);
+            i += 1;
+        }
+    }
+    out
+}
+
+/// Normalizes as in section 6.2.2: lowercases the scheme and host, evens out
+/// percent-encoding and removes dot segments. A uri with an authority and
+/// an empty path gets the path /.
+pub fn normalize_parts(p: &Parts) -> Parts {
+    let authority = p.authority.as_ref().map(|a| {
+        let (user_info, host, port) = split_authority(a);
+        let mut s = String::new();
+        if let Some(x) = user_info {
+            s.push_str(&normalize_escapes(x));
+            s.push('@');
+        }
+        s.push_str(&normalize_escapes(&host.to_ascii_lowercase()));
+        if let Some(x) = port {
+            s.push(':');
+            s.push_str(x);
+        }
+        s
+    });
+    let mut path = remove_dot_segments(&normalize_escapes(&p.path));
+    if authority.is_some() && path.is_empty() {
+        path.push('/');
+    }
+    Parts {
+        scheme: p.scheme.as_ref().map(|x| x.to_ascii_lowercase()),
+        authority,
+        path,
+        query: p.query.as_ref().map(|x| normalize_escapes(x)),
+        fragment: p.fragment.as_ref().map(|x| normalize_escapes(x)),
+    }
+}
+
+pub fn normalize(u: &Value) -> Value {
+    from_parts(&normalize_parts(&parts(prism_of(u)))).value()
+}
+
+/// Percent-encodes the utf-8 bytes of every character but the unreserved ones.
+pub fn encode(s: &str) -> String {
+    let mut out = String::new();
+    for &b in s.as_bytes() {
+        if is_unreserved(b) {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{:02X}", b));
+        }
+    }
+    out
+}
+
+/// Decodes percent escapes, which should make up valid utf-8.
+pub fn decode(s: &str) -> Result<String, String> {
+    let b = s.as_bytes();
+    let mut out = vec![];
+    let mut i = 0;
+    while i < b.len() {
+        if b[i] == b'%' {
+            let x = match (b.get(i + 1).and_then(|&h| hex_value(h)), b.get(i + 2).and_then(|&h| hex_value(h))) {
+                (Some(h), Some(l)) => h * 16 + l,
+                _ => return Err(format!("Bad percent escape at byte {} of {:?}.", i, s)),
+            };
+            out.push(x);
+            i += 3;
+        } else {
+            out.push(b[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).map_err(|_| format!("Percent escapes of {:?} aren't valid utf-8.", s))
+}
+
+/// Reads a uri written by fressian, returning it and the bytes used.
+pub fn read_fressian(bytes: &[u8]) -> Result<(Handle, usize), String> {
+    if bytes.get(0) != Some(&fressian::Code::URI) {
+        return Err(format!("Not a fressian uri."))
+    }
+    match fressian::read_string(&bytes[1..]) {
+        Some((s, used)) => new_parsed(&s).map(|h| (h, 1 + used)),
+        None => Err(format!("Bad fressian uri, it should hold a string.")),
+    }
+}
+
+impl Dispatch for Uri_ {
+    fn tear_down(&self, prism: AnchoredLine) {
+        let seg = prism.segment();
+        assert_eq!(0, seg.anchor().aliases());
+        for i in SCHEME..(FRAGMENT + 1) {
+            prism[i].handle().retire();
+        }
+        Segment::free(seg)
+    }
+    fn alias_components(&self, prism: AnchoredLine) {
+        for i in SCHEME..(FRAGMENT + 1) {
+            prism[i].handle().split();
+        }
+    }
+}
+impl Identification for Uri_ {
+    fn type_name(&self) -> &'static str { "Uri" }
+}
+impl Distinguish for Uri_ {
+    fn hash(&self, prism: AnchoredLine) -> u32 {
+        use random::{PI, cycle_abc};
+        let mut y = PI[93];
+        for i in SCHEME..(FRAGMENT + 1) {
+            y = cycle_abc(34, y.wrapping_add(prism[i].handle().hash() as u64));
+        }
+        cycle_abc(210, y) as u32
+    }
+    fn eq(&self, prism: AnchoredLine, other: Unit) -> bool {
+        match find_prism(other.handle()) {
+            Some(o_prism) => (SCHEME..(FRAGMENT + 1)).all(|i| prism[i].handle().eq(o_prism[i].handle())),
+            None => false,
+        }
+    }
+    fn cmp(&self, prism: AnchoredLine, other: Unit) -> Option<Ordering> {
+        let o = other.handle();
+        if let Some(o_prism) = find_prism(o) {
+            return parts(prism).recompose().partial_cmp(&parts(o_prism).recompose())
+        }
+        if o.is_ref() {
+            let o_prism_unit = o.logical_value()[0];
+            Some(prism_unit().cmp(&o_prism_unit))
+        } else {
+            Some(Ordering::Greater)
+        }
+    }
+}
+impl Aggregate for Uri_ { }
+impl Sequential for Uri_ { }
+impl Associative for Uri_ { }
+impl Reversible for Uri_ { }
+impl Sorted for Uri_ { }
+impl Notation for Uri_ {
+    fn edn(&self, prism: AnchoredLine, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#uri {:?}", parts(prism).recompose())
+    }
+    fn fressian(&self, prism: AnchoredLine, w: &mut dyn io::Write) -> io::Result<usize> {
+        let mut buf = vec![fressian::Code::URI];
+        fressian::write_string(&mut buf, &parts(prism).recompose());
+        w.write_all(&buf)?;
+        Ok(buf.len())
+    }
+}
+impl Numeral for Uri_ { }
+impl Callable for Uri_ { }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn s(x: &str) -> Value { string::new_value_from_str(x) }
+
+    #[test]
+    fn components() {
+        let u = new_value("HTTP://user@Example.com:8080/a/b?q=1#top");
+        assert_eq!(scheme(&u), s("HTTP"));
+        assert_eq!(authority(&u), s("user@Example.com:8080"));
+        assert_eq!(path(&u), s("/a/b"));
+        assert_eq!(query(&u), s("q=1"));
+        assert_eq!(fragment(&u), s("top"));
+        assert_eq!(user_info(&u), s("user"));
+        assert_eq!(host(&u), s("Example.com"));
+        assert_eq!(port(&u), Value::from(8080));
+        let m = new_value("mailto:a@b.org");
+        assert_eq!(path(&m), s("a@b.org"));
+        assert!(authority(&m).is_nil() && query(&m).is_nil() && host(&m).is_nil());
+        assert_eq!(host(&new_value("//[::1]:80")), s("[::1]"));
+        assert!(new_parsed("a b").is_err() && new_parsed("x%4").is_err() && new_parsed("1x:y").is_err());
+    }
+
+    #[test]
+    fn resolution() {
+        let base = new_value("http://a/b/c/d;p?q");
+        let cases = [("g:h", "g:h"), ("g", "http://a/b/c/g"), ("./g", "http://a/b/c/g"),
+            ("g/", "http://a/b/c/g/"), ("/g", "http://a/g"), ("//g", "http://g"),
+            ("?y", "http://a/b/c/d;p?y"), ("g?y", "http://a/b/c/g?y"), ("#s", "http://a/b/c/d;p?q#s"),
+            ("", "http://a/b/c/d;p?q"), (".", "http://a/b/c/"), ("..", "http://a/b/"),
+            ("../g", "http://a/b/g"), ("../../g", "http://a/g"), ("../../../g", "http://a/g"),
+            ("/./g", "http://a/g"), ("g.", "http://a/b/c/g."), ("./../g", "http://a/b/g"),
+            ("g;x=1/../y", "http://a/b/c/y")];
+        for &(r, expected) in cases.iter() {
+            assert_eq!(resolve(&base, &new_value(r)), new_value(expected), "{}", r);
+        }
+    }
+
+    #[test]
+    fn normalizing_and_encoding() {
+        let u = new_value("HTTP://User@EXAMPLE.com/%7euser/./a/../b%2f?%61=%3d");
+        assert_eq!(format!("{}", normalize(&u)), r#"#uri "http://User@example.com/~user/b%2F?a=%3D""#);
+        assert_eq!(normalize(&new_value("http://example.com")), new_value("http://example.com/"));
+        assert_eq!(encode("a b/ü"), "a%20b%2F%C3%BC");
+        assert_eq!(decode("a%20b%2F%C3%BC"), Ok("a b/ü".to_string()));
+        assert!(decode("%zz").is_err() && decode("%FF").is_err());
+    }
+
+    #[test]
+    fn notations() {
+        let u: Value = r#"[#uri "http://x.org/a?b#c" 1]"#.parse().unwrap();
+        assert_eq!(format!("{}", u), r#"[#uri "http://x.org/a?b#c" 1]"#);
+        assert_eq!(*u.nth(0), new_value("http://x.org/a?b#c"));
+        let h = u.nth(0)._handle();
+        let mut buf = vec![];
+        let prism = find_prism(h).unwrap();
+        let used = mechanism::as_dispatch(&prism[0]).fressian(prism, &mut buf).unwrap();
+        assert_eq!(used, buf.len());
+        assert_eq!(buf[0], fressian::Code::URI);
+        let (back, n) = read_fressian(&buf).unwrap();
+        assert_eq!(n, buf.len());
+        assert_eq!(back.value(), *u.nth(0));
+    }
+}
@@ -9,15 +9,21 @@ use std::cmp;
 use std::fmt;
 use std::default;
 use std::ops;
+use std::mem;
+use std::marker::PhantomData;
 use std::hash::{Hash, Hasher};
 use handle::*;
 use transduce::{Transducers, Process};
+use {vector, list, map, set, queue, range};
 
 pub mod operators;
 pub mod conversions;
 
 pub struct Value {
     pub handle: Handle,
+    // Non-atomic alias counts can't be shared, so values stay on their thread.
+    #[cfg(feature = "anchor_non_atomic")]
+    _local: PhantomData<*const ()>,
 }
 
 impl Value {
@@ -98,6 +104,7 @@ impl Value {
         let v = self.handle().nth(idx) as *const Value;
         unsafe { &*v }
     }
+    pub fn nth_out(&self, idx: u32) -> Value { self.handle().nth_out(idx).value() }
     pub fn nth_set(self, idx: u32, v: Value) -> Value {
         self.consume().nth_set(idx, v.consume()).value()
     }
@@ -149,7 +156,10 @@ impl Value {
 }
 
 impl From<Handle> for Value {
+    #[cfg(not(feature = "anchor_non_atomic"))]
     fn from(h: Handle) -> Self { Value { handle: h } }
+    #[cfg(feature = "anchor_non_atomic")]
+    fn from(h: Handle) -> Self { Value { handle: h, _local: PhantomData } }
 }
 impl Drop for Value {
     fn drop(&mut self) { self.handle().retire(); }
@@ -210,6 +220,68 @@ impl<'a> ops::Index<i32> for Value {
 #[cfg(test)]
 mod test {
     use super::*;
+
+    #[test]
+    fn iterates() {
+        for &ct in [0u32, 5, 16, 20, 40, 300, 5000].iter() {
+            let v: Value = (0..ct).collect();
+            assert!(v.iter().map(|x| x.as_i64()).eq(0..(ct as i64)));
+            let mut l = list::new_value();
+            let mut q = queue::new_value();
+            let mut m = map::new_value();
+            let mut s = set::new_value();
+            for i in 0..ct {
+                l = l.conj(i.into());
+                q = q.conj(i.into());
+                m = m.assoc(i.into(), (i * 2).into());
+                s = s.conj(i.into());
+            }
+            assert!(l.iter().map(|x| x.as_i64()).eq((0..(ct as i64)).rev()));
+            assert!(q.iter().map(|x| x.as_i64()).eq(0..(ct as i64)));
+            assert_eq!(s.iter().map(|x| x.as_i64()).sum::<i64>(), (0..(ct as i64)).sum());
+            assert_eq!(s.iter().count() as u32, ct);
+            assert!(m.iter_kv().all(|(k, v)| v.as_i64() == k.as_i64() * 2));
+            assert_eq!(m.iter_kv().count() as u32, ct);
+            let w: Value = v.split_out().into_iter().collect();
+            assert_eq!(w, v);
+        }
+    }
+
+    #[test]
+    fn iterates_others() {
+        use {string, character};
+        let r = range::new_value(3, 30, 3);
+        assert!(r.into_iter().map(|x| x.as_i64()).eq((3..30).step_by(3)));
+        let big = range::new_value(0, 2_000_000, 1);
+        assert_eq!(big.into_iter().fold(0, |s, x| s + x.as_i64()), 1_999_999_000_000);
+        let t = string::new_value_from_str("héllo");
+        let cs: String = t.into_iter().map(|c| character::as_char(character::find_prism(c._handle()).unwrap())).collect();
+        assert_eq!(cs, "héllo");
+        let e = ::educe(::map(|x: &Value| Value::from(x.as_i64() * 10)), ::vector().conj(1.into()).conj(2.into()));
+        assert!(e.into_iter().map(|x| x.as_i64()).eq(vec![10, 20]));
+        let w: Vec<i64> = ::vector().conj(4.into()).into_iter().map(|x| x.as_i64()).collect();
+        assert_eq!(w, vec![4]);
+        let mut p = queue::priority::new_value();
+        for i in [5, 3, 8, 1].iter() {
+            p = p.conj((*i).into());
+        }
+        let mut xs: Vec<i64> = p.iter().map(|x| x.as_i64()).collect();
+        assert_eq!(xs[0], 1);
+        xs.sort();
+        assert_eq!(xs, vec![1, 3, 5, 8]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Range elements can't be borrowed, iterate over it by value.")]
+    fn borrows_computed() {
+        range::new_value(0, 3, 1).iter().count();
+    }
+
+    #[test]
+    #[should_panic(expected = "Can't iterate over an infinite Cycle.")]
+    fn iterates_infinite() {
+        ::cycle(::vector().conj(1.into())).into_iter().count();
+    }
 }
 // Important Traits:
 // Drop, Default, Display, Debug, Clone
@@ -221,9 +293,14 @@ mod test {
 // PartialOrd: < <= => >
 // From: numbers, strings
 
-// derived?
-// unsafe impl Send for Value {}
-// unsafe impl Sync for Value {}
+// A value is a word, so Value is Send + Sync. Sharing values between threads
+// is sound without anchor_non_atomic, where alias counts change atomically.
+// With anchor_non_atomic, Value is neither.
+#[cfg(not(feature = "anchor_non_atomic"))]
+fn _assert_send_sync() {
+    fn is_send_sync<T: Send + Sync>() { }
+    is_send_sync::<Value>();
+}
 // index with i32, Value, &str ?
 // partialEq with i32, &str
 // FromIterator, Extend, IntoIterator<Value>, IntoIterator<&Value>
@@ -246,24 +323,122 @@ impl<K: Into<Value>, V: Into<Value>> FromIterator<(K, V)> for Value {
         m
     }
 }
-pub struct ValueIter { }
+/// Borrowing iterator over the elements of a collection.
+/// Maps yield their keys, see iter_kv for entries. Priority queues yield
+/// in heap order, pop or reduce for priority order. Sequences that compute
+/// their elements, like strings, ranges and eductions, have nothing to lend,
+/// so they iterate by value, see ValueIter.
+pub enum Iter<'a> {
+    Empty,
+    // Boxed, as node stacks make these hundreds of bytes.
+    Seq(Box<vector::iter::Iter<'a>>),
+    Keys(Box<map::iter::Iter<'a>>),
+    Queue(Box<(vector::iter::Iter<'a>, vector::iter::Iter<'a>)>),
+}
+impl<'a> Iterator for Iter<'a> {
+    type Item = &'a Value;
+    fn next(&mut self) -> Option<&'a Value> {
+        match *self {
+            Iter::Empty => None,
+            Iter::Seq(ref mut i) => i.next(),
+            Iter::Keys(ref mut i) => i.next(),
+            Iter::Queue(ref mut q) => q.0.next().or_else(|| q.1.next()),
+        }
+    }
+}
+fn can_iterate(h: Handle) {
+    if range::is_infinite(h) {
+        panic!("Can't iterate over an infinite {}.", h.type_name())
+    }
+}
+impl Value {
+    pub fn iter(&self) -> Iter<'_> {
+        let h = self.handle();
+        can_iterate(h);
+        if h.is_nil() {
+            Iter::Empty
+        } else if let Some(prism) = vector::find_prism(h) {
+            Iter::Seq(Box::new(vector::iter::Iter::new(prism, false)))
+        } else if let Some(prism) = list::find_prism(h) {
+            Iter::Seq(Box::new(vector::iter::Iter::new(prism, true)))
+        } else if let Some(prism) = set::find_prism(h) {
+            Iter::Keys(Box::new(map::iter::Iter::new(prism, 0)))
+        } else if let Some(prism) = map::find_prism(h) {
+            Iter::Keys(Box::new(map::iter::Iter::new(prism, 1)))
+        } else if let Some(prism) = queue::find_prism(h) {
+            let front = list::find_prism(prism[1].handle()).unwrap();
+            let rear = vector::find_prism(prism[2].handle()).unwrap();
+            Iter::Queue(Box::new((vector::iter::Iter::new(front, true), vector::iter::Iter::new(rear, false))))
+        } else if let Some(prism) = queue::priority::find_prism(h) {
+            let heap = vector::find_prism(queue::priority::heap(prism)).unwrap();
+            Iter::Seq(Box::new(vector::iter::Iter::new(heap, false)))
+        } else if h.is_sequential() {
+            panic!("{} elements can't be borrowed, iterate over it by value.", h.type_name())
+        } else {
+            panic!("Can't iterate over a {}.", h.type_name())
+        }
+    }
+    pub fn iter_kv(&self) -> map::iter::IterKv<'_> {
+        let h = self.handle();
+        match map::find_prism(h) {
+            Some(prism) => map::iter::IterKv::new(prism),
+            None => panic!("Can't iterate over entries of a {}.", h.type_name()),
+        }
+    }
+}
+/// Owning iterator, yielding aliases of the elements of a collection.
+/// Computed sequences yield fresh elements from nth_out, and eductions
+/// the elements of a vector they pour into.
+pub struct ValueIter {
+    coll: Value,
+    elems: Elems,
+}
+enum Elems {
+    Lent(Iter<'static>),
+    Out(u32, u32),
+}
 impl Iterator for ValueIter {
     type Item = Value;
     fn next(&mut self) -> Option<Value> {
-        todo!()
+        match self.elems {
+            Elems::Lent(ref mut i) => i.next().map(|x| x.split_out()),
+            Elems::Out(ref mut idx, count) => {
+                if *idx == count {
+                    return None
+                }
+                *idx += 1;
+                Some(self.coll.nth_out(*idx - 1))
+            },
+        }
     }
 }
 impl IntoIterator for Value {
     type Item = Value;
     type IntoIter = ValueIter;
     fn into_iter(self) -> Self::IntoIter {
-        todo!()
+        use transduce::eduction;
+        let h = self.handle();
+        can_iterate(h);
+        let coll = match eduction::find_prism(h) {
+            Some(prism) => {
+                let v = eduction::materialize(prism).value();
+                drop(self);
+                v
+            },
+            None => self,
+        };
+        let h = coll.handle();
+        let lends = h.is_nil() || !h.is_sequential() || vector::is_vector(h) || list::is_list(h) ||
+            queue::is_queue(h) || queue::priority::is_priority(h);
+        if !lends {
+            let count = h.count();
+            return ValueIter { coll, elems: Elems::Out(0, count) }
+        }
+        // Elements live in segments held by coll, which moves along with the iterator.
+        let elems = unsafe { mem::transmute::<Iter, Iter<'static>>(coll.iter()) };
+        ValueIter { coll, elems: Elems::Lent(elems) }
     }
 }
-/*
- * Value.iter()
- * Value.keys(), Value.values(), into_keys, into_values
- * Value.iter_kv()
 impl<'a> IntoIterator for &'a Value {
     type Item = &'a Value;
     type IntoIter = Iter<'a>;
@@ -271,7 +446,6 @@ impl<'a> IntoIterator for &'a Value {
         self.iter()
     }
 }
-*/
 
 //struct MapValue {}
 //struct SetValue {}
@@ -9,6 +9,7 @@ use Value;
 use memory::segment::{self, Usage};
 use memory::schedule::{self, Schedule};
 use vector;
+use map;
 
 use std::panic;
 use std::thread;
@@ -23,6 +24,9 @@ pub enum Op {
     Pop,
     Split,
     Drop,
+    NewMap(i32),
+    Assoc {key: i32, val: i32},
+    Dissoc(i32),
 }
 
 pub fn new_with(size: i32) -> Value {
@@ -32,11 +36,22 @@ pub fn new_with(size: i32) -> Value {
     }
     v
 }
+pub fn new_map_with(size: i32) -> Value {
+    let mut m = map::new_value();
+    for i in 0..size {
+        m = m.assoc(i.into(), i.into());
+    }
+    m
+}
 pub fn apply_op(machine: &mut Vec<Value>, op: Op) {
     if let Op::New(size) = op {
         machine.push(new_with(size));
         return;
     }
+    if let Op::NewMap(size) = op {
+        machine.push(new_map_with(size));
+        return;
+    }
     if let Some(c) = machine.pop() {
         match op {
             Op::Conj(elem) => { machine.push(c.conj(elem.into())); }
@@ -48,8 +63,12 @@ pub fn apply_op(machine: &mut Vec<Value>, op: Op) {
                 machine.push(c.split_out());
                 machine.push(c);
             }
+            Op::Assoc { key, val } => {
+                machine.push(c.assoc(key.into(), val.into()));
+            }
+            Op::Dissoc(key) => { machine.push(c.dissoc(&key.into())); }
             Op::Drop => { }  // drops c
-            Op::New(_) => { } // dead code
+            Op::New(_) | Op::NewMap(_) => { } // dead code
         }
     }
 }
@@ -106,7 +125,10 @@ pub fn stepper(mut machine: Vec<Value>, ops: Vec<Op>, info: schedule::Info)
 }
 
 use std::collections::VecDeque;
-pub fn explore_schedules(ops: Ops, preempt_limit: u32) {
+/// Runs the ops under every schedule with up to preempt_limit preemptions,
+/// checking each gives the same output and frees every segment it made.
+/// Returns the segment usage of each schedule, see table.
+pub fn explore_schedules(ops: Ops, preempt_limit: u32) -> Vec<(Schedule, Vec<Usage>)> {
     let mut record: Vec<(Schedule, Vec<Usage>)> = vec![];
     let (orig_out, scheds, orig_usage) = run_schedule(ops.clone(), Schedule::empty());
     record.push((Schedule::empty(), orig_usage));
@@ -145,8 +167,7 @@ pub fn explore_schedules(ops: Ops, preempt_limit: u32) {
     println!("Limit: {}, Schedules tried: {}", preempt_limit, schedule_count);
     println!("Schedules: {:?}", schedule_counts);
     println!("Output: {}", orig_out);
-    use std::fs;
-    fs::write("table.csv", table(&record)).unwrap();
+    record
 }
 
 pub fn table(record: &Vec<(Schedule, Vec<Usage>)>) -> String {
@@ -171,4 +192,17 @@ pub fn table(record: &Vec<(Schedule, Vec<Usage>)>) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+
+    #[test]
+    fn vectors() {
+        explore_schedules(Ops(vec![Op::New(17)],
+                              vec![Op::Conj(7), Op::Split, Op::Set {index: 3, elem: 11}],
+                              vec![Op::Set {index: 16, elem: 22}, Op::Drop]), 2);
+    }
+    #[test]
+    fn maps() {
+        explore_schedules(Ops(vec![Op::NewMap(40)],
+                              vec![Op::Assoc {key: 3, val: 11}, Op::Split, Op::Dissoc(7)],
+                              vec![Op::Dissoc(39), Op::Assoc {key: 40, val: 22}]), 2);
+    }
 }
@@ -5,139 +5,139 @@
 // By using this software in any fashion, you are agreeing to be bound by the terms of this license.
 // You must not remove this notice, or any other, from this software.
 
-//use super::*;
-//use super::tear_down::{NodeRecord, NodeRecordStack, BLANK};
-//use transduce::{ingest, last_call, Process};
+use std::marker::PhantomData;
+use super::*;
+use super::tear_down::{NodeRecord, BLANK};
+use super::reduce::child_record;
 
-pub struct Iter {
-    pub x: usize,
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Stage {
+    Root(u32),
+    Tree,
+    Tail,
 }
-/*
-pub fn iter(prism: AnchoredLine, process_stack: &mut [Box<Process>]) {
 
-    unimplemented!()
+// Walks the leaves of the tree with an explicit stack of records,
+// yielding each element of a leaf before moving to the next.
+// Lists store their elements back to front, so they walk in reverse.
+pub struct Iter<'a> {
+    root: AnchoredLine,
+    count: u32,
+    tailoff: u32,
+    stages: [Stage; 2],
+    stage_count: u32,
+    next_stage: u32,
+    stack: [NodeRecord; 8],
+    depth: u32,
+    leaf: AnchoredLine,
+    at: u32,
+    len: u32,
+    reverse: bool,
+    life: PhantomData<&'a Value>,
 }
 
-pub fn reduce(prism: AnchoredLine, process_stack: &mut [Box<Process>]) -> Value {
-    let guide = Guide::hydrate(prism);
-    let count = guide.count;
-    if count <= TAIL_CAP {
-        for i in 0..count {
-            let x = guide.root.offset(i as i32).line().star() as *const Value;
-            let y = unsafe { &* x };
-            if let Some(ret) = ingest(process_stack, y) {
-                return ret;
-            }
-        }
-        last_call(process_stack)
-    } else {
-        let tailoff = tailoff(guide.count);
-        if tailoff == TAIL_CAP {
-            for i in 0..TAIL_CAP {
-                let x = guide.root.offset(i as i32).line().star() as *const Value;
-                let y = unsafe { &* x };
-                if let Some(ret) = ingest(process_stack, y) {
-                    return ret;
-                }
-            }
-            let tail = guide.root[-1].segment();
-            for i in 0..(count - tailoff) {
-                let x = tail.line_at(i).line().star() as *const Value;
-                let y = unsafe { &* x };
-                if let Some(ret) = ingest(process_stack, y) {
-                    return ret;
-                }
-            }
-            last_call(process_stack)
+impl<'a> Iter<'a> {
+    pub fn new(prism: AnchoredLine, reverse: bool) -> Iter<'a> {
+        let guide = Guide::hydrate(prism);
+        let count = guide.count;
+        let tailoff = if count == 0 { 0 } else { tailoff(count) };
+        let (mut stages, stage_count) = if count == 0 {
+            ([Stage::Tail; 2], 0)
+        } else if count <= TAIL_CAP {
+            ([Stage::Root(count), Stage::Tail], 1)
+        } else if tailoff == TAIL_CAP {
+            ([Stage::Root(TAIL_CAP), Stage::Tail], 2)
         } else {
-            reduce_tree(guide, tailoff, process_stack)
+            ([Stage::Tree, Stage::Tail], 2)
+        };
+        if reverse && stage_count == 2 {
+            stages.swap(0, 1);
         }
+        Iter { root: guide.root, count, tailoff, stages, stage_count, next_stage: 0,
+            stack: [BLANK; 8], depth: 0, leaf: guide.root, at: 0, len: 0, reverse,
+            life: PhantomData }
     }
-}
 
-pub fn reduce_tree(guide: Guide, tailoff: u32, process_stack: &mut [Box<Process>]) -> Value {
-    let last_tree_index = tailoff - 1;
-    let stack_space = [BLANK; 8];
-    let mut stack = NodeRecordStack::new(stack_space.as_ptr());
-    stack.push(NodeRecord {
-        first_child: guide.root,
-        child_count: root_content_count(tailoff),
-        height: digit_count(last_tree_index),
-        on_boundary: true,
-        current_child: None,
-    });
-    while !stack.is_empty() {
-        if let Some(ret) = step(&mut stack, process_stack, last_tree_index) {
-            return ret;
+    fn next_leaf(&mut self) -> bool {
+        let last_tree_index = self.tailoff - 1;
+        while self.depth != 0 {
+            let top = &mut self.stack[(self.depth - 1) as usize];
+            let next = match top.current_child {
+                Some(i) => { i + 1 },
+                None    => { 0 },
+            };
+            let cap = top.child_count;
+            if next == cap {
+                self.depth -= 1;
+                continue;
+            }
+            top.current_child = Some(next);
+            let idx = if self.reverse { cap - next - 1 } else { next };
+            let s = top.first_child[idx as i32].segment();
+            if top.height == 2 {
+                self.set_leaf(s.line_at(0), TAIL_CAP);
+                return true;
+            }
+            let r = child_record(top, last_tree_index, s, idx == cap - 1);
+            self.stack[self.depth as usize] = r;
+            self.depth += 1;
         }
+        false
     }
-    let tail = guide.root[-1].segment();
-    for i in 0..(guide.count - tailoff) {
-        let x = tail.line_at(i).line().star() as *const Value;
-        let y = unsafe { &* x };
-        if let Some(ret) = ingest(process_stack, y) {
-            return ret;
-        }
+
+    fn set_leaf(&mut self, leaf: AnchoredLine, len: u32) {
+        self.leaf = leaf;
+        self.len = len;
+        self.at = 0;
     }
-    last_call(process_stack)
-}
 
-pub fn base_case(node: &NodeRecord, process_stack: &mut [Box<Process>]) -> Option<Value> {
-    for i in 0..node.child_count {
-        let a_tail = node.first_child[i as i32].segment();
-        for j in 0..TAIL_CAP {
-            let x = a_tail.line_at(j).line().star() as *const Value;
-            let y = unsafe { &* x };
-            if let Some(ret) = ingest(process_stack, y) {
-                return Some(ret);
+    fn next_run(&mut self) -> bool {
+        loop {
+            if self.depth != 0 && self.next_leaf() {
+                return true;
+            }
+            if self.next_stage == self.stage_count {
+                return false;
+            }
+            let stage = self.stages[self.next_stage as usize];
+            self.next_stage += 1;
+            match stage {
+                Stage::Root(len) => {
+                    let root = self.root;
+                    self.set_leaf(root, len);
+                    return true;
+                },
+                Stage::Tail => {
+                    let tail = self.root[-1].segment();
+                    let len = self.count - self.tailoff;
+                    self.set_leaf(tail.line_at(0), len);
+                    return true;
+                },
+                Stage::Tree => {
+                    let last_tree_index = self.tailoff - 1;
+                    self.stack[0] = NodeRecord {
+                        first_child: self.root,
+                        child_count: root_content_count(self.tailoff),
+                        height: digit_count(last_tree_index),
+                        on_boundary: true,
+                        current_child: None,
+                    };
+                    self.depth = 1;
+                },
             }
         }
     }
-    None
 }
 
-pub fn step(stack: &mut NodeRecordStack, process_stack: &mut [Box<Process>],
-            last_tree_index: u32) -> Option<Value> {
-    let top = stack.top();
-    if top.height == 2 {
-        if let Some(ret) = base_case(top, process_stack) {
-            return Some(ret);
+impl<'a> Iterator for Iter<'a> {
+    type Item = &'a Value;
+    fn next(&mut self) -> Option<&'a Value> {
+        if self.at == self.len && !self.next_run() {
+            return None;
         }
-        stack.pop();
-        return None;
-    }
-    let next = match top.current_child {
-        Some(i) => { i + 1 },
-        None    => { 0 },
-    };
-    let cap = top.child_count;
-    if next < cap {
-        let idx = next;
-        let s = top.first_child[idx as i32].segment();
-        let r = child_record(top, last_tree_index, s, idx == cap - 1);
-        top.current_child = Some(next);
-        stack.push(r);
-    } else {
-        stack.pop();
+        let i = if self.reverse { self.len - self.at - 1 } else { self.at };
+        self.at += 1;
+        let x = self.leaf.offset(i as i32).line().star() as *const Value;
+        Some(unsafe { &* x })
     }
-    None
 }
-
-pub fn child_record(top: &mut NodeRecord, last_tree_index: u32, s: Segment, last_child: bool)
-                    -> NodeRecord {
-    let mut r = NodeRecord {
-        first_child: s.line_at(0),
-        child_count: TAIL_CAP,
-        height: top.height - 1,
-        on_boundary: false,
-        current_child: None,
-    };
-    if top.on_boundary && last_child {
-        let child_idx = (last_tree_index >> (BITS * (r.height - 1))) & MASK;
-        r.child_count = child_idx + 1;
-        r.on_boundary = true;
-    }
-    r
-}
-
-*/
@@ -25,6 +25,8 @@ pub mod eq;
 pub mod tear_down;
 pub mod reduce;
 pub mod iter;
+// The harness runs threads sharing values, which needs atomic alias counts.
+#[cfg(not(feature = "anchor_non_atomic"))]
 pub mod harness;
 pub mod util;
 use self::util::*;
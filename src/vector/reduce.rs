@@ -151,3 +151,23 @@ impl Process for Conjer {
     }
 }
 
+
+// Feeds elements [start, end) into the process stack, without the last call.
+// Elements of a leaf (or the tail) are contiguous, so each leaf is found once.
+pub fn reduce_span(prism: AnchoredLine, start: u32, end: u32,
+                   process_stack: &mut [Box<dyn Process>]) -> Option<Value> {
+    let mut i = start;
+    while i < end {
+        let leaf_end = ((i | MASK) + 1).min(end);
+        let first = nth::nth(prism, i);
+        for j in 0..(leaf_end - i) {
+            let x = first.offset(j as i32).line().star() as *const Value;
+            let y = unsafe { &* x };
+            if let Some(ret) = inges(process_stack, y) {
+                return Some(ret);
+            }
+        }
+        i = leaf_end;
+    }
+    None
+}